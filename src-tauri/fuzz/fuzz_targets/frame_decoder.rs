@@ -0,0 +1,25 @@
+#![no_main]
+
+//! 把任意数据按输入首字节决定的步长切片后喂给 `FrameReassembler`，
+//! 模拟设备乱发数据时的蓝牙通知：不得 panic，解出的帧必须能原样重新编码
+
+use app_lib::miwear::packet::{FrameReassembler, MiWearPacket};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&step, data)) = data.split_first() else {
+        return;
+    };
+    let step = usize::from(step).max(1);
+
+    let mut reassembler = FrameReassembler::new();
+    for chunk in data.chunks(step) {
+        reassembler.push(chunk);
+        while let Some(pkt) = reassembler.next_packet() {
+            let raw = pkt.encode();
+            let reparsed = MiWearPacket::parse(&raw).expect("decoded frame must reparse");
+            assert_eq!(reparsed.body, pkt.body);
+        }
+    }
+    assert!(reassembler.buffered() <= data.len());
+});
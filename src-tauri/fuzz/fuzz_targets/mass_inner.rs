@@ -0,0 +1,12 @@
+#![no_main]
+
+//! 对 Mass 数据块解析喂入任意数据（含伪造的 deflate 流）：不得 panic
+
+use app_lib::miwear::device::mass::packet::parse_mass_inner;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(mass) = parse_mass_inner(data, None) {
+        assert_eq!(mass.md5.len(), 16);
+    }
+});
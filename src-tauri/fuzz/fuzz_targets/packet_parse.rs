@@ -0,0 +1,26 @@
+#![no_main]
+
+//! 对 `MiWearPacket::parse` / `parse_all` 喂入任意数据：不得 panic，
+//! 解析出的帧重新编码后必须与输入中的原始字节一致
+
+use app_lib::miwear::packet::{MiWearPacket, HEADER_LEN};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(pkt) = MiWearPacket::parse(data) {
+        let raw = pkt.try_encode().expect("parsed frame must be encodable");
+        assert_eq!(&data[..raw.len()], &raw[..]);
+        let _ = pkt.data_fields();
+    }
+
+    let parsed = MiWearPacket::parse_all(data);
+    let framed: usize = parsed
+        .packets
+        .iter()
+        .map(|pkt| HEADER_LEN + pkt.body.len())
+        .sum();
+    assert_eq!(framed + parsed.skipped_bytes, data.len());
+    for err in &parsed.errors {
+        assert!(err.offset < data.len());
+    }
+});
@@ -32,8 +32,13 @@ pub async fn init(app: &AppHandle) -> Result<()> {
         let json = tokio::fs::read_to_string(&path)
             .await
             .with_context(|| format!("read accounts: {}", path.display()))?;
-        let map: HashMap<String, Vec<Account>> = serde_json::from_str(&json)?;
+        let mut map: HashMap<String, Vec<Account>> = serde_json::from_str(&json)?;
+        let migrated = restore_secrets(&mut map);
         *ACCOUNTS.write() = map;
+        if migrated {
+            log::info!("[Account] Moved plaintext account tokens into the secrets store");
+            persist(&ACCOUNTS.read())?;
+        }
     } else {
         persist(&ACCOUNTS.read())?;
     }
@@ -92,7 +97,7 @@ fn persist(data: &HashMap<String, Vec<Account>>) -> Result<()> {
         .get()
         .expect("ACCOUNTS_PATH not set; did you forget to call init()?");
 
-    let json = serde_json::to_string_pretty(data)?;
+    let json = serde_json::to_string_pretty(&sanitized(data))?;
 
     if let Some(dir) = path.parent() {
         std::fs::create_dir_all(dir)?;
@@ -102,6 +107,68 @@ fn persist(data: &HashMap<String, Vec<Account>>) -> Result<()> {
     Ok(())
 }
 
+/// secrets 解锁后填回账号 token，并迁移锁定期间写入的明文
+pub fn reload_secrets() {
+    let snapshot = {
+        let mut guard = ACCOUNTS.write();
+        restore_secrets(&mut guard);
+        guard.clone()
+    };
+    if let Err(e) = persist(&snapshot) {
+        log::error!("failed to save accounts: {e:#}");
+    }
+}
+
+/// 写盘用的副本：`Account.data`（token 等）转存到 secrets
+///
+/// secrets 未解锁时保留明文，解锁后再迁移
+fn sanitized(data: &HashMap<String, Vec<Account>>) -> HashMap<String, Vec<Account>> {
+    let mut out = data.clone();
+    if !crate::secrets::is_ready() {
+        return out;
+    }
+
+    let stored = crate::secrets::write(|secrets| {
+        secrets.retain(|name, _| !name.starts_with("account/"));
+        for (provider, accounts) in data {
+            for account in accounts {
+                if let Ok(json) = serde_json::to_string(&account.data) {
+                    secrets.insert(crate::secrets::account_key(provider, &account.id), json);
+                }
+            }
+        }
+    });
+
+    match stored {
+        Ok(()) => out
+            .values_mut()
+            .flatten()
+            .for_each(|account| account.data.clear()),
+        Err(e) => log::error!("[Account] Failed to store account tokens: {e:#}"),
+    }
+    out
+}
+
+/// 从 secrets 填回 `Account.data`，返回是否还有待迁移的明文
+fn restore_secrets(data: &mut HashMap<String, Vec<Account>>) -> bool {
+    let mut has_plaintext = false;
+    for (provider, accounts) in data.iter_mut() {
+        for account in accounts {
+            if !account.data.is_empty() {
+                has_plaintext = true;
+                continue;
+            }
+            if let Some(stored) = crate::secrets::get_json(&crate::secrets::account_key(
+                provider,
+                &account.id,
+            )) {
+                account.data = stored;
+            }
+        }
+    }
+    has_plaintext && crate::secrets::is_ready()
+}
+
 fn account_file_path(app: &AppHandle) -> Result<PathBuf> {
     let dir = app
         .path()
@@ -111,6 +111,24 @@ pub struct MiAccountToken {
     pub c_user_id: String,
 }
 
+const MI_ACCOUNT_TOKEN_SECRET: &str = "mi_account/token";
+
+impl MiAccountToken {
+    /// Keep the token in the encrypted secrets store so later calls can reuse it.
+    pub fn save(&self) -> Result<()> {
+        crate::secrets::set_json(MI_ACCOUNT_TOKEN_SECRET, self)
+    }
+
+    /// Last saved token, if any.
+    pub fn load() -> Option<Self> {
+        crate::secrets::get_json(MI_ACCOUNT_TOKEN_SECRET)
+    }
+
+    pub fn clear() -> Result<()> {
+        crate::secrets::remove(MI_ACCOUNT_TOKEN_SECRET)
+    }
+}
+
 /// Remove Xiaomi’s magic prefix from JSON payloads.
 fn strip_prefix(contents: &str) -> &str {
     const PREFIX: &str = "&&&START&&&";
@@ -217,23 +217,12 @@ impl BTDevice {
                                 let addr_cloned = device_addr_for_clear_check.clone();
 
                                 tauri::async_runtime::spawn(async move {
-                                    let mut should_clear = false;
-                                    let global_dev_guard =
-                                        crate::miwear::CONNECTED_DEVICE.read().await;
-                                    if let Some(global_dev_arc) =
-                                        global_dev_guard.as_ref().cloned()
+                                    if let Some(device) =
+                                        crate::miwear::get_device(Some(&addr_cloned)).await
                                     {
-                                        let global_dev_state =
-                                            global_dev_arc.state.read().await;
-                                        if global_dev_state.addr == addr_cloned {
-                                            should_clear = true;
-                                        }
-                                    }
-                                    drop(global_dev_guard);
-                                    if should_clear {
-                                        crate::miwear::set_connected_device(None).await;
+                                        crate::miwear::unregister_device(&device).await;
                                         log::error!(
-                                            "SPP connection error for device {}, cleared CONNECTED_DEVICE. Error: {}",
+                                            "SPP connection error for device {}, removed from device registry. Error: {}",
                                             addr_cloned, e_cloned
                                         );
                                     }
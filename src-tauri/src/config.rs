@@ -2,16 +2,43 @@ use anyhow::{Context, Result};
 use once_cell::sync::{Lazy, OnceCell};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
 use tauri::{AppHandle, Manager};
 
-use crate::{bt::device::ConnectType, miwear::device::MiWearState};
+use crate::{
+    bt::device::ConnectType,
+    miwear::{command_pool::SchedulerConfig, device::MiWearState},
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct AppConfig {
     pub connect_type: ConnectType,
     pub fragments_send_delay: u32,
+    pub adaptive_pacing: bool,
+    pub retransmit_rto_ms: u32,
+    pub retransmit_max_retries: u32,
+    pub mass_compression: bool,
+    pub auto_reconnect: bool,
+    pub reconnect_max_attempts: u32,
+    pub reconnect_initial_delay_ms: u32,
+    pub reconnect_max_delay_ms: u32,
+    pub reconnect_resume_transfers: bool,
+    /// 重连时优先复用上次的会话密钥，被设备拒绝后再走完整认证（默认关闭）
+    pub fast_reauth: bool,
+    pub session_cache_ttl_secs: u64,
+    /// 连接时自动开始 MiWear 链路层抓包
+    pub protocol_capture: bool,
+    /// 连接时录制会话文件，用于离线回放复现问题
+    pub session_recording: bool,
+    /// 把传感器 / 科研文件通道收到的数据块保存到本地
+    pub collect_file_streams: bool,
+    /// CommandPool 通道调度参数，已配对设备可单独覆盖
+    pub command_scheduler: SchedulerConfig,
     pub official_community_provider_cdn: String,
     pub plugin_dir: String,
     pub disabled_plugins: Vec<String>,
@@ -34,6 +61,22 @@ impl Default for AppConfig {
             #[cfg(not(target_os = "ios"))]
             connect_type: ConnectType::SPP,
             fragments_send_delay: 5,
+            adaptive_pacing: true,
+            retransmit_rto_ms: 800,
+            retransmit_max_retries: 4,
+            // Deflate 模式的取值没有抓包佐证，默认不向设备提出压缩
+            mass_compression: false,
+            auto_reconnect: true,
+            reconnect_max_attempts: 6,
+            reconnect_initial_delay_ms: 1000,
+            reconnect_max_delay_ms: 30000,
+            reconnect_resume_transfers: true,
+            fast_reauth: false,
+            session_cache_ttl_secs: 24 * 60 * 60,
+            protocol_capture: false,
+            session_recording: false,
+            collect_file_streams: false,
+            command_scheduler: SchedulerConfig::default(),
             official_community_provider_cdn: "ghfast".to_string(),
             plugin_dir: {
                 let base = crate::APP_HANDLE
@@ -53,6 +96,8 @@ impl Default for AppConfig {
 
 static CONFIG: Lazy<RwLock<AppConfig>> = Lazy::new(|| RwLock::new(AppConfig::default()));
 static CONFIG_PATH: OnceCell<PathBuf> = OnceCell::new();
+/// secrets 锁定期间每次写配置都会走到 sanitized，只提示一次
+static LOCKED_WARNED: AtomicBool = AtomicBool::new(false);
 
 pub async fn init(app: &AppHandle) -> Result<()> {
     let path = config_file_path(app)?;
@@ -66,8 +111,13 @@ pub async fn init(app: &AppHandle) -> Result<()> {
         let json = tokio::fs::read_to_string(&path)
             .await
             .with_context(|| format!("read config: {}", path.display()))?;
-        let cfg: AppConfig = serde_json::from_str(&json)?;
+        let mut cfg: AppConfig = serde_json::from_str(&json)?;
+        let migrated = restore_secrets(&mut cfg);
         *CONFIG.write() = cfg;
+        if migrated {
+            log::info!("[Config] Moved plaintext auth keys into the secrets store");
+            persist(&CONFIG.read())?;
+        }
     } else {
         persist(&CONFIG.read())?;
     }
@@ -76,8 +126,7 @@ pub async fn init(app: &AppHandle) -> Result<()> {
 }
 
 pub async fn save(app: &AppHandle) -> Result<()> {
-    let guard = CONFIG.read();
-    let data = serde_json::to_string_pretty(&*guard)?;
+    let data = serde_json::to_string_pretty(&sanitized(&CONFIG.read()))?;
 
     let path = config_file_path(app)?;
     if let Some(dir) = path.parent() {
@@ -127,19 +176,110 @@ pub fn merge(dst: &mut serde_json::Value, patch: &serde_json::Value) {
 fn persist(cfg: &AppConfig) -> Result<()> {
     let path = CONFIG_PATH
         .get()
-        .expect("CONFIG_PATH not set; did you forget to call init()?");
+        .context("CONFIG_PATH not set; did you forget to call init()?")?;
 
-    let data = serde_json::to_string_pretty(cfg)?;
+    let data = serde_json::to_string_pretty(&sanitized(cfg))?;
 
     if let Some(dir) = path.parent() {
         std::fs::create_dir_all(dir)?;
     }
 
-    std::fs::write(path, data).with_context(|| format!("write config: {}", path.display()))?;
+    crate::tools::write_file_atomic(path, data)
+        .with_context(|| format!("write config: {}", path.display()))?;
 
     Ok(())
 }
 
+/// secrets 解锁后填回 authkey，并迁移锁定期间写入的明文 authkey
+pub fn reload_secrets() {
+    let snapshot = {
+        let mut guard = CONFIG.write();
+        restore_secrets(&mut guard);
+        guard.clone()
+    };
+    if let Err(e) = persist(&snapshot) {
+        log::error!("failed to save config: {e:#}");
+    }
+}
+
+/// 写盘用的副本：authkey 转存到 secrets，会话密钥不落盘
+///
+/// secrets 未解锁时保留明文 authkey，避免丢失配对信息，解锁后再迁移
+fn sanitized(cfg: &AppConfig) -> AppConfig {
+    let mut out = cfg.clone();
+    if !crate::secrets::is_ready() {
+        if !LOCKED_WARNED.swap(true, Ordering::Relaxed) {
+            log::warn!(
+                "[Config] Secrets store is locked, auth keys stay in config.json until unlocked"
+            );
+        }
+        for dev in out
+            .paired_devices
+            .iter_mut()
+            .chain(out.current_device.iter_mut())
+        {
+            dev.sec_keys = None;
+        }
+        return out;
+    }
+
+    let paired: HashSet<String> = cfg.paired_devices.iter().map(|d| d.addr.clone()).collect();
+    let stored = crate::secrets::write(|secrets| {
+        for dev in cfg.paired_devices.iter().chain(cfg.current_device.iter()) {
+            if !dev.authkey.is_empty() {
+                secrets.insert(
+                    crate::secrets::device_key(&dev.addr, "authkey"),
+                    dev.authkey.clone(),
+                );
+            }
+        }
+        // 已移除的设备的密钥一并清理
+        secrets.retain(|name, _| match name.strip_prefix("device/") {
+            Some(rest) => rest
+                .rsplit_once('/')
+                .is_some_and(|(addr, _)| paired.contains(addr)),
+            None => true,
+        });
+    });
+
+    match stored {
+        Ok(()) => {
+            for dev in out
+                .paired_devices
+                .iter_mut()
+                .chain(out.current_device.iter_mut())
+            {
+                dev.authkey.clear();
+                dev.sec_keys = None;
+            }
+        }
+        Err(e) => log::error!("[Config] Failed to store auth keys: {e:#}"),
+    }
+    out
+}
+
+/// 从 secrets 填回 authkey，返回配置中是否还有待迁移的明文 authkey
+fn restore_secrets(cfg: &mut AppConfig) -> bool {
+    let mut has_plaintext = false;
+    for dev in cfg
+        .paired_devices
+        .iter_mut()
+        .chain(cfg.current_device.iter_mut())
+    {
+        if dev.authkey.is_empty() {
+            if let Some(key) =
+                crate::secrets::get(&crate::secrets::device_key(&dev.addr, "authkey"))
+            {
+                dev.authkey = key;
+            }
+        } else {
+            has_plaintext = true;
+        }
+        dev.sec_keys = None;
+    }
+    has_plaintext && crate::secrets::is_ready()
+}
+
 fn config_file_path(app: &AppHandle) -> Result<PathBuf> {
     let dir = app
         .path()
@@ -0,0 +1,397 @@
+//! 运动健康数据
+//!
+//! 解析手表在 `Channel::FileFitness` 上发来的记录文件（由 `miwear::device::download` 保存），
+//! 并导出：运动记录 → GPX / TCX / FIT，每日汇总（步数、心率、睡眠）→ CSV。
+//!
+//! 文件格式见 [`parser`]；导出器只依赖这里的模型，格式细节的修正不会影响导出。
+
+use std::{collections::BTreeMap, path::Path, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::miwear::{device::download, packet::Channel};
+
+pub mod daily;
+pub mod fit;
+pub mod gpx;
+pub mod parser;
+pub mod tcx;
+
+/// 运动类型，取自运动记录文件的 subtype
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sport {
+    Running,
+    Walking,
+    Hiking,
+    Cycling,
+    Swimming,
+    Other(u8),
+}
+
+impl Sport {
+    pub fn from_subtype(subtype: u8) -> Self {
+        match subtype {
+            1 | 5 | 24 => Sport::Running,
+            2 | 4 => Sport::Walking,
+            3 => Sport::Hiking,
+            6 | 7 => Sport::Cycling,
+            9 | 10 => Sport::Swimming,
+            other => Sport::Other(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackPoint {
+    /// unix 时间戳（秒）
+    pub time: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// 米
+    pub altitude: Option<f64>,
+    /// 米/秒
+    pub speed: Option<f32>,
+    pub heart_rate: Option<u8>,
+}
+
+/// 运动记录文件中的汇总部分
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkoutSummary {
+    pub start: i64,
+    pub end: i64,
+    /// 有效运动时长（秒），不含暂停
+    pub duration: u32,
+    /// 米
+    pub distance: u32,
+    /// 千卡
+    pub calories: u16,
+    pub avg_heart_rate: Option<u8>,
+    pub max_heart_rate: Option<u8>,
+    pub min_heart_rate: Option<u8>,
+    pub steps: Option<u32>,
+}
+
+/// 一次运动：汇总文件与 GPS 轨迹文件按起始时间与运动类型合并
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Activity {
+    /// "<起始时间戳>-<subtype>"
+    pub id: String,
+    pub sport: Sport,
+    pub start: i64,
+    /// 相对 UTC 的偏移（秒）
+    pub utc_offset: i32,
+    pub summary: Option<WorkoutSummary>,
+    pub track: Vec<TrackPoint>,
+}
+
+impl Activity {
+    pub fn end(&self) -> i64 {
+        self.summary
+            .as_ref()
+            .map(|s| s.end)
+            .or_else(|| self.track.last().map(|p| p.time))
+            .unwrap_or(self.start)
+    }
+
+    /// 总距离（米），优先取手表给出的汇总值
+    pub fn distance(&self) -> f64 {
+        match &self.summary {
+            Some(s) if s.distance > 0 => s.distance as f64,
+            _ => cumulative_distance(&self.track)
+                .last()
+                .copied()
+                .unwrap_or(0.0),
+        }
+    }
+
+    pub fn duration(&self) -> u32 {
+        match &self.summary {
+            Some(s) if s.duration > 0 => s.duration,
+            _ => (self.end() - self.start).max(0) as u32,
+        }
+    }
+
+    pub fn avg_heart_rate(&self) -> Option<u8> {
+        self.summary
+            .as_ref()
+            .and_then(|s| s.avg_heart_rate)
+            .or_else(|| {
+                let hrs: Vec<u32> = self
+                    .track
+                    .iter()
+                    .filter_map(|p| p.heart_rate.map(u32::from))
+                    .collect();
+                (!hrs.is_empty()).then(|| (hrs.iter().sum::<u32>() / hrs.len() as u32) as u8)
+            })
+    }
+
+    pub fn max_heart_rate(&self) -> Option<u8> {
+        self.summary
+            .as_ref()
+            .and_then(|s| s.max_heart_rate)
+            .or_else(|| self.track.iter().filter_map(|p| p.heart_rate).max())
+    }
+
+    pub fn calories(&self) -> Option<u16> {
+        self.summary.as_ref().map(|s| s.calories)
+    }
+}
+
+/// 列表用的运动概要，不含轨迹
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityInfo {
+    pub id: String,
+    pub sport: Sport,
+    pub start: i64,
+    pub end: i64,
+    pub duration: u32,
+    pub distance: f64,
+    pub calories: Option<u16>,
+    pub avg_heart_rate: Option<u8>,
+    pub track_points: usize,
+}
+
+impl From<&Activity> for ActivityInfo {
+    fn from(activity: &Activity) -> Self {
+        Self {
+            id: activity.id.clone(),
+            sport: activity.sport,
+            start: activity.start,
+            end: activity.end(),
+            duration: activity.duration(),
+            distance: activity.distance(),
+            calories: activity.calories(),
+            avg_heart_rate: activity.avg_heart_rate(),
+            track_points: activity.track.len(),
+        }
+    }
+}
+
+/// 日常数据中的一分钟
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinuteSample {
+    pub time: i64,
+    pub utc_offset: i32,
+    pub steps: u16,
+    pub calories: u8,
+    pub heart_rate: Option<u8>,
+    /// 血氧饱和度（%）
+    pub spo2: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SleepStage {
+    Awake,
+    Light,
+    Deep,
+    Rem,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SleepSegment {
+    pub stage: SleepStage,
+    pub start: i64,
+    pub end: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SleepSession {
+    pub bed_time: i64,
+    pub wake_time: i64,
+    pub utc_offset: i32,
+    pub segments: Vec<SleepSegment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Gpx,
+    Tcx,
+    Fit,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Gpx => "gpx",
+            ExportFormat::Tcx => "tcx",
+            ExportFormat::Fit => "fit",
+        }
+    }
+}
+
+/// 所有已下载记录文件解析合并后的结果
+#[derive(Debug, Default)]
+pub struct FitnessData {
+    pub activities: Vec<Activity>,
+    pub samples: Vec<MinuteSample>,
+    pub sleep: Vec<SleepSession>,
+}
+
+impl FitnessData {
+    /// 合并一个已解析的记录文件
+    pub fn add(&mut self, file: parser::FitnessFile) {
+        match file.content {
+            parser::FitnessContent::WorkoutSummary(summary) => {
+                self.activity_mut(&file.id).summary = Some(summary);
+            }
+            parser::FitnessContent::WorkoutTrack(points) => {
+                let activity = self.activity_mut(&file.id);
+                activity.track.extend(points);
+                activity.track.sort_by_key(|p| p.time);
+                activity.track.dedup_by_key(|p| p.time);
+            }
+            parser::FitnessContent::DailyDetails(samples) => {
+                self.samples.extend(samples);
+            }
+            parser::FitnessContent::Sleep(session) => {
+                self.sleep.push(session);
+            }
+        }
+    }
+
+    fn activity_mut(&mut self, id: &parser::FileId) -> &mut Activity {
+        let key = id.activity_id();
+        match self.activities.iter().position(|a| a.id == key) {
+            Some(pos) => &mut self.activities[pos],
+            None => {
+                self.activities.push(Activity {
+                    id: key,
+                    sport: Sport::from_subtype(id.subtype),
+                    start: id.timestamp as i64,
+                    utc_offset: id.utc_offset(),
+                    summary: None,
+                    track: Vec::new(),
+                });
+                self.activities.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// 去重并按时间排序；同一段数据可能被手表重复发送
+    fn normalize(&mut self) {
+        self.activities.sort_by_key(|a| a.start);
+
+        let samples: BTreeMap<i64, MinuteSample> =
+            self.samples.drain(..).map(|s| (s.time, s)).collect();
+        self.samples = samples.into_values().collect();
+
+        self.sleep.sort_by_key(|s| s.bed_time);
+        self.sleep.dedup_by_key(|s| s.bed_time);
+    }
+
+    pub fn activity(&self, id: &str) -> Option<&Activity> {
+        self.activities.iter().find(|a| a.id == id)
+    }
+
+    pub fn daily_summaries(&self) -> Vec<daily::DailySummary> {
+        daily::summarize(&self.samples, &self.sleep)
+    }
+}
+
+/// 上次加载时的下载 id 列表与解析结果
+static CACHE: Lazy<Mutex<Option<(Vec<String>, Arc<FitnessData>)>>> = Lazy::new(|| Mutex::new(None));
+
+/// 从下载目录加载全部 FileFitness 文件，无法解析的文件记日志后跳过
+///
+/// 下载列表没有变化时直接返回上次的结果；会读文件，异步上下文中需放到阻塞线程调用
+pub fn load() -> Arc<FitnessData> {
+    let channel = format!("{:?}", Channel::FileFitness);
+    let mut ids: Vec<String> = download::list()
+        .into_iter()
+        .filter(|f| f.channel == channel)
+        .map(|f| f.id)
+        .collect();
+    ids.sort();
+
+    if let Some((cached, data)) = CACHE.lock().as_ref() {
+        if *cached == ids {
+            return data.clone();
+        }
+    }
+
+    let mut data = FitnessData::default();
+    for id in &ids {
+        let parsed = download::read(id).and_then(|bytes| parser::parse(&bytes));
+        match parsed {
+            Ok(parsed) => data.add(parsed),
+            Err(e) => log::warn!("[Fitness] Skipping download {}: {:#}", id, e),
+        }
+    }
+    data.normalize();
+
+    let data = Arc::new(data);
+    *CACHE.lock() = Some((ids, data.clone()));
+    data
+}
+
+pub fn list_activities() -> Vec<ActivityInfo> {
+    load().activities.iter().map(ActivityInfo::from).collect()
+}
+
+pub fn export_activity(activity: &Activity, format: ExportFormat) -> Result<Vec<u8>> {
+    match format {
+        ExportFormat::Gpx => {
+            if activity.track.is_empty() {
+                bail!("Activity {} has no GPS track", activity.id);
+            }
+            Ok(gpx::write(activity).into_bytes())
+        }
+        ExportFormat::Tcx => Ok(tcx::write(activity).into_bytes()),
+        ExportFormat::Fit => Ok(fit::write(activity)),
+    }
+}
+
+/// 导出单次运动到 path
+pub fn export_activity_to(id: &str, format: ExportFormat, path: &Path) -> Result<()> {
+    let data = load();
+    let activity = data
+        .activity(id)
+        .with_context(|| format!("Activity {} not found", id))?;
+    let bytes = export_activity(activity, format)?;
+    std::fs::write(path, bytes).with_context(|| format!("write export: {}", path.display()))
+}
+
+/// 导出全部每日汇总为 CSV
+pub fn export_daily_csv_to(path: &Path) -> Result<()> {
+    let summaries = load().daily_summaries();
+    let file =
+        std::fs::File::create(path).with_context(|| format!("create csv: {}", path.display()))?;
+    daily::write_csv(file, &summaries)
+}
+
+/// ISO 8601 UTC 时间，GPX / TCX 共用
+pub(crate) fn utc_time(ts: i64) -> String {
+    chrono::DateTime::from_timestamp(ts, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
+
+/// 两点间的大圆距离（米）
+pub fn haversine(a: &TrackPoint, b: &TrackPoint) -> f64 {
+    const EARTH_RADIUS: f64 = 6_371_008.8;
+    let (lat1, lat2) = (a.latitude.to_radians(), b.latitude.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (b.longitude - a.longitude).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+/// 每个轨迹点处的累计距离（米）
+pub fn cumulative_distance(track: &[TrackPoint]) -> Vec<f64> {
+    let mut total = 0.0;
+    let mut out = Vec::with_capacity(track.len());
+    for (i, point) in track.iter().enumerate() {
+        if i > 0 {
+            total += haversine(&track[i - 1], point);
+        }
+        out.push(total);
+    }
+    out
+}
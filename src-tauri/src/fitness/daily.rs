@@ -0,0 +1,105 @@
+//! 按本地日期汇总的步数、心率、睡眠，以及 CSV 导出
+
+use std::{collections::BTreeMap, io::Write};
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate};
+use serde::Serialize;
+
+use super::{MinuteSample, SleepSession, SleepStage};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DailySummary {
+    /// 本地日期 YYYY-MM-DD
+    pub date: String,
+    pub steps: u32,
+    pub calories: u32,
+    pub avg_heart_rate: Option<u8>,
+    pub min_heart_rate: Option<u8>,
+    pub max_heart_rate: Option<u8>,
+    /// 睡眠各阶段分钟数，按醒来的日期计入
+    pub sleep_minutes: u32,
+    pub deep_sleep_minutes: u32,
+    pub light_sleep_minutes: u32,
+    pub rem_sleep_minutes: u32,
+    pub awake_minutes: u32,
+}
+
+#[derive(Default)]
+struct Accumulator {
+    steps: u32,
+    calories: u32,
+    hr_sum: u32,
+    hr_count: u32,
+    hr_min: Option<u8>,
+    hr_max: Option<u8>,
+    deep: u32,
+    light: u32,
+    rem: u32,
+    awake: u32,
+}
+
+fn local_date(ts: i64, utc_offset: i32) -> NaiveDate {
+    DateTime::from_timestamp(ts + utc_offset as i64, 0)
+        .unwrap_or_default()
+        .date_naive()
+}
+
+pub fn summarize(samples: &[MinuteSample], sleep: &[SleepSession]) -> Vec<DailySummary> {
+    let mut days: BTreeMap<NaiveDate, Accumulator> = BTreeMap::new();
+
+    for sample in samples {
+        let day = days
+            .entry(local_date(sample.time, sample.utc_offset))
+            .or_default();
+        day.steps += sample.steps as u32;
+        day.calories += sample.calories as u32;
+        if let Some(hr) = sample.heart_rate {
+            day.hr_sum += hr as u32;
+            day.hr_count += 1;
+            day.hr_min = Some(day.hr_min.map_or(hr, |m| m.min(hr)));
+            day.hr_max = Some(day.hr_max.map_or(hr, |m| m.max(hr)));
+        }
+    }
+
+    for session in sleep {
+        let day = days
+            .entry(local_date(session.wake_time, session.utc_offset))
+            .or_default();
+        for segment in &session.segments {
+            let minutes = ((segment.end - segment.start) / 60) as u32;
+            match segment.stage {
+                SleepStage::Deep => day.deep += minutes,
+                SleepStage::Light => day.light += minutes,
+                SleepStage::Rem => day.rem += minutes,
+                SleepStage::Awake => day.awake += minutes,
+            }
+        }
+    }
+
+    days.into_iter()
+        .map(|(date, day)| DailySummary {
+            date: date.format("%Y-%m-%d").to_string(),
+            steps: day.steps,
+            calories: day.calories,
+            avg_heart_rate: (day.hr_count > 0).then(|| (day.hr_sum / day.hr_count) as u8),
+            min_heart_rate: day.hr_min,
+            max_heart_rate: day.hr_max,
+            sleep_minutes: day.deep + day.light + day.rem,
+            deep_sleep_minutes: day.deep,
+            light_sleep_minutes: day.light,
+            rem_sleep_minutes: day.rem,
+            awake_minutes: day.awake,
+        })
+        .collect()
+}
+
+/// 首行为字段名，缺失的心率留空
+pub fn write_csv<W: Write>(writer: W, summaries: &[DailySummary]) -> Result<()> {
+    let mut csv = csv::Writer::from_writer(writer);
+    for summary in summaries {
+        csv.serialize(summary)?;
+    }
+    csv.flush()?;
+    Ok(())
+}
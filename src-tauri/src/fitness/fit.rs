@@ -0,0 +1,258 @@
+//! Garmin FIT 活动文件
+//!
+//! 只写导入所需的最小集合：file_id、record（每个轨迹点）、lap、session、activity，
+//! 全部为小端；缺失的字段写 FIT 规定的无效值。
+
+use super::{cumulative_distance, Activity, Sport};
+
+/// 1989-12-31T00:00:00Z 的 unix 时间戳
+const FIT_EPOCH: i64 = 631_065_600;
+const PROTOCOL_VERSION: u8 = 0x20;
+const PROFILE_VERSION: u16 = 2132;
+const HEADER_SIZE: u8 = 14;
+
+mod mesg {
+    pub const FILE_ID: u16 = 0;
+    pub const SESSION: u16 = 18;
+    pub const LAP: u16 = 19;
+    pub const RECORD: u16 = 20;
+    pub const ACTIVITY: u16 = 34;
+}
+
+/// event 与 event_type 枚举
+const EVENT_SESSION: u8 = 8;
+const EVENT_LAP: u8 = 9;
+const EVENT_ACTIVITY: u8 = 26;
+const EVENT_TYPE_STOP: u8 = 1;
+const FILE_TYPE_ACTIVITY: u8 = 4;
+const MANUFACTURER_DEVELOPMENT: u16 = 255;
+const TIMESTAMP: u8 = 253;
+
+#[derive(Debug, Clone, Copy)]
+enum Value {
+    Enum(u8),
+    U8(Option<u8>),
+    U16(Option<u16>),
+    S32(Option<i32>),
+    U32(Option<u32>),
+}
+
+impl Value {
+    fn base_type(&self) -> u8 {
+        match self {
+            Value::Enum(_) => 0x00,
+            Value::U8(_) => 0x02,
+            Value::U16(_) => 0x84,
+            Value::S32(_) => 0x85,
+            Value::U32(_) => 0x86,
+        }
+    }
+
+    fn size(&self) -> u8 {
+        match self {
+            Value::Enum(_) | Value::U8(_) => 1,
+            Value::U16(_) => 2,
+            Value::S32(_) | Value::U32(_) => 4,
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match *self {
+            Value::Enum(v) => out.push(v),
+            Value::U8(v) => out.push(v.unwrap_or(u8::MAX)),
+            Value::U16(v) => out.extend_from_slice(&v.unwrap_or(u16::MAX).to_le_bytes()),
+            Value::S32(v) => out.extend_from_slice(&v.unwrap_or(i32::MAX).to_le_bytes()),
+            Value::U32(v) => out.extend_from_slice(&v.unwrap_or(u32::MAX).to_le_bytes()),
+        }
+    }
+}
+
+/// 定义消息中的一个字段：(field number, size, base type)
+type FieldDef = (u8, u8, u8);
+
+/// 按需写定义消息：local 类型的字段布局变化时重新定义
+#[derive(Default)]
+struct Encoder {
+    data: Vec<u8>,
+    definitions: [Option<(u16, Vec<FieldDef>)>; 16],
+}
+
+impl Encoder {
+    fn message(&mut self, local: u8, global: u16, fields: &[(u8, Value)]) {
+        let layout: Vec<FieldDef> = fields
+            .iter()
+            .map(|(num, v)| (*num, v.size(), v.base_type()))
+            .collect();
+
+        let slot = &mut self.definitions[local as usize];
+        let defined = matches!(slot, Some((g, l)) if *g == global && *l == layout);
+        if !defined {
+            self.data.push(0x40 | local);
+            self.data.push(0); // reserved
+            self.data.push(0); // 小端
+            self.data.extend_from_slice(&global.to_le_bytes());
+            self.data.push(layout.len() as u8);
+            for (num, size, base) in &layout {
+                self.data.extend_from_slice(&[*num, *size, *base]);
+            }
+            *slot = Some((global, layout));
+        }
+
+        self.data.push(local);
+        for (_, value) in fields {
+            value.write(&mut self.data);
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_SIZE as usize + self.data.len() + 2);
+        out.push(HEADER_SIZE);
+        out.push(PROTOCOL_VERSION);
+        out.extend_from_slice(&PROFILE_VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        out.extend_from_slice(b".FIT");
+        let header_crc = crc16(&out);
+        out.extend_from_slice(&header_crc.to_le_bytes());
+        out.extend_from_slice(&self.data);
+        let crc = crc16(&out);
+        out.extend_from_slice(&crc.to_le_bytes());
+        out
+    }
+}
+
+/// FIT 使用的 CRC-16（多项式 0xA001，初值 0）
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn fit_time(ts: i64) -> Option<u32> {
+    u32::try_from(ts - FIT_EPOCH).ok()
+}
+
+fn semicircles(degrees: f64) -> i32 {
+    (degrees * (2f64.powi(31) / 180.0)).round() as i32
+}
+
+fn fit_sport(sport: Sport) -> u8 {
+    match sport {
+        Sport::Running => 1,
+        Sport::Cycling => 2,
+        Sport::Swimming => 5,
+        Sport::Walking => 11,
+        Sport::Hiking => 17,
+        Sport::Other(_) => 0,
+    }
+}
+
+pub fn write(activity: &Activity) -> Vec<u8> {
+    let start = fit_time(activity.start);
+    let end = fit_time(activity.end());
+    let elapsed = ((activity.end() - activity.start).max(0) as u32).saturating_mul(1000);
+    let timer = activity.duration().saturating_mul(1000);
+    let distance = (activity.distance() * 100.0).round() as u32;
+    let sport = fit_sport(activity.sport);
+
+    let mut enc = Encoder::default();
+    enc.message(
+        0,
+        mesg::FILE_ID,
+        &[
+            (0, Value::Enum(FILE_TYPE_ACTIVITY)),
+            (1, Value::U16(Some(MANUFACTURER_DEVELOPMENT))),
+            (2, Value::U16(Some(0))),
+            (4, Value::U32(start)),
+        ],
+    );
+
+    let distances = cumulative_distance(&activity.track);
+    for (point, distance) in activity.track.iter().zip(distances) {
+        enc.message(
+            1,
+            mesg::RECORD,
+            &[
+                (TIMESTAMP, Value::U32(fit_time(point.time))),
+                (0, Value::S32(Some(semicircles(point.latitude)))),
+                (1, Value::S32(Some(semicircles(point.longitude)))),
+                // scale 5, offset 500
+                (
+                    2,
+                    Value::U16(point.altitude.map(|a| ((a + 500.0) * 5.0).round() as u16)),
+                ),
+                (3, Value::U8(point.heart_rate)),
+                (5, Value::U32(Some((distance * 100.0).round() as u32))),
+                (
+                    6,
+                    Value::U16(point.speed.map(|s| (s * 1000.0).round() as u16)),
+                ),
+            ],
+        );
+    }
+
+    enc.message(
+        2,
+        mesg::LAP,
+        &[
+            (TIMESTAMP, Value::U32(end)),
+            (0, Value::Enum(EVENT_LAP)),
+            (1, Value::Enum(EVENT_TYPE_STOP)),
+            (2, Value::U32(start)),
+            (7, Value::U32(Some(elapsed))),
+            (8, Value::U32(Some(timer))),
+            (9, Value::U32(Some(distance))),
+            (11, Value::U16(activity.calories())),
+            (15, Value::U8(activity.avg_heart_rate())),
+            (16, Value::U8(activity.max_heart_rate())),
+            (25, Value::Enum(sport)),
+        ],
+    );
+
+    enc.message(
+        3,
+        mesg::SESSION,
+        &[
+            (TIMESTAMP, Value::U32(end)),
+            (0, Value::Enum(EVENT_SESSION)),
+            (1, Value::Enum(EVENT_TYPE_STOP)),
+            (2, Value::U32(start)),
+            (5, Value::Enum(sport)),
+            (7, Value::U32(Some(elapsed))),
+            (8, Value::U32(Some(timer))),
+            (9, Value::U32(Some(distance))),
+            (11, Value::U16(activity.calories())),
+            (16, Value::U8(activity.avg_heart_rate())),
+            (17, Value::U8(activity.max_heart_rate())),
+            (25, Value::U16(Some(0))),
+            (26, Value::U16(Some(1))),
+        ],
+    );
+
+    enc.message(
+        4,
+        mesg::ACTIVITY,
+        &[
+            (TIMESTAMP, Value::U32(end)),
+            (0, Value::U32(Some(timer))),
+            (1, Value::U16(Some(1))),
+            (2, Value::Enum(0)),
+            (3, Value::Enum(EVENT_ACTIVITY)),
+            (4, Value::Enum(EVENT_TYPE_STOP)),
+            (
+                5,
+                Value::U32(fit_time(activity.end() + activity.utc_offset as i64)),
+            ),
+        ],
+    );
+
+    enc.finish()
+}
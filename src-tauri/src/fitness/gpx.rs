@@ -0,0 +1,60 @@
+//! GPX 1.1，心率写在 Garmin TrackPointExtension v1 中
+
+use std::fmt::Write;
+
+use super::{utc_time, Activity, Sport};
+
+fn gpx_type(sport: Sport) -> &'static str {
+    match sport {
+        Sport::Running => "running",
+        Sport::Walking => "walking",
+        Sport::Hiking => "hiking",
+        Sport::Cycling => "cycling",
+        Sport::Swimming => "swimming",
+        Sport::Other(_) => "other",
+    }
+}
+
+pub fn write(activity: &Activity) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(
+        "<gpx version=\"1.1\" creator=\"AstroBox\" \
+         xmlns=\"http://www.topografix.com/GPX/1/1\" \
+         xmlns:gpxtpx=\"http://www.garmin.com/xmlschemas/TrackPointExtension/v1\">\n",
+    );
+    let _ = writeln!(
+        out,
+        "  <metadata><time>{}</time></metadata>",
+        utc_time(activity.start)
+    );
+    out.push_str("  <trk>\n");
+    let _ = writeln!(out, "    <name>{}</name>", activity.id);
+    let _ = writeln!(out, "    <type>{}</type>", gpx_type(activity.sport));
+    out.push_str("    <trkseg>\n");
+
+    for point in &activity.track {
+        let _ = writeln!(
+            out,
+            "      <trkpt lat=\"{:.7}\" lon=\"{:.7}\">",
+            point.latitude, point.longitude
+        );
+        if let Some(ele) = point.altitude {
+            let _ = writeln!(out, "        <ele>{:.1}</ele>", ele);
+        }
+        let _ = writeln!(out, "        <time>{}</time>", utc_time(point.time));
+        if let Some(hr) = point.heart_rate {
+            let _ = writeln!(
+                out,
+                "        <extensions><gpxtpx:TrackPointExtension>\
+                 <gpxtpx:hr>{}</gpxtpx:hr>\
+                 </gpxtpx:TrackPointExtension></extensions>",
+                hr
+            );
+        }
+        out.push_str("      </trkpt>\n");
+    }
+
+    out.push_str("    </trkseg>\n  </trk>\n</gpx>\n");
+    out
+}
@@ -0,0 +1,517 @@
+//! FileFitness 记录文件解析
+//!
+//! 文件头 7 字节：
+//!
+//! | 偏移 | 类型 | 含义 |
+//! | ---- | ---- | ---- |
+//! | 0 | u32 | 记录起始时间（unix 秒） |
+//! | 4 | i8 | 时区，单位 15 分钟 |
+//! | 5 | u8 | 格式版本 |
+//! | 6 | u8 | bit7 类型（0 日常 / 1 运动），bit6..2 subtype，bit1..0 明细类型（0 明细 / 1 汇总 / 2 GPS） |
+//!
+//! 其后一个保留字节，然后是记录，多字节字段均为小端：
+//!
+//! - 日常 / subtype 0 / 明细：从起始时间开始每分钟一条，
+//!   steps u16 | calories u8 | heart_rate u8（0 或 0xFF 表示无效），版本 3 起追加 spo2 u8（同上）
+//! - 日常 / subtype 3 / 明细（睡眠）：bed_time u32 | wake_time u32 | count u16 |
+//!   count × (stage u8 | start u32 | end u32)，stage 0 清醒 / 1 浅睡 / 2 深睡 / 3 REM
+//! - 运动 / 汇总：start u32 | end u32 | duration u32 | distance u32 | calories u16 |
+//!   avg_hr u8 | max_hr u8 | min_hr u8，版本 2 起追加 steps u32
+//! - 运动 / GPS：版本 1、2 每点 18 字节：time u32 | longitude f32 | latitude f32 |
+//!   保留 u32 | speed i16（右移 2 位后单位 0.1 m/s）；版本 3 每点追加 10 字节未知字段
+//!
+//! 未列出的类型与版本直接报错，不做猜测。
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use super::{MinuteSample, SleepSegment, SleepSession, SleepStage, TrackPoint, WorkoutSummary};
+
+pub const FILE_ID_LEN: usize = 7;
+const HEADER_LEN: usize = FILE_ID_LEN + 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    Activity,
+    Sports,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetailType {
+    Details,
+    Summary,
+    GpsTrack,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FileId {
+    pub timestamp: u32,
+    /// 单位 15 分钟
+    pub timezone: i8,
+    pub version: u8,
+    pub kind: FileKind,
+    pub subtype: u8,
+    pub detail: DetailType,
+}
+
+impl FileId {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < FILE_ID_LEN {
+            bail!("File id too short: {} bytes", data.len());
+        }
+        let flags = data[6];
+        Ok(Self {
+            timestamp: u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+            timezone: data[4] as i8,
+            version: data[5],
+            kind: if flags >> 7 == 0 {
+                FileKind::Activity
+            } else {
+                FileKind::Sports
+            },
+            subtype: (flags & 0x7F) >> 2,
+            detail: match flags & 0x03 {
+                0 => DetailType::Details,
+                1 => DetailType::Summary,
+                2 => DetailType::GpsTrack,
+                _ => DetailType::Unknown,
+            },
+        })
+    }
+
+    /// 相对 UTC 的偏移（秒）
+    pub fn utc_offset(&self) -> i32 {
+        self.timezone as i32 * 15 * 60
+    }
+
+    /// 同一次运动的汇总与 GPS 文件共用的 id："<起始时间戳>-<subtype>"
+    pub fn activity_id(&self) -> String {
+        format!("{}-{}", self.timestamp, self.subtype)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum FitnessContent {
+    DailyDetails(Vec<MinuteSample>),
+    Sleep(SleepSession),
+    WorkoutSummary(WorkoutSummary),
+    WorkoutTrack(Vec<TrackPoint>),
+}
+
+#[derive(Debug, Clone)]
+pub struct FitnessFile {
+    pub id: FileId,
+    pub content: FitnessContent,
+}
+
+/// 小端顺序读取，越界时报错
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + N)
+            .with_context(|| format!("Unexpected end of record at offset {}", self.pos))?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn skip(&mut self, n: usize) -> Result<()> {
+        if self.remaining() < n {
+            bail!("Unexpected end of record at offset {}", self.pos);
+        }
+        self.pos += n;
+        Ok(())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(i16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+}
+
+/// 0 与 0xFF 表示该时段没有测量
+fn measured(value: u8) -> Option<u8> {
+    (value != 0 && value != 0xFF).then_some(value)
+}
+
+pub fn parse(data: &[u8]) -> Result<FitnessFile> {
+    let id = FileId::parse(data)?;
+    if data.len() < HEADER_LEN {
+        bail!("Fitness file too short: {} bytes", data.len());
+    }
+    let mut reader = Reader::new(&data[HEADER_LEN..]);
+
+    let content = match (id.kind, id.subtype, id.detail) {
+        (FileKind::Activity, 0, DetailType::Details) => {
+            FitnessContent::DailyDetails(parse_daily_details(&id, &mut reader)?)
+        }
+        (FileKind::Activity, 3, DetailType::Details) => {
+            FitnessContent::Sleep(parse_sleep(&id, &mut reader)?)
+        }
+        (FileKind::Sports, _, DetailType::Summary) => {
+            FitnessContent::WorkoutSummary(parse_workout_summary(&id, &mut reader)?)
+        }
+        (FileKind::Sports, _, DetailType::GpsTrack) => {
+            FitnessContent::WorkoutTrack(parse_workout_track(&id, &mut reader)?)
+        }
+        (kind, subtype, detail) => bail!(
+            "Unsupported fitness file: {:?} subtype {} {:?}",
+            kind,
+            subtype,
+            detail
+        ),
+    };
+    Ok(FitnessFile { id, content })
+}
+
+fn parse_daily_details(id: &FileId, reader: &mut Reader) -> Result<Vec<MinuteSample>> {
+    if !(1..=3).contains(&id.version) {
+        bail!("Unsupported daily details version {}", id.version);
+    }
+    let record_len = if id.version >= 3 { 5 } else { 4 };
+    let mut samples = Vec::with_capacity(reader.remaining() / record_len);
+    let mut time = id.timestamp as i64;
+    while reader.remaining() >= record_len {
+        samples.push(MinuteSample {
+            time,
+            utc_offset: id.utc_offset(),
+            steps: reader.u16()?,
+            calories: reader.u8()?,
+            heart_rate: measured(reader.u8()?),
+            spo2: if id.version >= 3 {
+                measured(reader.u8()?).filter(|v| *v <= 100)
+            } else {
+                None
+            },
+        });
+        time += 60;
+    }
+    Ok(samples)
+}
+
+fn parse_sleep(id: &FileId, reader: &mut Reader) -> Result<SleepSession> {
+    if !(1..=2).contains(&id.version) {
+        bail!("Unsupported sleep version {}", id.version);
+    }
+    let bed_time = reader.u32()? as i64;
+    let wake_time = reader.u32()? as i64;
+    let count = reader.u16()?;
+    let mut segments = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let stage = match reader.u8()? {
+            0 => SleepStage::Awake,
+            1 => SleepStage::Light,
+            2 => SleepStage::Deep,
+            3 => SleepStage::Rem,
+            other => bail!("Unknown sleep stage {}", other),
+        };
+        let start = reader.u32()? as i64;
+        let end = reader.u32()? as i64;
+        if end < start {
+            bail!("Sleep segment ends before it starts: {} < {}", end, start);
+        }
+        segments.push(SleepSegment { stage, start, end });
+    }
+    Ok(SleepSession {
+        bed_time,
+        wake_time,
+        utc_offset: id.utc_offset(),
+        segments,
+    })
+}
+
+fn parse_workout_summary(id: &FileId, reader: &mut Reader) -> Result<WorkoutSummary> {
+    if !(1..=2).contains(&id.version) {
+        bail!("Unsupported workout summary version {}", id.version);
+    }
+    Ok(WorkoutSummary {
+        start: reader.u32()? as i64,
+        end: reader.u32()? as i64,
+        duration: reader.u32()?,
+        distance: reader.u32()?,
+        calories: reader.u16()?,
+        avg_heart_rate: measured(reader.u8()?),
+        max_heart_rate: measured(reader.u8()?),
+        min_heart_rate: measured(reader.u8()?),
+        steps: if id.version >= 2 {
+            Some(reader.u32()?)
+        } else {
+            None
+        },
+    })
+}
+
+fn parse_workout_track(id: &FileId, reader: &mut Reader) -> Result<Vec<TrackPoint>> {
+    let extra = match id.version {
+        1 | 2 => 0,
+        3 => 10,
+        v => bail!("Unsupported workout GPS version {}", v),
+    };
+    let record_len = 18 + extra;
+
+    let mut points = Vec::with_capacity(reader.remaining() / record_len);
+    while reader.remaining() >= record_len {
+        let time = reader.u32()? as i64;
+        let longitude = reader.f32()? as f64;
+        let latitude = reader.f32()? as f64;
+        reader.skip(4)?;
+        let speed = (reader.i16()? >> 2) as f32 / 10.0;
+        reader.skip(extra)?;
+
+        // 未定位时手表写入 0 或无效值
+        let valid = latitude.is_finite()
+            && longitude.is_finite()
+            && latitude.abs() <= 90.0
+            && longitude.abs() <= 180.0
+            && (latitude != 0.0 || longitude != 0.0);
+        if !valid {
+            continue;
+        }
+        points.push(TrackPoint {
+            time,
+            latitude,
+            longitude,
+            altitude: None,
+            speed: (speed >= 0.0).then_some(speed),
+            heart_rate: None,
+        });
+    }
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TS: u32 = 1_700_000_000;
+    /// UTC+8
+    const TZ: i8 = 32;
+
+    /// kind: 0 日常 / 1 运动；detail: 0 明细 / 1 汇总 / 2 GPS
+    fn file(version: u8, kind: u8, subtype: u8, detail: u8, body: &[u8]) -> Vec<u8> {
+        let mut out = TS.to_le_bytes().to_vec();
+        out.push(TZ as u8);
+        out.push(version);
+        out.push((kind << 7) | (subtype << 2) | detail);
+        out.push(0);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn daily(data: &[u8]) -> Vec<MinuteSample> {
+        match parse(data).unwrap().content {
+            FitnessContent::DailyDetails(samples) => samples,
+            other => panic!("expected daily details, got {:?}", other),
+        }
+    }
+
+    fn sleep(data: &[u8]) -> SleepSession {
+        match parse(data).unwrap().content {
+            FitnessContent::Sleep(session) => session,
+            other => panic!("expected sleep, got {:?}", other),
+        }
+    }
+
+    fn summary(data: &[u8]) -> WorkoutSummary {
+        match parse(data).unwrap().content {
+            FitnessContent::WorkoutSummary(summary) => summary,
+            other => panic!("expected workout summary, got {:?}", other),
+        }
+    }
+
+    fn track(data: &[u8]) -> Vec<TrackPoint> {
+        match parse(data).unwrap().content {
+            FitnessContent::WorkoutTrack(points) => points,
+            other => panic!("expected workout track, got {:?}", other),
+        }
+    }
+
+    fn gps_point(time: u32, lon: f32, lat: f32, speed: i16, extra: usize) -> Vec<u8> {
+        let mut out = time.to_le_bytes().to_vec();
+        out.extend_from_slice(&lon.to_le_bytes());
+        out.extend_from_slice(&lat.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&speed.to_le_bytes());
+        out.resize(out.len() + extra, 0xEE);
+        out
+    }
+
+    #[test]
+    fn file_id_header() {
+        let id = FileId::parse(&file(2, 1, 6, 2, &[])).unwrap();
+        assert_eq!(id.timestamp, TS);
+        assert_eq!(id.utc_offset(), 8 * 3600);
+        assert_eq!(id.version, 2);
+        assert_eq!(id.kind, FileKind::Sports);
+        assert_eq!(id.subtype, 6);
+        assert_eq!(id.detail, DetailType::GpsTrack);
+        assert_eq!(id.activity_id(), format!("{}-6", TS));
+
+        assert!(FileId::parse(&[0; 6]).is_err());
+    }
+
+    #[test]
+    fn daily_details_v1_v2() {
+        // steps u16 | calories u8 | heart_rate u8，末尾残缺的记录忽略
+        let body = [0x10, 0x00, 0x03, 72, 0x00, 0x01, 0x00, 0xFF, 0x05];
+        for version in [1, 2] {
+            let samples = daily(&file(version, 0, 0, 0, &body));
+            assert_eq!(samples.len(), 2);
+            assert_eq!(samples[0].time, TS as i64);
+            assert_eq!(samples[0].utc_offset, 8 * 3600);
+            assert_eq!(samples[0].steps, 16);
+            assert_eq!(samples[0].calories, 3);
+            assert_eq!(samples[0].heart_rate, Some(72));
+            assert_eq!(samples[0].spo2, None);
+            assert_eq!(samples[1].time, TS as i64 + 60);
+            assert_eq!(samples[1].steps, 256);
+            assert_eq!(samples[1].heart_rate, None);
+        }
+    }
+
+    #[test]
+    fn daily_details_v3_adds_spo2() {
+        let body = [
+            0x02, 0x00, 0x01, 65, 97, 0x00, 0x00, 0x00, 0, 0xFF, 0, 0, 0, 60, 101,
+        ];
+        let samples = daily(&file(3, 0, 0, 0, &body));
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0].heart_rate, Some(65));
+        assert_eq!(samples[0].spo2, Some(97));
+        assert_eq!(samples[1].heart_rate, None);
+        assert_eq!(samples[1].spo2, None);
+        // 超过 100% 的血氧视为无效
+        assert_eq!(samples[2].heart_rate, Some(60));
+        assert_eq!(samples[2].spo2, None);
+
+        assert!(parse(&file(4, 0, 0, 0, &body)).is_err());
+    }
+
+    #[test]
+    fn sleep_v1_v2() {
+        let bed = TS + 100;
+        let wake = TS + 3700;
+        let mut body = bed.to_le_bytes().to_vec();
+        body.extend_from_slice(&wake.to_le_bytes());
+        body.extend_from_slice(&2u16.to_le_bytes());
+        for (stage, start, end) in [(1u8, bed, bed + 1800), (3, bed + 1800, wake)] {
+            body.push(stage);
+            body.extend_from_slice(&start.to_le_bytes());
+            body.extend_from_slice(&end.to_le_bytes());
+        }
+
+        for version in [1, 2] {
+            let session = sleep(&file(version, 0, 3, 0, &body));
+            assert_eq!(session.bed_time, bed as i64);
+            assert_eq!(session.wake_time, wake as i64);
+            assert_eq!(session.utc_offset, 8 * 3600);
+            assert_eq!(session.segments.len(), 2);
+            assert_eq!(session.segments[0].stage, SleepStage::Light);
+            assert_eq!(session.segments[1].stage, SleepStage::Rem);
+            assert_eq!(session.segments[1].end, wake as i64);
+        }
+        assert!(parse(&file(3, 0, 3, 0, &body)).is_err());
+
+        // 未知阶段与倒序的时间段
+        let mut bad_stage = body.clone();
+        bad_stage[10] = 9;
+        assert!(parse(&file(1, 0, 3, 0, &bad_stage)).is_err());
+        let mut reversed = body.clone();
+        reversed[11..15].copy_from_slice(&(wake + 1).to_le_bytes());
+        assert!(parse(&file(1, 0, 3, 0, &reversed)).is_err());
+        // 段数超出实际数据
+        assert!(parse(&file(1, 0, 3, 0, &body[..body.len() - 1])).is_err());
+    }
+
+    #[test]
+    fn workout_summary_v1_v2() {
+        let mut body = Vec::new();
+        body.extend_from_slice(&TS.to_le_bytes());
+        body.extend_from_slice(&(TS + 1900).to_le_bytes());
+        body.extend_from_slice(&1800u32.to_le_bytes());
+        body.extend_from_slice(&5000u32.to_le_bytes());
+        body.extend_from_slice(&320u16.to_le_bytes());
+        body.extend_from_slice(&[150, 178, 0xFF]);
+
+        let v1 = summary(&file(1, 1, 1, 1, &body));
+        assert_eq!(v1.start, TS as i64);
+        assert_eq!(v1.end, TS as i64 + 1900);
+        assert_eq!(v1.duration, 1800);
+        assert_eq!(v1.distance, 5000);
+        assert_eq!(v1.calories, 320);
+        assert_eq!(v1.avg_heart_rate, Some(150));
+        assert_eq!(v1.max_heart_rate, Some(178));
+        assert_eq!(v1.min_heart_rate, None);
+        assert_eq!(v1.steps, None);
+
+        // 版本 2 追加 steps，缺少时报错
+        assert!(parse(&file(2, 1, 1, 1, &body)).is_err());
+        body.extend_from_slice(&6400u32.to_le_bytes());
+        assert_eq!(summary(&file(2, 1, 1, 1, &body)).steps, Some(6400));
+
+        assert!(parse(&file(3, 1, 1, 1, &body)).is_err());
+    }
+
+    #[test]
+    fn workout_track_v1_v2_v3() {
+        for (version, extra) in [(1, 0), (2, 0), (3, 10)] {
+            let mut body = gps_point(TS, 116.4, 39.9, 30 << 2, extra);
+            // 未定位的点被跳过
+            body.extend(gps_point(TS + 1, 0.0, 0.0, 0, extra));
+            body.extend(gps_point(TS + 2, 116.5, 91.0, 0, extra));
+            body.extend(gps_point(TS + 3, 116.41, 39.91, -4, extra));
+
+            let points = track(&file(version, 1, 1, 2, &body));
+            assert_eq!(points.len(), 2, "version {}", version);
+            assert_eq!(points[0].time, TS as i64);
+            assert!((points[0].longitude - 116.4).abs() < 1e-5);
+            assert!((points[0].latitude - 39.9).abs() < 1e-5);
+            assert_eq!(points[0].speed, Some(3.0));
+            assert_eq!(points[1].time, TS as i64 + 3);
+            assert_eq!(points[1].speed, None);
+        }
+
+        assert!(parse(&file(4, 1, 1, 2, &gps_point(TS, 1.0, 1.0, 0, 0))).is_err());
+    }
+
+    #[test]
+    fn unsupported_files_are_rejected() {
+        // 日常汇总、未知 subtype、未知明细类型
+        assert!(parse(&file(1, 0, 0, 1, &[0; 8])).is_err());
+        assert!(parse(&file(1, 0, 5, 0, &[0; 8])).is_err());
+        assert!(parse(&file(1, 1, 1, 3, &[0; 8])).is_err());
+        // 只有文件 id 没有保留字节
+        assert!(parse(&file(1, 0, 0, 0, &[])[..FILE_ID_LEN]).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+//! Garmin Training Center Database v2，整次运动作为一个 Lap
+
+use std::fmt::Write;
+
+use super::{cumulative_distance, utc_time, Activity, Sport};
+
+/// TCX 只定义了 Running / Biking / Other
+fn tcx_sport(sport: Sport) -> &'static str {
+    match sport {
+        Sport::Running => "Running",
+        Sport::Cycling => "Biking",
+        _ => "Other",
+    }
+}
+
+pub fn write(activity: &Activity) -> String {
+    let start = utc_time(activity.start);
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(
+        "<TrainingCenterDatabase \
+         xmlns=\"http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2\">\n",
+    );
+    out.push_str("  <Activities>\n");
+    let _ = writeln!(
+        out,
+        "    <Activity Sport=\"{}\">",
+        tcx_sport(activity.sport)
+    );
+    let _ = writeln!(out, "      <Id>{}</Id>", start);
+    let _ = writeln!(out, "      <Lap StartTime=\"{}\">", start);
+    let _ = writeln!(
+        out,
+        "        <TotalTimeSeconds>{}</TotalTimeSeconds>",
+        activity.duration()
+    );
+    let _ = writeln!(
+        out,
+        "        <DistanceMeters>{:.1}</DistanceMeters>",
+        activity.distance()
+    );
+    let _ = writeln!(
+        out,
+        "        <Calories>{}</Calories>",
+        activity.calories().unwrap_or(0)
+    );
+    if let Some(hr) = activity.avg_heart_rate() {
+        let _ = writeln!(
+            out,
+            "        <AverageHeartRateBpm><Value>{}</Value></AverageHeartRateBpm>",
+            hr
+        );
+    }
+    if let Some(hr) = activity.max_heart_rate() {
+        let _ = writeln!(
+            out,
+            "        <MaximumHeartRateBpm><Value>{}</Value></MaximumHeartRateBpm>",
+            hr
+        );
+    }
+    out.push_str("        <Intensity>Active</Intensity>\n");
+    out.push_str("        <TriggerMethod>Manual</TriggerMethod>\n");
+
+    if !activity.track.is_empty() {
+        out.push_str("        <Track>\n");
+        let distances = cumulative_distance(&activity.track);
+        for (point, distance) in activity.track.iter().zip(distances) {
+            out.push_str("          <Trackpoint>\n");
+            let _ = writeln!(out, "            <Time>{}</Time>", utc_time(point.time));
+            let _ = writeln!(
+                out,
+                "            <Position><LatitudeDegrees>{:.7}</LatitudeDegrees>\
+                 <LongitudeDegrees>{:.7}</LongitudeDegrees></Position>",
+                point.latitude, point.longitude
+            );
+            if let Some(ele) = point.altitude {
+                let _ = writeln!(
+                    out,
+                    "            <AltitudeMeters>{:.1}</AltitudeMeters>",
+                    ele
+                );
+            }
+            let _ = writeln!(
+                out,
+                "            <DistanceMeters>{:.1}</DistanceMeters>",
+                distance
+            );
+            if let Some(hr) = point.heart_rate {
+                let _ = writeln!(
+                    out,
+                    "            <HeartRateBpm><Value>{}</Value></HeartRateBpm>",
+                    hr
+                );
+            }
+            out.push_str("          </Trackpoint>\n");
+        }
+        out.push_str("        </Track>\n");
+    }
+
+    out.push_str("      </Lap>\n");
+    out.push_str("    </Activity>\n  </Activities>\n</TrainingCenterDatabase>\n");
+    out
+}
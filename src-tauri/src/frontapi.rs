@@ -8,15 +8,23 @@ use crate::{
         Item, ProgressData, ProviderState, ResourceManifestV1, SearchConfig,
     },
     config::AppConfig,
+    fitness::{daily::DailySummary, Activity, ActivityInfo, ExportFormat},
     frontmodels::BTDeviceInfo,
+    health::{HealthQuery, HealthRecords, ImportReport},
+    miwear::capture::CaptureFiles,
+    miwear::command_pool::{retransmit::RetransmitSnapshot, SchedulerConfig},
+    miwear::device::download::DownloadedFile,
     miwear::device::models::DeviceMap,
     miwear::device::{
         mass::SendMassCallbackData,
         system::{system_get_device_info, system_get_device_status, SystemInfo, SystemStatus},
         MiWearBleCharaUuid, MiWearState,
     },
+    miwear::reconnect::ResumableTransfer,
+    miwear::{ConnectionState, MiWearError},
     pluginstore::provider::StorePluginManifest,
     pluginsystem::{apis::models::PluginUINode, manifest::PluginManifest, plugin::PluginState},
+    secrets::SecretsStatus,
 };
 use base64::{engine::general_purpose, Engine};
 use boa_engine::{js_string, JsValue};
@@ -59,16 +67,16 @@ pub fn miwear_scan(cb: Channel<Vec<BTDeviceInfo>>) -> Result<(), String> {
 
 // 前端API：停止扫描
 #[tauri::command]
-pub async fn miwear_stop_scan() -> Result<(), String> {
+pub async fn miwear_stop_scan() -> Result<(), MiWearError> {
     if crate::config::read(|c| c.clone().connect_type) == ConnectType::BLE {
         crate::bt::get_ble_handler()
             .stop_scan()
             .await
-            .map_err(|e| e.to_string())?;
+            .map_err(MiWearError::transport)?;
     } else {
         crate::bt::get_spp_handle()
             .stop_scan()
-            .map_err(|e| e.to_string())?;
+            .map_err(MiWearError::transport)?;
     }
 
     Ok(())
@@ -76,51 +84,84 @@ pub async fn miwear_stop_scan() -> Result<(), String> {
 
 // 前端API：连接设备
 #[tauri::command]
-pub async fn miwear_connect(addr: String, name: String) -> Result<(), String> {
-    if let Some(current_device) = crate::miwear::CONNECTED_DEVICE.read().await.as_ref() {
-        if current_device.state.read().await.addr != addr {
-            log::info!("Switching device, disconnecting from the old one first.");
-            current_device
-                .disconnect()
-                .await
-                .map_err(|e| e.to_string())?;
-            // 等待一小段时间确保资源完全释放
-            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-        } else {
-            log::info!("Already connected to this device. No action needed.");
-            return Ok(());
+// 新连接的设备会成为 primary 设备，已连接的其他设备保持连接
+#[tauri::command]
+pub async fn miwear_connect(addr: String, name: String) -> Result<(), MiWearError> {
+    if crate::miwear::get_device(Some(&addr)).await.is_some() {
+        log::info!("Already connected to this device. Making it primary.");
+        crate::miwear::set_primary_device(&addr).await;
+        return Ok(());
+    }
+
+    // SPP 只有一个全局数据监听，无法同时连接多台设备
+    if crate::config::read(|c| c.connect_type.clone()) == ConnectType::SPP
+        && !crate::miwear::DEVICES.is_empty().await
+    {
+        log::info!("Switching SPP device, disconnecting from the old ones first.");
+        for device in crate::miwear::DEVICES.all().await {
+            device.disconnect().await?;
         }
+        // 等待一小段时间确保资源完全释放
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
     }
 
-    let device = crate::miwear::device::MiWearDevice::connect(addr, name)
-        .await
-        .map_err(|e| e.to_string())?;
+    // 用户手动连接时停止该设备的自动重连
+    crate::miwear::reconnect::cancel(&addr);
 
+    let device = crate::miwear::device::MiWearDevice::connect(addr.clone(), name).await?;
+
+    crate::miwear::register_device(device).await;
     if let Some(app) = crate::APP_HANDLE.get() {
-        let _ = app.emit("device-connected", ());
+        let _ = app.emit("device-connected", addr);
     }
-    crate::miwear::set_connected_device(Some(device)).await;
     Ok(())
 }
 
-// 前端API：断连设备
+// 前端API：断连设备，未指定地址时断开 primary 设备
 #[tauri::command]
-pub async fn miwear_disconnect() -> Result<(), String> {
-    crate::miwear::with_connected_device_async(|device| async move {
+pub async fn miwear_disconnect(addr: Option<String>) -> Result<(), MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
         device.disconnect().await?;
         anyhow::Ok(())
     })
     .await
 }
 
+// 前端API：列出所有已连接设备，primary 设备排在最前
 #[tauri::command]
-pub async fn miwear_remove_device(addr: String) -> Result<(), String> {
-    let current_device = crate::config::read(|c| c.current_device.clone());
+pub async fn miwear_list_connected_devices() -> Result<Vec<MiWearState>, MiWearError> {
+    Ok(crate::miwear::connected_device_states().await)
+}
 
-    if let Some(dev) = &current_device {
-        if dev.addr == addr {
-            let _ = miwear_disconnect().await;
-        }
+// 前端API：获取设备的连接状态，未指定地址时查询 primary 设备
+#[tauri::command]
+pub async fn miwear_get_connection_state(
+    addr: Option<String>,
+) -> Result<ConnectionState, MiWearError> {
+    let addr = match addr {
+        Some(addr) => addr,
+        None => crate::miwear::DEVICES
+            .primary_addr()
+            .await
+            .ok_or(MiWearError::NotConnected)?,
+    };
+    Ok(crate::miwear::connection::current(&addr))
+}
+
+// 前端API：切换 primary 设备
+#[tauri::command]
+pub async fn miwear_set_primary_device(addr: String) -> Result<(), MiWearError> {
+    if !crate::miwear::set_primary_device(&addr).await {
+        return Err(MiWearError::NotConnected);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn miwear_remove_device(addr: String) -> Result<(), MiWearError> {
+    crate::miwear::reconnect::cancel(&addr);
+    if crate::miwear::get_device(Some(&addr)).await.is_some() {
+        let _ = miwear_disconnect(Some(addr.clone())).await;
     }
 
     crate::config::write(|c| {
@@ -131,14 +172,15 @@ pub async fn miwear_remove_device(addr: String) -> Result<(), String> {
             }
         }
     });
+    crate::miwear::device::mass::resume::clear_device(&addr);
 
     Ok(())
 }
 
 // 前端API：获取已连接设备的信息
 #[tauri::command]
-pub async fn miwear_get_state() -> Result<MiWearState, String> {
-    crate::miwear::with_connected_device_async(|device| async move {
+pub async fn miwear_get_state(addr: Option<String>) -> Result<MiWearState, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
         let state = device.state.read().await;
         anyhow::Ok(state.clone())
     })
@@ -147,37 +189,37 @@ pub async fn miwear_get_state() -> Result<MiWearState, String> {
 
 // 前端API：获取已连接设备的电量等信息
 #[tauri::command]
-pub async fn miwear_get_device_state() -> Result<SystemStatus, String> {
-    let result = crate::miwear::with_connected_device_async(|device| async move {
+pub async fn miwear_get_device_state(addr: Option<String>) -> Result<SystemStatus, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
         system_get_device_status(device).await
     })
-    .await;
-    result.map_err(|e| e.to_string())
+    .await
 }
 
 //获取设备详细信息
 #[tauri::command]
-pub async fn miwear_get_device_info() -> Result<SystemInfo, String> {
-    let result = crate::miwear::with_connected_device_async(|device| async move {
+pub async fn miwear_get_device_info(addr: Option<String>) -> Result<SystemInfo, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
         system_get_device_info(device).await
     })
-    .await;
-    result.map_err(|e| e.to_string())
+    .await
 }
 
 // 前端API：给设备发送hello和sc
 #[tauri::command]
-pub async fn miwear_start_hello() -> Result<(), String> {
-    crate::miwear::with_connected_device_async(|device| async move { device.start_hello().await })
-        .await
+pub async fn miwear_start_hello(addr: Option<String>) -> Result<(), MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        device.start_hello().await
+    })
+    .await
 }
 
 // 前端API：开始设备auth
 #[tauri::command]
-pub async fn miwear_start_auth(auth_key: String) -> Result<(), String> {
-    crate::miwear::with_connected_device_async(
-        |device| async move { device.start_auth(auth_key).await },
-    )
+pub async fn miwear_start_auth(auth_key: String, addr: Option<String>) -> Result<(), MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        device.start_auth(auth_key).await
+    })
     .await
 }
 
@@ -188,19 +230,16 @@ pub async fn miwear_install_third_app(
     package_name: String,
     version_code: u32,
     on_progress: Channel<SendMassCallbackData>,
-) -> Result<(), String> {
-    crate::miwear::with_connected_device_async(|device| async move {
-        crate::miwear::device::thirdpartyapp::install_app(
-            device,
-            &file_path,
-            &package_name,
+    addr: Option<String>,
+) -> Result<(), MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        let op = device.operations.begin("install_third_app");
+        let transfer = ResumableTransfer::ThirdPartyApp {
+            file_path,
+            package_name,
             version_code,
-            move |data| {
-                let _ = on_progress.send(data);
-            },
-        )
-        .await?;
-        anyhow::Ok(())
+        };
+        run_resumable(device, transfer, op.token(), on_progress).await
     })
     .await
 }
@@ -211,30 +250,23 @@ pub async fn miwear_install_watchface(
     file_path: String,
     on_progress: Channel<SendMassCallbackData>,
     new_watchface_id: Option<Vec<u8>>,
-) -> Result<(), String> {
-    crate::miwear::with_connected_device_async(|device| async move {
-        let (id, id_bytes) = match new_watchface_id {
-            Some(ref bytes) => (String::from_utf8_lossy(bytes).into_owned(), Some(bytes)),
-            None => (
-                crate::miwear::device::resutils::get_watchface_id(&file_path)
-                    .await
-                    .unwrap_or("000000000000".to_string()),
-                None,
-            ),
+    addr: Option<String>,
+) -> Result<(), MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        let id = match new_watchface_id {
+            Some(ref bytes) => String::from_utf8_lossy(bytes).into_owned(),
+            None => crate::miwear::device::resutils::get_watchface_id(&file_path)
+                .await
+                .unwrap_or("000000000000".to_string()),
         };
 
-        crate::miwear::device::watchface::install_watchface(
-            device,
-            &file_path,
-            id_bytes,
-            &id,
-            move |data| {
-                let _ = on_progress.send(data);
-            },
-        )
-        .await?;
-
-        anyhow::Ok(())
+        let op = device.operations.begin("install_watchface");
+        let transfer = ResumableTransfer::Watchface {
+            file_path,
+            new_watchface_id,
+            id,
+        };
+        run_resumable(device, transfer, op.token(), on_progress).await
     })
     .await
 }
@@ -242,8 +274,9 @@ pub async fn miwear_install_watchface(
 // 前端API：获取表盘列表
 #[tauri::command]
 pub async fn miwear_get_watchface_list(
-) -> Result<Vec<crate::miwear::device::watchface::WatchfaceInfo>, String> {
-    let watchface_list = crate::miwear::with_connected_device_async(|device| async {
+    addr: Option<String>,
+) -> Result<Vec<crate::miwear::device::watchface::WatchfaceInfo>, MiWearError> {
+    let watchface_list = crate::miwear::with_device_async(addr.as_deref(), |device| async {
         crate::miwear::device::watchface::get_watchface_list(device).await
     })
     .await?;
@@ -254,8 +287,9 @@ pub async fn miwear_get_watchface_list(
 #[tauri::command]
 pub async fn miwear_uninstall_watchface(
     watchface: crate::miwear::device::watchface::WatchfaceInfo,
-) -> Result<(), String> {
-    crate::miwear::with_connected_device_async(|device| async {
+    addr: Option<String>,
+) -> Result<(), MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async {
         crate::miwear::device::watchface::uninstall_watchface(device, watchface).await
     })
     .await?;
@@ -266,8 +300,9 @@ pub async fn miwear_uninstall_watchface(
 #[tauri::command]
 pub async fn miwear_set_watchface(
     watchface: crate::miwear::device::watchface::WatchfaceInfo,
-) -> Result<(), String> {
-    crate::miwear::with_connected_device_async(|device| async {
+    addr: Option<String>,
+) -> Result<(), MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async {
         crate::miwear::device::watchface::set_watchface(device, watchface).await
     })
     .await?;
@@ -279,22 +314,39 @@ pub async fn miwear_set_watchface(
 pub async fn miwear_install_firmware(
     file_path: String,
     on_progress: Channel<SendMassCallbackData>,
-) -> Result<(), String> {
-    crate::miwear::with_connected_device_async(|device| async move {
-        crate::miwear::device::firmware::install_firmware(device, &file_path, move |data| {
-            let _ = on_progress.send(data);
-        })
-        .await?;
-        anyhow::Ok(())
+    addr: Option<String>,
+) -> Result<(), MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        let op = device.operations.begin("install_firmware");
+        let transfer = ResumableTransfer::Firmware { file_path };
+        run_resumable(device, transfer, op.token(), on_progress).await
     })
     .await
 }
 
+// 执行安装；因设备断开而失败时记下请求，自动重连成功后从断点续传
+async fn run_resumable(
+    device: std::sync::Arc<crate::miwear::MiWearDevice>,
+    transfer: ResumableTransfer,
+    cancel: tokio_util::sync::CancellationToken,
+    on_progress: Channel<SendMassCallbackData>,
+) -> anyhow::Result<()> {
+    let ticket = crate::miwear::reconnect::track_transfer(&device.addr, transfer.clone());
+    let ret = transfer
+        .run(device.clone(), cancel, move |data| {
+            let _ = on_progress.send(data);
+        })
+        .await;
+    crate::miwear::reconnect::finish_transfer(ticket, &device, &ret).await;
+    ret
+}
+
 //获取设备应用列表
 #[tauri::command]
 pub async fn miwear_get_app_list(
-) -> Result<Vec<crate::miwear::device::thirdpartyapp::AppInfo>, String> {
-    let app_list = crate::miwear::with_connected_device_async(|device| async {
+    addr: Option<String>,
+) -> Result<Vec<crate::miwear::device::thirdpartyapp::AppInfo>, MiWearError> {
+    let app_list = crate::miwear::with_device_async(addr.as_deref(), |device| async {
         crate::miwear::device::thirdpartyapp::get_app_list(device).await
     })
     .await?;
@@ -305,8 +357,9 @@ pub async fn miwear_get_app_list(
 #[tauri::command]
 pub async fn miwear_uninstall_quickapp(
     app: crate::miwear::device::thirdpartyapp::AppInfo,
-) -> Result<(), String> {
-    crate::miwear::with_connected_device_async(|device| async {
+    addr: Option<String>,
+) -> Result<(), MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async {
         crate::miwear::device::thirdpartyapp::uninstall_app(device, app).await
     })
     .await?;
@@ -318,40 +371,206 @@ pub async fn miwear_uninstall_quickapp(
 pub async fn miwear_open_quickapp(
     app: crate::miwear::device::thirdpartyapp::AppInfo,
     page: String,
-) -> Result<(), String> {
-    crate::miwear::with_connected_device_async(|device| async {
+    addr: Option<String>,
+) -> Result<(), MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async {
         crate::miwear::device::thirdpartyapp::launch_app(device, app, &page).await
     })
     .await?;
     Ok(())
 }
 
+// 前端API：取消正在进行的安装等操作，返回被取消的操作数
 #[tauri::command]
-pub async fn miwear_is_sending_mass() -> Result<bool, String> {
-    crate::miwear::with_connected_device_async(|device| async move {
-        let stat = device.is_sending_mass.lock().await.clone();
+pub async fn miwear_cancel_operation(addr: Option<String>) -> Result<usize, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        anyhow::Ok(device.operations.cancel_all())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn miwear_is_sending_mass(addr: Option<String>) -> Result<bool, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        let stat = device
+            .is_sending_mass
+            .load(std::sync::atomic::Ordering::Relaxed);
         anyhow::Ok(stat)
     })
     .await
 }
 
 #[tauri::command]
-pub async fn miwear_get_codename() -> Result<String, String> {
-    crate::miwear::with_connected_device_async(|device| async move {
-        device.get_codename().await.map_err(|e| e.to_string())
+pub async fn miwear_get_codename(addr: Option<String>) -> Result<String, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        device.get_codename().await
     })
     .await
 }
 
 #[tauri::command]
-pub async fn miwear_debug_get_commandpool_json_table() -> Result<Vec<serde_json::Value>, String> {
-    crate::miwear::with_connected_device_async(|device| async move {
+pub async fn miwear_debug_get_commandpool_json_table(
+    addr: Option<String>,
+) -> Result<Vec<serde_json::Value>, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
         let table = device.cmd_pool.to_json_table().await;
         anyhow::Ok(table)
     })
     .await
 }
 
+#[tauri::command]
+pub async fn miwear_get_retransmit_stats(
+    addr: Option<String>,
+) -> Result<RetransmitSnapshot, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        anyhow::Ok(device.cmd_pool.retry_stats())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn miwear_start_capture(addr: Option<String>) -> Result<CaptureFiles, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        crate::miwear::capture::start(&device.addr)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn miwear_stop_capture(
+    addr: Option<String>,
+) -> Result<Option<CaptureFiles>, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        anyhow::Ok(crate::miwear::capture::stop(&device.addr))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn miwear_get_capture(addr: Option<String>) -> Result<Option<CaptureFiles>, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        anyhow::Ok(crate::miwear::capture::files(&device.addr))
+    })
+    .await
+}
+
+/// 获取设备当前生效的通道调度参数
+#[tauri::command]
+pub async fn miwear_get_scheduler(addr: Option<String>) -> Result<SchedulerConfig, MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        anyhow::Ok(device.cmd_pool.config().await)
+    })
+    .await
+}
+
+/// 设置设备的通道调度参数并立即生效，传入 None 时恢复使用全局配置
+#[tauri::command]
+pub async fn miwear_set_scheduler(
+    addr: Option<String>,
+    config: Option<SchedulerConfig>,
+) -> Result<(), MiWearError> {
+    crate::miwear::with_device_async(addr.as_deref(), |device| async move {
+        let effective = config
+            .clone()
+            .unwrap_or_else(|| crate::config::read(|c| c.command_scheduler.clone()));
+        device.state.write().await.scheduler = config.clone();
+        device.cmd_pool.set_config(effective).await;
+
+        crate::config::write(|c| {
+            if let Some(dev) = c.paired_devices.iter_mut().find(|d| d.addr == device.addr) {
+                dev.scheduler = config;
+            }
+        });
+        anyhow::Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn miwear_list_downloads() -> Vec<DownloadedFile> {
+    crate::miwear::device::download::list()
+}
+
+/// 返回下载文件在本地的路径，供前端读取或另存
+#[tauri::command]
+pub async fn miwear_get_download_path(id: String) -> Result<String, MiWearError> {
+    crate::miwear::device::download::path_of(&id)
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(MiWearError::from)
+}
+
+#[tauri::command]
+pub async fn miwear_remove_download(id: String) -> Result<bool, MiWearError> {
+    crate::miwear::device::download::remove(&id).map_err(MiWearError::from)
+}
+
+// 运动健康数据
+/// 解析下载文件、读写健康数据库都会阻塞，放到阻塞线程执行
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> anyhow::Result<T> + Send + 'static,
+) -> Result<T, MiWearError> {
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| MiWearError::from(e.to_string()))?
+        .map_err(MiWearError::from)
+}
+
+#[tauri::command]
+pub async fn fitness_list_activities() -> Result<Vec<ActivityInfo>, MiWearError> {
+    run_blocking(|| anyhow::Ok(crate::fitness::list_activities())).await
+}
+
+#[tauri::command]
+pub async fn fitness_get_daily_summaries() -> Result<Vec<DailySummary>, MiWearError> {
+    run_blocking(|| anyhow::Ok(crate::fitness::load().daily_summaries())).await
+}
+
+#[tauri::command]
+pub async fn fitness_export_activity(
+    id: String,
+    format: ExportFormat,
+    path: String,
+) -> Result<(), MiWearError> {
+    run_blocking(move || {
+        crate::fitness::export_activity_to(&id, format, std::path::Path::new(&path))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn fitness_export_daily_csv(path: String) -> Result<(), MiWearError> {
+    run_blocking(move || crate::fitness::export_daily_csv_to(std::path::Path::new(&path))).await
+}
+
+// 本地健康数据库
+#[tauri::command]
+pub async fn health_query(query: HealthQuery) -> Result<HealthRecords, MiWearError> {
+    run_blocking(move || crate::health::query(&query)).await
+}
+
+/// 含完整轨迹的运动记录
+#[tauri::command]
+pub async fn health_get_workout(addr: String, id: String) -> Result<Option<Activity>, MiWearError> {
+    run_blocking(move || crate::health::get_workout(&addr, &id)).await
+}
+
+#[tauri::command]
+pub async fn health_list_devices() -> Result<Vec<String>, MiWearError> {
+    run_blocking(crate::health::devices).await
+}
+
+/// 导入尚未入库的下载文件，新下载会自动导入，这里用于手动补导
+#[tauri::command]
+pub async fn health_import_downloads() -> Result<ImportReport, MiWearError> {
+    run_blocking(crate::health::import_downloads).await
+}
+
+#[tauri::command]
+pub async fn health_clear(addr: String) -> Result<(), MiWearError> {
+    run_blocking(move || crate::health::clear(&addr)).await
+}
+
 // 插件系统API
 #[tauri::command]
 pub async fn plugsys_get_list() -> Vec<PluginManifest> {
@@ -803,16 +1022,32 @@ pub async fn get_login_mi_account(
 ) -> Result<MiAccountToken, String> {
     let account = login_mi_account(username, password, ua).await;
     match account {
-        Ok(res) => Ok(res),
+        Ok(res) => {
+            if let Err(e) = res.save() {
+                log::warn!("[MiAccount] Failed to save token: {e:#}");
+            }
+            Ok(res)
+        }
         Err(err) => Err(err.to_string()),
     }
 }
 
+// 前端API：退出小米账号，清除保存的 token
+#[tauri::command]
+pub fn logout_mi_account() -> Result<(), String> {
+    MiAccountToken::clear().map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_mi_device_list(
-    token: MiAccountToken,
+    token: Option<MiAccountToken>,
     ua: String,
 ) -> Result<DeviceListRespone, String> {
+    // 未传入时使用上次登录保存的 token
+    let token = token
+        .or_else(MiAccountToken::load)
+        .ok_or_else(|| "Not logged in to Mi account".to_string())?;
+
     let mut params = HashMap::<String, String>::new();
     params.insert(
         "data".to_string(),
@@ -838,6 +1073,31 @@ pub async fn get_mi_device_list(
     }
 }
 
+// 前端API：secrets 存储的模式与锁定状态
+#[tauri::command]
+pub fn secrets_status() -> Result<SecretsStatus, String> {
+    crate::secrets::status().map_err(|e| e.to_string())
+}
+
+// 前端API：用口令解锁 secrets，并把其中的 authkey / 账号 token 填回内存
+#[tauri::command]
+pub async fn secrets_unlock(passphrase: String) -> Result<(), String> {
+    crate::secrets::unlock(passphrase)
+        .await
+        .map_err(|e| e.to_string())?;
+    crate::config::reload_secrets();
+    crate::account::reload_secrets();
+    Ok(())
+}
+
+// 前端API：设置 secrets 口令，传入 null 时改回 keyfile 模式
+#[tauri::command]
+pub async fn secrets_set_passphrase(passphrase: Option<String>) -> Result<(), String> {
+    crate::secrets::set_passphrase(passphrase)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn add_new_device(name: String, addr: String, authkey: String) {
     let state: MiWearState = MiWearState {
@@ -852,6 +1112,9 @@ pub fn add_new_device(name: String, addr: String, authkey: String) {
         sec_keys: None,
         network_mtu: 800, /* 默认800 此值过大会导致表端buffer溢出 极限大概在900左右 设置为900会导致不稳定 */
         codename: String::new(),
+        fragment_delay_us: None,
+        scheduler: None,
+        max_tx_frame_len: None,
     };
 
     crate::config::write(|c| {
@@ -0,0 +1,652 @@
+//! 本地健康数据库
+//!
+//! 把手表同步来的心率、血氧、步数、睡眠阶段与运动记录存入 `app_data_dir/health.db`（SQLite），
+//! 所有数据按设备地址区分。导入以 (addr, 时间) 等主键 upsert，同一文件重复导入结果不变；
+//! 已导入的下载文件记录在 `imports` 表中，不会重复解析。
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    fitness::{
+        parser::{self, FileKind},
+        Activity, ActivityInfo, FitnessData, SleepStage,
+    },
+    miwear::{
+        device::download::{self, DownloadedFile},
+        packet::Channel,
+    },
+};
+
+const SCHEMA_VERSION: i32 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS heart_rate (
+    addr TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    bpm INTEGER NOT NULL,
+    PRIMARY KEY (addr, time)
+);
+CREATE TABLE IF NOT EXISTS spo2 (
+    addr TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    percent INTEGER NOT NULL,
+    PRIMARY KEY (addr, time)
+);
+CREATE TABLE IF NOT EXISTS steps (
+    addr TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    steps INTEGER NOT NULL,
+    calories INTEGER NOT NULL,
+    PRIMARY KEY (addr, time)
+);
+CREATE TABLE IF NOT EXISTS sleep_stages (
+    addr TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+    end_time INTEGER NOT NULL,
+    stage TEXT NOT NULL,
+    PRIMARY KEY (addr, start_time)
+);
+CREATE TABLE IF NOT EXISTS workouts (
+    addr TEXT NOT NULL,
+    id TEXT NOT NULL,
+    start INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (addr, id)
+);
+CREATE INDEX IF NOT EXISTS workouts_start ON workouts (addr, start);
+CREATE TABLE IF NOT EXISTS imports (
+    download_id TEXT PRIMARY KEY,
+    addr TEXT NOT NULL,
+    imported_at INTEGER NOT NULL
+);
+";
+
+static DB: OnceCell<Mutex<Connection>> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthMetric {
+    HeartRate,
+    Spo2,
+    Steps,
+    Sleep,
+    Workouts,
+}
+
+impl HealthMetric {
+    pub fn parse_name(name: &str) -> Option<Self> {
+        match name {
+            "heart_rate" | "heartRate" => Some(HealthMetric::HeartRate),
+            "spo2" => Some(HealthMetric::Spo2),
+            "steps" => Some(HealthMetric::Steps),
+            "sleep" => Some(HealthMetric::Sleep),
+            "workouts" => Some(HealthMetric::Workouts),
+            _ => None,
+        }
+    }
+}
+
+/// 时间范围为 [from, to)，unix 秒；addr 为空时查询所有设备
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthQuery {
+    pub metric: HealthMetric,
+    #[serde(default)]
+    pub addr: Option<String>,
+    pub from: i64,
+    pub to: i64,
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HeartRateRecord {
+    pub addr: String,
+    pub time: i64,
+    pub bpm: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Spo2Record {
+    pub addr: String,
+    pub time: i64,
+    pub percent: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepsRecord {
+    pub addr: String,
+    pub time: i64,
+    pub steps: u32,
+    pub calories: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SleepRecord {
+    pub addr: String,
+    pub start: i64,
+    pub end: i64,
+    pub stage: SleepStage,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkoutRecord {
+    pub addr: String,
+    #[serde(flatten)]
+    pub info: ActivityInfo,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum HealthRecords {
+    HeartRate(Vec<HeartRateRecord>),
+    Spo2(Vec<Spo2Record>),
+    Steps(Vec<StepsRecord>),
+    Sleep(Vec<SleepRecord>),
+    Workouts(Vec<WorkoutRecord>),
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// 已导入过或不是运动健康文件
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+pub async fn init(app: &AppHandle) -> Result<()> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .context("app_data_dir unavailable; did you call after app was ready?")?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .with_context(|| format!("create app data dir: {}", dir.display()))?;
+
+    let path = dir.join("health.db");
+    let conn = Connection::open(&path)
+        .with_context(|| format!("open health database: {}", path.display()))?;
+    conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
+    create_schema(&conn)?;
+    DB.set(Mutex::new(conn)).ok();
+
+    // 补导入本地已有但尚未入库的下载
+    tauri::async_runtime::spawn_blocking(|| match import_downloads() {
+        Ok(report) if report.imported > 0 || report.failed > 0 => log::info!(
+            "[HealthStore] Imported {} downloads ({} failed)",
+            report.imported,
+            report.failed
+        ),
+        Ok(_) => {}
+        Err(e) => log::error!("[HealthStore] Backfill failed: {:#}", e),
+    });
+    Ok(())
+}
+
+fn create_schema(conn: &Connection) -> Result<()> {
+    conn.execute_batch(SCHEMA).context("create health schema")?;
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    Ok(())
+}
+
+fn with_db<R>(f: impl FnOnce(&mut Connection) -> Result<R>) -> Result<R> {
+    let db = DB.get().context("Health store is not initialized")?;
+    f(&mut db.lock())
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+fn stage_name(stage: SleepStage) -> &'static str {
+    match stage {
+        SleepStage::Awake => "awake",
+        SleepStage::Light => "light",
+        SleepStage::Deep => "deep",
+        SleepStage::Rem => "rem",
+    }
+}
+
+fn parse_stage(name: &str) -> SleepStage {
+    match name {
+        "light" => SleepStage::Light,
+        "deep" => SleepStage::Deep,
+        "rem" => SleepStage::Rem,
+        _ => SleepStage::Awake,
+    }
+}
+
+/// 下载完成后调用；非 FileFitness 文件直接忽略
+pub fn on_downloaded(file: &DownloadedFile) {
+    if DB.get().is_none() {
+        return;
+    }
+    match import_download(file) {
+        Ok(true) => log::info!(
+            "[HealthStore] Imported download {} from {}",
+            file.id,
+            file.addr
+        ),
+        Ok(false) => {}
+        Err(e) => log::warn!("[HealthStore] Failed to import {}: {:#}", file.id, e),
+    }
+}
+
+/// 导入一个下载文件，已导入过或不是运动健康文件时返回 false
+pub fn import_download(file: &DownloadedFile) -> Result<bool> {
+    if file.channel != format!("{:?}", Channel::FileFitness) {
+        return Ok(false);
+    }
+    let imported: bool = with_db(|conn| {
+        Ok(conn
+            .query_row(
+                "SELECT 1 FROM imports WHERE download_id = ?1",
+                params![file.id],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    })?;
+    if imported {
+        return Ok(false);
+    }
+
+    let parsed = parser::parse(&download::read(&file.id)?)?;
+    with_db(|conn| {
+        let tx = conn.transaction()?;
+        import_file(&tx, &file.addr, parsed)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO imports (download_id, addr, imported_at) VALUES (?1, ?2, ?3)",
+            params![file.id, file.addr, now()],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    })
+}
+
+/// 导入所有尚未入库的 FileFitness 下载
+pub fn import_downloads() -> Result<ImportReport> {
+    let done: HashSet<String> = with_db(|conn| {
+        let mut stmt = conn.prepare("SELECT download_id FROM imports")?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(ids)
+    })?;
+
+    let mut report = ImportReport::default();
+    for file in download::list() {
+        if done.contains(&file.id) {
+            report.skipped += 1;
+            continue;
+        }
+        match import_download(&file) {
+            Ok(true) => report.imported += 1,
+            Ok(false) => report.skipped += 1,
+            Err(e) => {
+                log::warn!("[HealthStore] Failed to import {}: {:#}", file.id, e);
+                report.failed += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn import_file(tx: &Transaction, addr: &str, file: parser::FitnessFile) -> Result<()> {
+    // 运动的汇总与轨迹分两个文件到达，先取出已入库的部分再合并
+    let mut data = FitnessData::default();
+    if file.id.kind == FileKind::Sports {
+        if let Some(existing) = load_workout(tx, addr, &file.id.activity_id())? {
+            data.activities.push(existing);
+        }
+    }
+    data.add(file);
+
+    for sample in &data.samples {
+        tx.prepare_cached(
+            "INSERT OR REPLACE INTO steps (addr, time, steps, calories) VALUES (?1, ?2, ?3, ?4)",
+        )?
+        .execute(params![addr, sample.time, sample.steps, sample.calories])?;
+        if let Some(bpm) = sample.heart_rate {
+            tx.prepare_cached(
+                "INSERT OR REPLACE INTO heart_rate (addr, time, bpm) VALUES (?1, ?2, ?3)",
+            )?
+            .execute(params![addr, sample.time, bpm])?;
+        }
+        if let Some(percent) = sample.spo2 {
+            tx.prepare_cached(
+                "INSERT OR REPLACE INTO spo2 (addr, time, percent) VALUES (?1, ?2, ?3)",
+            )?
+            .execute(params![addr, sample.time, percent])?;
+        }
+    }
+
+    for session in &data.sleep {
+        for segment in &session.segments {
+            tx.prepare_cached(
+                "INSERT OR REPLACE INTO sleep_stages (addr, start_time, end_time, stage) \
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?
+            .execute(params![
+                addr,
+                segment.start,
+                segment.end,
+                stage_name(segment.stage)
+            ])?;
+        }
+    }
+
+    for activity in &data.activities {
+        tx.prepare_cached(
+            "INSERT OR REPLACE INTO workouts (addr, id, start, data) VALUES (?1, ?2, ?3, ?4)",
+        )?
+        .execute(params![
+            addr,
+            activity.id,
+            activity.start,
+            serde_json::to_string(activity)?
+        ])?;
+    }
+    Ok(())
+}
+
+fn load_workout(conn: &Connection, addr: &str, id: &str) -> Result<Option<Activity>> {
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT data FROM workouts WHERE addr = ?1 AND id = ?2",
+            params![addr, id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    json.map(|json| serde_json::from_str(&json).context("corrupted workout record"))
+        .transpose()
+}
+
+/// 含完整轨迹的运动记录
+pub fn get_workout(addr: &str, id: &str) -> Result<Option<Activity>> {
+    with_db(|conn| load_workout(conn, addr, id))
+}
+
+pub fn query(q: &HealthQuery) -> Result<HealthRecords> {
+    // SQLite 中 LIMIT -1 表示不限
+    let limit = q.limit.map(i64::from).unwrap_or(-1);
+    let args = params![q.addr, q.from, q.to, limit];
+
+    with_db(|conn| {
+        let records = match q.metric {
+            HealthMetric::HeartRate => HealthRecords::HeartRate(
+                conn.prepare_cached(
+                    "SELECT addr, time, bpm FROM heart_rate \
+                     WHERE (?1 IS NULL OR addr = ?1) AND time >= ?2 AND time < ?3 \
+                     ORDER BY time LIMIT ?4",
+                )?
+                .query_map(args, |row| {
+                    Ok(HeartRateRecord {
+                        addr: row.get(0)?,
+                        time: row.get(1)?,
+                        bpm: row.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?,
+            ),
+            HealthMetric::Spo2 => HealthRecords::Spo2(
+                conn.prepare_cached(
+                    "SELECT addr, time, percent FROM spo2 \
+                     WHERE (?1 IS NULL OR addr = ?1) AND time >= ?2 AND time < ?3 \
+                     ORDER BY time LIMIT ?4",
+                )?
+                .query_map(args, |row| {
+                    Ok(Spo2Record {
+                        addr: row.get(0)?,
+                        time: row.get(1)?,
+                        percent: row.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?,
+            ),
+            HealthMetric::Steps => HealthRecords::Steps(
+                conn.prepare_cached(
+                    "SELECT addr, time, steps, calories FROM steps \
+                     WHERE (?1 IS NULL OR addr = ?1) AND time >= ?2 AND time < ?3 \
+                     ORDER BY time LIMIT ?4",
+                )?
+                .query_map(args, |row| {
+                    Ok(StepsRecord {
+                        addr: row.get(0)?,
+                        time: row.get(1)?,
+                        steps: row.get(2)?,
+                        calories: row.get(3)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?,
+            ),
+            HealthMetric::Sleep => HealthRecords::Sleep(
+                conn.prepare_cached(
+                    "SELECT addr, start_time, end_time, stage FROM sleep_stages \
+                     WHERE (?1 IS NULL OR addr = ?1) AND start_time >= ?2 AND start_time < ?3 \
+                     ORDER BY start_time LIMIT ?4",
+                )?
+                .query_map(args, |row| {
+                    Ok(SleepRecord {
+                        addr: row.get(0)?,
+                        start: row.get(1)?,
+                        end: row.get(2)?,
+                        stage: parse_stage(&row.get::<_, String>(3)?),
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?,
+            ),
+            HealthMetric::Workouts => {
+                let rows: Vec<(String, String)> = conn
+                    .prepare_cached(
+                        "SELECT addr, data FROM workouts \
+                         WHERE (?1 IS NULL OR addr = ?1) AND start >= ?2 AND start < ?3 \
+                         ORDER BY start LIMIT ?4",
+                    )?
+                    .query_map(args, |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<_>>()?;
+                let mut workouts = Vec::with_capacity(rows.len());
+                for (addr, json) in rows {
+                    let activity: Activity =
+                        serde_json::from_str(&json).context("corrupted workout record")?;
+                    workouts.push(WorkoutRecord {
+                        addr,
+                        info: ActivityInfo::from(&activity),
+                    });
+                }
+                HealthRecords::Workouts(workouts)
+            }
+        };
+        Ok(records)
+    })
+}
+
+/// 有数据的设备地址
+pub fn devices() -> Result<Vec<String>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT addr FROM steps UNION SELECT addr FROM heart_rate \
+             UNION SELECT addr FROM spo2 UNION SELECT addr FROM sleep_stages \
+             UNION SELECT addr FROM workouts ORDER BY addr",
+        )?;
+        let addrs = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(addrs)
+    })
+}
+
+/// 删除某台设备的全部数据，之后可以重新导入
+pub fn clear(addr: &str) -> Result<()> {
+    with_db(|conn| {
+        let tx = conn.transaction()?;
+        for table in [
+            "heart_rate",
+            "spo2",
+            "steps",
+            "sleep_stages",
+            "workouts",
+            "imports",
+        ] {
+            tx.execute(&format!("DELETE FROM {} WHERE addr = ?1", table), [addr])?;
+        }
+        tx.commit()?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fitness::{
+        parser::{DetailType, FileId, FitnessContent, FitnessFile},
+        MinuteSample, SleepSegment, SleepSession, TrackPoint, WorkoutSummary,
+    };
+
+    const ADDR: &str = "AA:BB:CC:DD:EE:FF";
+    const TS: u32 = 1_700_000_000;
+
+    fn file_id(kind: FileKind, subtype: u8, detail: DetailType) -> FileId {
+        FileId {
+            timestamp: TS,
+            timezone: 32,
+            version: 2,
+            kind,
+            subtype,
+            detail,
+        }
+    }
+
+    fn point(time: i64) -> TrackPoint {
+        TrackPoint {
+            time,
+            latitude: 39.9,
+            longitude: 116.4,
+            altitude: None,
+            speed: Some(3.0),
+            heart_rate: None,
+        }
+    }
+
+    /// 日常明细、睡眠，以及同一次运动的汇总与轨迹
+    fn files() -> Vec<FitnessFile> {
+        let start = TS as i64;
+        let sample = |time, heart_rate, spo2| MinuteSample {
+            time,
+            utc_offset: 8 * 3600,
+            steps: 12,
+            calories: 1,
+            heart_rate,
+            spo2,
+        };
+        vec![
+            FitnessFile {
+                id: file_id(FileKind::Activity, 0, DetailType::Details),
+                content: FitnessContent::DailyDetails(vec![
+                    sample(start, Some(70), Some(98)),
+                    sample(start + 60, None, None),
+                ]),
+            },
+            FitnessFile {
+                id: file_id(FileKind::Activity, 3, DetailType::Details),
+                content: FitnessContent::Sleep(SleepSession {
+                    bed_time: start,
+                    wake_time: start + 3600,
+                    utc_offset: 8 * 3600,
+                    segments: vec![
+                        SleepSegment {
+                            stage: SleepStage::Light,
+                            start,
+                            end: start + 1800,
+                        },
+                        SleepSegment {
+                            stage: SleepStage::Deep,
+                            start: start + 1800,
+                            end: start + 3600,
+                        },
+                    ],
+                }),
+            },
+            FitnessFile {
+                id: file_id(FileKind::Sports, 1, DetailType::Summary),
+                content: FitnessContent::WorkoutSummary(WorkoutSummary {
+                    start,
+                    end: start + 600,
+                    duration: 600,
+                    distance: 1500,
+                    calories: 90,
+                    ..Default::default()
+                }),
+            },
+            FitnessFile {
+                id: file_id(FileKind::Sports, 1, DetailType::GpsTrack),
+                content: FitnessContent::WorkoutTrack(vec![point(start), point(start + 1)]),
+            },
+        ]
+    }
+
+    fn import_all(conn: &mut Connection) {
+        for file in files() {
+            let tx = conn.transaction().unwrap();
+            import_file(&tx, ADDR, file).unwrap();
+            tx.commit().unwrap();
+        }
+    }
+
+    /// 每张表按主键排序后的全部行
+    fn snapshot(conn: &Connection) -> Vec<Vec<String>> {
+        ["heart_rate", "spo2", "steps", "sleep_stages", "workouts"]
+            .iter()
+            .flat_map(|table| {
+                let mut stmt = conn
+                    .prepare(&format!("SELECT * FROM {} ORDER BY 1, 2", table))
+                    .unwrap();
+                let columns = stmt.column_count();
+                stmt.query_map([], |row| {
+                    (0..columns)
+                        .map(|i| Ok(format!("{:?}", row.get_ref(i)?)))
+                        .collect::<rusqlite::Result<Vec<_>>>()
+                })
+                .unwrap()
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn importing_twice_is_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+
+        import_all(&mut conn);
+        let first = snapshot(&conn);
+        import_all(&mut conn);
+        assert_eq!(snapshot(&conn), first);
+
+        assert_eq!(count(&conn, "steps"), 2);
+        assert_eq!(count(&conn, "heart_rate"), 1);
+        assert_eq!(count(&conn, "spo2"), 1);
+        assert_eq!(count(&conn, "sleep_stages"), 2);
+        assert_eq!(count(&conn, "workouts"), 1);
+
+        // 汇总与轨迹合并进同一条运动记录，重复导入不会让轨迹翻倍
+        let id = file_id(FileKind::Sports, 1, DetailType::Summary).activity_id();
+        let workout = load_workout(&conn, ADDR, &id).unwrap().unwrap();
+        assert_eq!(workout.summary.unwrap().distance, 1500);
+        assert_eq!(workout.track.len(), 2);
+    }
+}
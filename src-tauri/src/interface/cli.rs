@@ -1,4 +1,10 @@
-use crate::{interface, miwear::device::resutils::get_file_type};
+use crate::{
+    interface,
+    miwear::{
+        device::resutils::get_file_type,
+        dissector::{self, DissectKeys},
+    },
+};
 use serde::Serialize;
 use url::Url;
 use std::path::Path;
@@ -80,6 +86,40 @@ pub async fn handle(app: AppHandle, args: &[String]) -> bool {
             }
             true
         }
+        "dissect" => {
+            let Some(file) = args.get(1) else {
+                println!("Usage: astrobox dissect <file> [--enc-key <hex>] [--dec-key <hex>] [--out <dir>]");
+                return false;
+            };
+            let tail = &args[2..];
+            let mut enc_key = None;
+            let mut dec_key = None;
+            let mut out_dir = None;
+            for i in 0..tail.len() {
+                if i + 1 >= tail.len() {
+                    break;
+                }
+                match tail[i].as_str() {
+                    "--enc-key" => enc_key = Some(tail[i + 1].as_str()),
+                    "--dec-key" => dec_key = Some(tail[i + 1].as_str()),
+                    "--out" => out_dir = Some(Path::new(&tail[i + 1])),
+                    _ => {}
+                }
+            }
+            let ret = DissectKeys::from_hex(enc_key, dec_key)
+                .and_then(|keys| dissector::dissect_file(file, &keys, out_dir));
+            match ret {
+                Ok(timeline) => {
+                    println!("{}", timeline);
+                    log::info!("[Dissector] {}:\n{}", file, timeline);
+                }
+                Err(e) => {
+                    eprintln!("{:#}", e);
+                    log::error!("[Dissector] Failed to dissect {}: {:#}", file, e);
+                }
+            }
+            false
+        }
         _ => {
             if args[0].starts_with("astrobox://") {
                 if let std::result::Result::Ok(url) = Url::parse(&args[0]) {
@@ -10,16 +10,19 @@ pub mod buildinfo;
 pub mod community;
 pub mod config;
 pub mod crypto;
+pub mod fitness;
 pub mod account;
 pub mod frontapi;
 pub mod frontmodels;
 pub mod fs;
+pub mod health;
 pub mod interface;
 pub mod logger;
 pub mod miwear;
 pub mod pb;
 pub mod pluginstore;
 pub mod pluginsystem;
+pub mod secrets;
 pub mod tools;
 pub mod tracker;
 pub mod auth;
@@ -91,10 +94,36 @@ pub fn run() {
 
             println!("Initializing logger...");
             logger::init()?;
+            println!("Initializing secrets store...");
+            // 密钥文件损坏等情况下保持锁定，authkey 暂留在 config.json 中，不影响启动
+            if let Err(e) = tauri::async_runtime::block_on(secrets::init(&app.handle())) {
+                log::error!("[Secrets] Failed to open secrets store, staying locked: {:#}", e);
+            }
             println!("Initializing configuration system...");
             tauri::async_runtime::block_on(config::init(&app.handle()))?;
             println!("Initializing account store...");
             tauri::async_runtime::block_on(account::init(&app.handle()))?;
+            // 以下存储初始化失败只影响对应功能，记录日志后继续启动
+            println!("Initializing mass resume journal...");
+            if let Err(e) =
+                tauri::async_runtime::block_on(miwear::device::mass::resume::init(&app.handle()))
+            {
+                log::error!("[MassResume] Failed to load resume journal: {:#}", e);
+            }
+            println!("Initializing file download store...");
+            if let Err(e) =
+                tauri::async_runtime::block_on(miwear::device::download::init(&app.handle()))
+            {
+                log::error!("[FileDownload] Failed to initialize download store: {:#}", e);
+            }
+            println!("Initializing health store...");
+            if let Err(e) = tauri::async_runtime::block_on(health::init(&app.handle())) {
+                log::error!("[HealthStore] Failed to open health store: {:#}", e);
+            }
+            println!("Starting reconnect supervisor...");
+            miwear::reconnect::start_supervisor();
+            println!("Registering channel handlers...");
+            miwear::channels::collector::init();
             println!("Initializing plugin system...");
             pluginsystem::init(config::read(|c| c.clone().plugin_dir).into())?;
 
@@ -176,6 +205,9 @@ pub fn run() {
             frontapi::miwear_stop_scan,
             frontapi::miwear_connect,
             frontapi::miwear_disconnect,
+            frontapi::miwear_list_connected_devices,
+            frontapi::miwear_set_primary_device,
+            frontapi::miwear_get_connection_state,
             frontapi::miwear_remove_device,
             frontapi::miwear_get_state,
             frontapi::miwear_get_device_state,
@@ -184,6 +216,7 @@ pub fn run() {
             frontapi::miwear_install_third_app,
             frontapi::miwear_install_watchface,
             frontapi::miwear_install_firmware,
+            frontapi::miwear_cancel_operation,
             frontapi::miwear_is_sending_mass,
             frontapi::miwear_get_watchface_list,
             frontapi::miwear_uninstall_watchface,
@@ -195,6 +228,24 @@ pub fn run() {
             frontapi::miwear_get_device_info,
             frontapi::miwear_get_unlock_code,
             frontapi::miwear_debug_get_commandpool_json_table,
+            frontapi::miwear_get_retransmit_stats,
+            frontapi::miwear_start_capture,
+            frontapi::miwear_stop_capture,
+            frontapi::miwear_get_capture,
+            frontapi::miwear_get_scheduler,
+            frontapi::miwear_set_scheduler,
+            frontapi::miwear_list_downloads,
+            frontapi::miwear_get_download_path,
+            frontapi::miwear_remove_download,
+            frontapi::fitness_list_activities,
+            frontapi::fitness_get_daily_summaries,
+            frontapi::fitness_export_activity,
+            frontapi::fitness_export_daily_csv,
+            frontapi::health_query,
+            frontapi::health_get_workout,
+            frontapi::health_list_devices,
+            frontapi::health_import_downloads,
+            frontapi::health_clear,
             // Plugin System API
             frontapi::plugsys_get_list,
             frontapi::plugsys_get_state,
@@ -239,6 +290,10 @@ pub fn run() {
             frontapi::get_build_info,
             frontapi::get_login_mi_account,
             frontapi::get_mi_device_list,
+            frontapi::logout_mi_account,
+            frontapi::secrets_status,
+            frontapi::secrets_unlock,
+            frontapi::secrets_set_passphrase,
             frontapi::add_new_device,
             frontapi::image_url_to_base64_data_url,
             frontapi::app_get_current_log,
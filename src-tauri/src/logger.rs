@@ -2,7 +2,7 @@ use chrono::Local;
 use fern::colors::{Color, ColoredLevelConfig};
 use log::LevelFilter;
 use once_cell::sync::{Lazy, OnceCell};
-use std::{fs, io};
+use std::{fs, io, path::PathBuf};
 use tauri::{Emitter, Manager};
 use crossbeam::channel;
 
@@ -18,6 +18,16 @@ pub fn current_log_path() -> Option<&'static str> {
     LOG_FILE_PATH.get().map(|s| s.as_str())
 }
 
+/// 运行日志与各类抓包文件所在目录，没有 AppHandle 时（例如在测试中通过回环链路运行）
+/// 落到系统临时目录
+pub fn rslogs_dir() -> PathBuf {
+    crate::APP_HANDLE
+        .get()
+        .and_then(|app| app.path().app_log_dir().ok())
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rslogs")
+}
+
 pub fn read_current_log() -> Option<String> {
     current_log_path().and_then(|p| fs::read_to_string(p).ok())
 }
@@ -1,39 +1,121 @@
 use once_cell::sync::Lazy;
 use tauri::Emitter;
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::broadcast::{
+    self,
+    error::{RecvError, TryRecvError},
+};
 
 pub mod bleuuids;
 pub mod btrecv;
+pub mod capture;
+pub mod channels;
+pub mod connection;
 pub mod device;
+pub mod dissector;
+pub mod error;
 pub mod network_stack;
 pub mod packet;
+pub mod reconnect;
+pub mod registry;
+pub mod replay;
+pub mod simulator;
 pub mod command_pool;
+pub mod transport;
 
+pub use connection::ConnectionState;
 pub use device::{MiWearDevice, SecurityKeys};
+pub use error::MiWearError;
+pub use registry::DeviceRegistry;
 
-pub static CONNECTED_DEVICE: Lazy<RwLock<Option<Arc<MiWearDevice>>>> =
-    Lazy::new(|| tokio::sync::RwLock::new(None));
+/// 所有已连接的设备
+pub static DEVICES: Lazy<DeviceRegistry> = Lazy::new(DeviceRegistry::default);
 
 pub const DISCONNECT_EVENT: &str = "device-disconnected";
 
-static DISCONNECT_TX: Lazy<broadcast::Sender<()>> = Lazy::new(|| {
+/// 广播断开的设备地址
+static DISCONNECT_TX: Lazy<broadcast::Sender<String>> = Lazy::new(|| {
     let (tx, _rx) = broadcast::channel(16);
     tx
 });
 
-pub async fn set_connected_device(dev: Option<Arc<MiWearDevice>>) {
-    *CONNECTED_DEVICE.write().await = dev;
+/// 登记新连接的设备并设为 primary
+pub async fn register_device(dev: Arc<MiWearDevice>) {
+    insert_device(dev, true).await;
 }
 
-pub fn subscribe_disconnect() -> broadcast::Receiver<()> {
+/// 登记设备，成为 primary 时同时记为配置中的当前设备
+pub async fn insert_device(dev: Arc<MiWearDevice>, make_primary: bool) {
+    let state = dev.state.read().await.clone();
+    if DEVICES.insert(dev, make_primary).await {
+        crate::config::write(|c| c.current_device = Some(state));
+    }
+}
+
+/// 切换 primary 设备并记为配置中的当前设备，设备未连接时返回 false
+pub async fn set_primary_device(addr: &str) -> bool {
+    let Some(device) = DEVICES.get(Some(addr)).await else {
+        return false;
+    };
+    if !DEVICES.set_primary(addr).await {
+        return false;
+    }
+    let state = device.state.read().await.clone();
+    crate::config::write(|c| c.current_device = Some(state));
+    true
+}
+
+/// 从设备表中移除该连接，同一地址已被新连接占用时返回 false
+pub async fn unregister_device(dev: &MiWearDevice) -> bool {
+    DEVICES.remove(dev).await
+}
+
+/// `addr` 为 None 时返回 primary 设备
+pub async fn get_device(addr: Option<&str>) -> Option<Arc<MiWearDevice>> {
+    DEVICES.get(addr).await
+}
+
+/// 所有已连接设备的状态，primary 设备排在最前
+pub async fn connected_device_states() -> Vec<device::MiWearState> {
+    let primary = DEVICES.primary_addr().await;
+    let mut states = Vec::new();
+    for dev in DEVICES.all().await {
+        states.push(dev.state.read().await.clone());
+    }
+    states.sort_by_key(|st| Some(&st.addr) != primary.as_ref());
+    states
+}
+
+pub fn subscribe_disconnect() -> broadcast::Receiver<String> {
     DISCONNECT_TX.subscribe()
 }
 
-pub fn notify_disconnect() {
-    let _ = DISCONNECT_TX.send(());
+pub fn notify_disconnect(addr: &str) {
+    let _ = DISCONNECT_TX.send(addr.to_string());
     if let Some(app) = crate::APP_HANDLE.get() {
-        let _ = app.emit(DISCONNECT_EVENT, ());
+        let _ = app.emit(DISCONNECT_EVENT, addr);
+    }
+}
+
+/// 等待指定设备断开（忽略其他设备的断开通知）
+pub async fn wait_disconnect(rx: &mut broadcast::Receiver<String>, addr: &str) {
+    loop {
+        match rx.recv().await {
+            Ok(a) if a == addr => return,
+            Ok(_) | Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// 非阻塞地检查指定设备是否已断开
+pub fn has_disconnected(rx: &mut broadcast::Receiver<String>, addr: &str) -> bool {
+    loop {
+        match rx.try_recv() {
+            Ok(a) if a == addr => return true,
+            Ok(_) | Err(TryRecvError::Lagged(_)) => continue,
+            Err(_) => return false,
+        }
     }
 }
 
@@ -41,25 +123,30 @@ pub async fn with_connected_device<F, R>(f: F) -> Option<R>
 where
     F: FnOnce(Arc<MiWearDevice>) -> R,
 {
-    let guard = CONNECTED_DEVICE.read().await;
-    guard.as_ref().cloned().map(f)
+    get_device(None).await.map(f)
 }
 
-pub async fn with_connected_device_async<F, Fut, R, E>(f: F) -> Result<R, String>
+/// 对 primary 设备执行操作
+pub async fn with_connected_device_async<F, Fut, R, E>(f: F) -> Result<R, MiWearError>
 where
     F: FnOnce(Arc<MiWearDevice>) -> Fut,
     Fut: std::future::Future<Output = Result<R, E>>,
-    E: ToString,
+    E: Into<MiWearError>,
 {
-    let dev = {
-        let guard = CONNECTED_DEVICE.read().await;
-        guard.as_ref().cloned()
-    };
+    with_device_async(None, f).await
+}
 
-    let dev = match dev {
+/// 对指定地址的设备执行操作，`addr` 为 None 时使用 primary 设备
+pub async fn with_device_async<F, Fut, R, E>(addr: Option<&str>, f: F) -> Result<R, MiWearError>
+where
+    F: FnOnce(Arc<MiWearDevice>) -> Fut,
+    Fut: std::future::Future<Output = Result<R, E>>,
+    E: Into<MiWearError>,
+{
+    let dev = match get_device(addr).await {
         Some(dev) => dev,
-        None => return Err("No devices are connected".to_owned()),
+        None => return Err(MiWearError::NotConnected),
     };
 
-    f(dev).await.map_err(|e| e.to_string())
+    f(dev).await.map_err(Into::into)
 }
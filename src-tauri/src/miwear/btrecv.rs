@@ -4,7 +4,7 @@ use prost::Message;
 use crate::{
     miwear::packet::{Channel, MiWearPacket, OpCode, PacketData, PktType},
     pb::{self},
-    tools::{hex_stream_to_bytes, to_hex_string},
+    tools::to_hex_string,
 };
 use super::device::MiWearDevice;
 
@@ -17,160 +17,204 @@ pub async fn handle_bt_packet(device: Arc<MiWearDevice>, data: Vec<u8>) {
 
     /* ────────── Hello 阶段帧 ────────── */
     if data_str.starts_with("badcfe") {
-        super::device::hello::session_config_packet(device.clone())
-            .await
-            .unwrap();
+        if let Err(e) = super::device::hello::session_config_packet(device.clone()).await {
+            log::error!("[MiWearBTRecv] Failed to send SessionConfig: {:#}", e);
+            return;
+        }
+        device.hello_acked.send_replace(true);
         return;
     }
 
     /* ────────── MiWear Packet 解析 ────────── */
-    match MiWearPacket::parse_all(&data) {
-        Ok(mipkts) => {
-            for mipkt in mipkts {
+    let parsed = MiWearPacket::parse_all(&data);
+    for e in &parsed.errors {
+        log::error!("Error parsing packet at offset {}: {}", e.offset, e.error);
+    }
+    if parsed.skipped_bytes > 0 {
+        log::warn!(
+            "[MiWearBTRecv] Skipped {} bytes of unparseable data",
+            parsed.skipped_bytes
+        );
+    }
+    for mipkt in parsed.packets {
+        handle_miwear_packet(device.clone(), mipkt).await;
+    }
+}
+
+/// 处理一个已完成解帧、CRC 校验通过的 MiWear 包
+pub async fn handle_miwear_packet(device: Arc<MiWearDevice>, mipkt: MiWearPacket) {
+    log::info!(
+        "[MiWearBTRecv] RX seq={} type={:?} (waiting for {:?})",
+        mipkt.seq,
+        mipkt.pkt_type,
+        device
+            .pending_seq
+            .iter()
+            .map(|e| *e.key())
+            .collect::<Vec<_>>()
+    );
+
+    match mipkt.pkt_type {
+        PktType::Ack => match device.ack_window.ack(mipkt.seq) {
+            Ok(rtt) => {
+                if let Some(rtt) = rtt {
+                    device.pacer.on_ack(rtt);
+                }
                 log::info!(
-                    "[MiWearBTRecv] RX seq={} type={:?} (waiting for {:?})",
+                    "[MiWearBTRecv] BTRecv: Matched ACK seq {} -> delivered",
+                    mipkt.seq
+                );
+            }
+            Err(e) => {
+                log::error!("[MiWearBTRecv] Protocol error: {}", e);
+            }
+        },
+        PktType::SessionConfig => {
+            // 设备声明的最大帧长是它能接收的上限，只用来限制发往设备的分片；
+            // 接收方向仍以 App 声明的 MAX_RX_FRAME_LEN 为准
+            match super::device::hello::negotiated_frame_len(&mipkt.body) {
+                Some(len) => {
+                    device.state.write().await.max_tx_frame_len = Some(len);
+                    log::info!(
+                        "[MiWearBTRecv] SessionConfig Packet: device accepts frames up to {}",
+                        len
+                    );
+                }
+                None => log::info!("[MiWearBTRecv] SessionConfig Packet: ignored."),
+            }
+        }
+        PktType::Data => {
+            // 拆 Data Frame
+            let Some(payload) = mipkt.data_fields() else {
+                log::error!(
+                    "[MiWearBTRecv] Malformed data frame seq={}: {}",
                     mipkt.seq,
-                    mipkt.pkt_type,
-                    device
-                        .pending_seq
-                        .iter()
-                        .map(|e| *e.key())
-                        .collect::<Vec<_>>()
+                    to_hex_string(&mipkt.body)
                 );
+                return;
+            };
+
+            log::info!(
+                "[MiWearBTRecv] Data Packet: channel={:?}",
+                payload.channel
+            );
+
+            let mut content  = payload.data.to_vec();
+
+            if payload.opcode == OpCode::Encrypted {
+                let keys = { device.state.read().await.sec_keys.clone() };
+                let Some(dec_key) =
+                    keys.and_then(|keys| crate::tools::vec_to_array_16_opt(&keys.dec_key))
+                else {
+                    log::error!(
+                        "[MiWearBTRecv] Encrypted frame seq={} received without a valid session key, dropped",
+                        mipkt.seq
+                    );
+                    return;
+                };
+                content = crate::crypto::aesctr::aes128_ctr_crypt(
+                    &dec_key, &dec_key, &content,
+                );
+            }                        
 
-                match mipkt.pkt_type {
-                    PktType::Ack => {
-                        log::info!(
-                            "[MiWearBTRecv] ACK Packet: packet with seq {} acked.",
-                            mipkt.seq
-                        );
-                        if let Some((_, tx)) = device.pending_ack.remove(&()) {
-                            let _ = tx.send(()); 
-                            log::info!(
-                                "[MiWearBTRecv] BTRecv: Matched ACK seq {} -> delivered",
-                                mipkt.seq
-                            );
-                        }
-                    }
-                    PktType::SessionConfig => {
-                        log::info!("[MiWearBTRecv] SessionConfig Packet: ignored.");
-                    }
-                    PktType::Data => {
-                        // 拆 Data Frame
-                        let payload = mipkt.data_fields().unwrap();
-
-                        log::info!(
-                            "[MiWearBTRecv] Data Packet: channel={:?}",
-                            payload.channel
-                        );
-
-                        let mut content  = payload.data.to_vec();
-
-                        if payload.opcode == OpCode::Encrypted {
-                            let keys = { device.state.read().await.sec_keys.clone().unwrap() };
-                            let dec_key = crate::tools::vec_to_array_16_opt(&keys.dec_key).unwrap();
-                            content = crate::crypto::aesctr::aes128_ctr_crypt(
-                                &dec_key, &dec_key, &content,
-                            );
-                        }                        
-
-                        let pkt_data: PacketData = match payload.channel {
-                            Channel::Pb => {
-                                match pb::protocol::WearPacket::decode(Cursor::new(&content)) {
-                                    Ok(packet) => {
-                                        #[cfg(debug_assertions)] {
-                                            log::info!(
-                                                "[MiWearBTRecv] TYPE=Protobuf ID={} TYPE={:?}",
-                                                packet.__OPENSOURCE_DELETED__,
-                                                packet.__OPENSOURCE_DELETED__
-                                            );
-
-                                            log::info!(
-                                                "[MiWearBTRecv] Protobuf: {}",
-                                                serde_json::to_string(&packet).unwrap()
-                                            );
-                                        }
-
-                                        PacketData::PROTOBUF(packet)
-                                    }
-                                    Err(e) => {
-                                        log::error!("Error parsing packet to protobuf: {}", e);
-                                        return;
-                                    }
-                                }
-                            }
-                            Channel::Mass | Channel::FileFitness => {
-                                PacketData::DATA(content)
-                            }
-                            Channel::NetWork => {
-                                PacketData::NETWORK(content)
-                            }
-                            _ => {
-                                PacketData::UNSUPPORT(content)
+            // 交给通道处理器注册表，插件与 Rust 模块可按通道订阅
+            let handled = super::channels::dispatch(&device.addr, payload.channel, &content);
+
+            let pkt_data: PacketData = match payload.channel {
+                Channel::Pb => {
+                    match pb::protocol::WearPacket::decode(Cursor::new(&content)) {
+                        Ok(packet) => {
+                            #[cfg(debug_assertions)] {
+                                log::info!(
+                                    "[MiWearBTRecv] TYPE=Protobuf ID={} TYPE={:?}",
+                                    packet.__OPENSOURCE_DELETED__,
+                                    packet.__OPENSOURCE_DELETED__
+                                );
+
+                                log::info!(
+                                    "[MiWearBTRecv] Protobuf: {}",
+                                    serde_json::to_string(&packet).unwrap()
+                                );
                             }
-                        };
 
-                        if let Some(tx) = device.take_seq_pending(mipkt.seq) {
-                            let _ = tx.send(pkt_data.clone());
-                            log::info!("[MiWearBTRecv] BTRecv: TxRequestResultSent");
+                            PacketData::PROTOBUF(packet)
                         }
-
-                        if let PacketData::PROTOBUF(ref wp) = pkt_data.clone() {
-                            let key = (wp.__OPENSOURCE_DELETED__ as u32, wp.id);
-                            if let Some((_, sender)) = device.pending_proto.remove(&key) {
-                                let _ = sender.send(wp.clone());
-                                log::info!("[MiWearBTRecv] BTRecv: Matched ProtoKey({:?}) -> delivered", key);
-                            }
-                            if let Some(subs) = device.proto_subscribers.get(&(wp.__OPENSOURCE_DELETED__ as u32)) {
-                                for cb in subs.value().iter() {
-                                    let pkt = wp.clone();
-                                    cb(pkt.clone());
-                                }
-                            }
+                        Err(e) => {
+                            log::error!("Error parsing packet to protobuf: {}", e);
+                            return;
                         }
+                    }
+                }
+                Channel::Mass | Channel::FileFitness => {
+                    PacketData::DATA(content)
+                }
+                Channel::NetWork => {
+                    PacketData::NETWORK(content)
+                }
+                _ => {
+                    PacketData::UNSUPPORT(content)
+                }
+            };
 
-                        if let PacketData::NETWORK(ref network_packet) = pkt_data.clone() {
-                            log::info!("[MiWearBTRecv] BTRecv: Received TunPacket(size: {})", network_packet.len());
-                            
-                            let mut dhcp_process = false;
-
-                            super::network_stack::dhcp::process_dhcp(device.clone(), network_packet, &mut dhcp_process).await;
-
-                            if !dhcp_process {
-                                let tx_guard = device.network_tx.lock().await;
-                                if let Some(tx) = tx_guard.as_ref() {
-                                    if let Err(e) = tx.send(network_packet.clone()).await {
-                                        log::error!("[MiWearBTRecv] Failed to send packet to network stack: {}", e);
-                                    }
-                                } else {
-                                    log::warn!("[MiWearBTRecv] Received network packet, but stack is not running.");
-                                }
-                            }
-                        }
+            if let Some(tx) = device.take_seq_pending(mipkt.seq) {
+                let _ = tx.send(pkt_data.clone());
+                log::info!("[MiWearBTRecv] BTRecv: TxRequestResultSent");
+            }
 
-                        if let PacketData::UNSUPPORT(ref unk) = pkt_data.clone() {
-                            #[cfg(debug_assertions)] {
-                                log::info!("[MiWearBTRecv] BTRecv: Received UnsupportPacket: {}", to_hex_string(unk));
-                            }
+            if let PacketData::PROTOBUF(ref wp) = pkt_data.clone() {
+                let key = (wp.__OPENSOURCE_DELETED__ as u32, wp.id);
+                if let Some((_, sender)) = device.pending_proto.remove(&key) {
+                    let _ = sender.send(wp.clone());
+                    log::info!("[MiWearBTRecv] BTRecv: Matched ProtoKey({:?}) -> delivered", key);
+                }
+                if let Some(subs) = device.proto_subscribers.get(&(wp.__OPENSOURCE_DELETED__ as u32)) {
+                    for cb in subs.value().iter() {
+                        let pkt = wp.clone();
+                        cb(pkt.clone());
+                    }
+                }
+            }
+
+            if let PacketData::NETWORK(ref network_packet) = pkt_data.clone() {
+                log::info!("[MiWearBTRecv] BTRecv: Received TunPacket(size: {})", network_packet.len());
+                
+                let mut dhcp_process = false;
+
+                super::network_stack::dhcp::process_dhcp(device.clone(), network_packet, &mut dhcp_process).await;
+
+                if !dhcp_process {
+                    let tx_guard = device.network_tx.lock().await;
+                    if let Some(tx) = tx_guard.as_ref() {
+                        if let Err(e) = tx.send(network_packet.clone()).await {
+                            log::error!("[MiWearBTRecv] Failed to send packet to network stack: {}", e);
                         }
+                    } else {
+                        log::warn!("[MiWearBTRecv] Received network packet, but stack is not running.");
+                    }
+                }
+            }
 
-                        let _guard = device.send_lock.lock().await;
-                        // 回 ACK
-                        device
-                            .send(
-                                hex_stream_to_bytes(&format!("a5a501{:02x}00000000", mipkt.seq))
-                                    .unwrap(),
-                            )
-                            .await
-                            .unwrap();
-
-                        log::info!("[MiWearBTRecv] BTRecv: Data process finished");
+            if let PacketData::UNSUPPORT(ref unk) = pkt_data.clone() {
+                if !handled {
+                    #[cfg(debug_assertions)] {
+                        log::info!("[MiWearBTRecv] BTRecv: Received UnsupportPacket: {}", to_hex_string(unk));
                     }
                 }
             }
-        }
-        Err(e) => {
-            log::error!("Error parsing packet: {}", e);
+
+            let _guard = device.send_lock.lock().await;
+            // 回 ACK
+            let ack = MiWearPacket {
+                pkt_type: PktType::Ack,
+                seq: mipkt.seq,
+                body: vec![],
+            };
+            if let Err(e) = device.send(ack.encode()).await {
+                log::error!("[MiWearBTRecv] Failed to send ACK seq={}: {:#}", mipkt.seq, e);
+                return;
+            }
+
+            log::info!("[MiWearBTRecv] BTRecv: Data process finished");
         }
     }
 }
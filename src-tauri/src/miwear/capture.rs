@@ -0,0 +1,476 @@
+//! MiWear 链路层抓包
+//!
+//! 在 `MiWearDevice::send` 与蓝牙订阅回调处记录双向的每一个原始帧，
+//! 同时写出 pcapng 与 JSON Lines 两份文件。
+//!
+//! pcapng 使用 `LINKTYPE_USER0`（147），每个包的数据格式为：
+//!
+//! ```text
+//! 0      version        = 1
+//! 1      direction      0 = 设备 → 本机，1 = 本机 → 设备
+//! 2      kind           0 = MiWear 帧，1 = Hello 握手数据，2 = 无法解析的数据
+//! 3      pkt_type       Ack / SessionConfig / Data（非 MiWear 帧为 0）
+//! 4      seq
+//! 5      channel        非 Data 帧为 0
+//! 6      opcode         非 Data 帧为 0
+//! 7      flags          bit0 = payload 已解密
+//! 8..12  raw_len        u32 LE
+//! 12..   raw            链路上的原始帧
+//! ..     payload        明文负载（Data 帧去掉 channel/opcode 后的部分）
+//! ```
+//!
+//! 每个链路字节恰好写进一条记录：CRC 错误的帧、帧间垃圾和停止时未凑齐的残帧
+//! 都以 kind = 2 原样写出，离线解析时按字节流重新拼帧即可还原整条链路。
+//!
+//! 收发路径上只做拆帧，写文件交给每个会话独立的写盘线程，按固定间隔或停止时刷盘。
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use pcap_file::{
+    pcapng::{
+        blocks::{
+            enhanced_packet::{EnhancedPacketBlock, EnhancedPacketOption},
+            interface_description::InterfaceDescriptionBlock,
+        },
+        PcapNgWriter,
+    },
+    DataLink,
+};
+use serde::Serialize;
+
+use super::{
+    device::MiWearDevice,
+    packet::{Chunk, FrameSplitter, MiWearPacket, OpCode, PacketError, PktType, HELLO_MAGIC},
+};
+use crate::tools::to_hex_string;
+
+const FORMAT_VERSION: u8 = 1;
+
+/// 写盘线程刷盘的间隔，抓包文件最多落后这么久
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// pcapng EPB flags 中的方向位
+const EPB_FLAG_INBOUND: u32 = 0b01;
+const EPB_FLAG_OUTBOUND: u32 = 0b10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// 设备 → 本机
+    Rx,
+    /// 本机 → 设备
+    Tx,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureFiles {
+    pub pcapng: String,
+    pub jsonl: String,
+}
+
+/// JSON Lines 中的一行
+#[derive(Debug, Serialize)]
+struct CaptureRecord<'a> {
+    /// unix 时间戳（微秒）
+    ts_us: u128,
+    dir: Direction,
+    kind: &'static str,
+    pkt_type: Option<PktType>,
+    seq: Option<u8>,
+    channel: Option<String>,
+    opcode: Option<String>,
+    decrypted: bool,
+    raw: &'a str,
+    payload: Option<String>,
+    /// kind = "corrupt" 时的解析错误，帧间垃圾为 None
+    error: Option<PacketError>,
+}
+
+struct CaptureSession {
+    files: CaptureFiles,
+    /// 交给写盘线程的记录
+    writer: mpsc::Sender<(Duration, Direction, Frame)>,
+    thread: JoinHandle<()>,
+    /// 写盘线程出错退出后置位，下一次记录时结束会话
+    failed: Arc<AtomicBool>,
+    /// 发送侧可能按 MTU 分片，接收侧可能一次通知多个帧，各自重新拼帧
+    rx: FrameSplitter,
+    tx: FrameSplitter,
+}
+
+/// 写盘线程持有的文件
+struct CaptureWriter {
+    pcapng: PcapNgWriter<BufWriter<File>>,
+    jsonl: BufWriter<File>,
+}
+
+/// address → 抓包会话
+static SESSIONS: Lazy<Mutex<HashMap<String, CaptureSession>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn is_active(addr: &str) -> bool {
+    SESSIONS.lock().contains_key(addr)
+}
+
+pub fn files(addr: &str) -> Option<CaptureFiles> {
+    SESSIONS.lock().get(addr).map(|s| s.files.clone())
+}
+
+/// 开始抓包，已在抓包时返回当前文件
+pub fn start(addr: &str) -> Result<CaptureFiles> {
+    let mut sessions = SESSIONS.lock();
+    if let Some(session) = sessions.get(addr) {
+        return Ok(session.files.clone());
+    }
+
+    let dir = crate::logger::rslogs_dir();
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("create capture dir: {}", dir.display()))?;
+
+    let stem = format!(
+        "{}-{}.miwear",
+        Local::now().format("%Y-%m-%d_%H-%M-%S"),
+        addr.replace(':', "")
+    );
+    let pcapng_path = dir.join(format!("{}.pcapng", stem));
+    let jsonl_path = dir.join(format!("{}.jsonl", stem));
+
+    let pcapng_file = File::create(&pcapng_path)
+        .with_context(|| format!("create capture file: {}", pcapng_path.display()))?;
+    let mut pcapng = PcapNgWriter::new(BufWriter::new(pcapng_file))?;
+    pcapng.write_pcapng_block(InterfaceDescriptionBlock {
+        linktype: DataLink::USER0,
+        snaplen: 0,
+        options: vec![],
+    })?;
+    pcapng.get_mut().flush()?;
+
+    let jsonl = BufWriter::new(
+        File::create(&jsonl_path)
+            .with_context(|| format!("create capture file: {}", jsonl_path.display()))?,
+    );
+
+    let files = CaptureFiles {
+        pcapng: pcapng_path.to_string_lossy().to_string(),
+        jsonl: jsonl_path.to_string_lossy().to_string(),
+    };
+
+    let (writer, records) = mpsc::channel();
+    let failed = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let addr = addr.to_string();
+        let failed = failed.clone();
+        let mut file_writer = CaptureWriter { pcapng, jsonl };
+        std::thread::Builder::new()
+            .name(format!("capture-{}", addr))
+            .spawn(move || {
+                if let Err(e) = file_writer.run(&records) {
+                    log::error!(
+                        "[MiWearCapture] Failed to write capture for {}, stopping: {:#}",
+                        addr,
+                        e
+                    );
+                    failed.store(true, Ordering::Relaxed);
+                }
+            })
+            .context("spawn capture writer")?
+    };
+
+    log::info!(
+        "[MiWearCapture] Capturing {} to {} / {}",
+        addr,
+        files.pcapng,
+        files.jsonl
+    );
+
+    sessions.insert(
+        addr.to_string(),
+        CaptureSession {
+            files: files.clone(),
+            writer,
+            thread,
+            failed,
+            rx: FrameSplitter::default(),
+            tx: FrameSplitter::default(),
+        },
+    );
+    Ok(files)
+}
+
+/// 停止抓包，等写盘线程写完并刷盘后返回写出的文件
+pub fn stop(addr: &str) -> Option<CaptureFiles> {
+    let mut session = SESSIONS.lock().remove(addr)?;
+    let ts = now();
+    for dir in [Direction::Rx, Direction::Tx] {
+        if let Some(rest) = session.splitter(dir).take_rest() {
+            let _ = session
+                .writer
+                .send((ts, dir, Frame::corrupt(rest, None, None)));
+        }
+    }
+
+    let CaptureSession {
+        files,
+        writer,
+        thread,
+        ..
+    } = session;
+    drop(writer);
+    let _ = thread.join();
+    log::info!("[MiWearCapture] Stopped capture for {}", addr);
+    Some(files)
+}
+
+/// 记录一段链路数据，未在抓包时直接返回
+pub async fn record(device: &MiWearDevice, dir: Direction, data: &[u8]) {
+    if !is_active(&device.addr) {
+        return;
+    }
+
+    let keys = device.state.read().await.sec_keys.clone();
+    let key = keys.and_then(|keys| {
+        crate::tools::vec_to_array_16_opt(match dir {
+            Direction::Rx => &keys.dec_key,
+            Direction::Tx => &keys.enc_key,
+        })
+    });
+    let ts = now();
+
+    let mut sessions = SESSIONS.lock();
+    let Some(session) = sessions.get_mut(&device.addr) else {
+        return;
+    };
+    if session.failed.load(Ordering::Relaxed) {
+        sessions.remove(&device.addr);
+        return;
+    }
+
+    let frames = if data.starts_with(&HELLO_MAGIC) {
+        vec![Frame::hello(data)]
+    } else {
+        let splitter = session.splitter(dir);
+        splitter.push(data);
+        std::iter::from_fn(|| splitter.next_chunk())
+            .map(|chunk| match chunk {
+                Chunk::Frame { raw, pkt } => Frame::miwear(raw, pkt, key.as_ref()),
+                Chunk::Corrupt { raw, error } => {
+                    let pkt_type = error.and(raw.get(2).copied().and_then(PktType::from_wire));
+                    Frame::corrupt(raw, pkt_type, error)
+                }
+            })
+            .collect()
+    };
+
+    for frame in frames {
+        // 写盘线程已退出时由下一次记录清理会话
+        if session.writer.send((ts, dir, frame)).is_err() {
+            break;
+        }
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// 一个已拆解好的记录
+struct Frame {
+    kind: FrameKind,
+    raw: Vec<u8>,
+    pkt_type: Option<PktType>,
+    seq: Option<u8>,
+    channel: Option<u8>,
+    opcode: Option<OpCode>,
+    payload: Option<Vec<u8>>,
+    decrypted: bool,
+    error: Option<PacketError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    MiWear = 0,
+    Hello = 1,
+    Corrupt = 2,
+}
+
+impl FrameKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::MiWear => "frame",
+            Self::Hello => "hello",
+            Self::Corrupt => "corrupt",
+        }
+    }
+}
+
+impl Frame {
+    fn hello(data: &[u8]) -> Self {
+        Self {
+            kind: FrameKind::Hello,
+            raw: data.to_vec(),
+            pkt_type: None,
+            seq: None,
+            channel: None,
+            opcode: None,
+            payload: None,
+            decrypted: false,
+            error: None,
+        }
+    }
+
+    /// 包头可信时（如 CRC 错误）保留 type / seq 方便排查
+    fn corrupt(raw: Vec<u8>, pkt_type: Option<PktType>, error: Option<PacketError>) -> Self {
+        Self {
+            kind: FrameKind::Corrupt,
+            seq: pkt_type.and(raw.get(3).copied()),
+            raw,
+            pkt_type,
+            channel: None,
+            opcode: None,
+            payload: None,
+            decrypted: false,
+            error,
+        }
+    }
+
+    fn miwear(raw: Vec<u8>, pkt: MiWearPacket, key: Option<&[u8; 16]>) -> Self {
+        let mut frame = Self {
+            kind: FrameKind::MiWear,
+            raw,
+            pkt_type: Some(pkt.pkt_type),
+            seq: Some(pkt.seq),
+            channel: None,
+            opcode: None,
+            payload: None,
+            decrypted: false,
+            error: None,
+        };
+
+        if let Some(fields) = pkt.data_fields() {
+            frame.channel = Some(fields.channel.into());
+            frame.opcode = Some(fields.opcode);
+            frame.payload = match (fields.opcode, key) {
+                (OpCode::Encrypted, Some(key)) => {
+                    frame.decrypted = true;
+                    Some(crate::crypto::aesctr::aes128_ctr_crypt(
+                        key,
+                        key,
+                        fields.data,
+                    ))
+                }
+                // 还没有会话密钥时只能保留密文
+                _ => Some(fields.data.to_vec()),
+            };
+        }
+        frame
+    }
+
+    fn encode(&self, dir: Direction) -> Vec<u8> {
+        let payload = self.payload.as_deref().unwrap_or_default();
+        let mut out = Vec::with_capacity(12 + self.raw.len() + payload.len());
+        out.push(FORMAT_VERSION);
+        out.push(match dir {
+            Direction::Rx => 0,
+            Direction::Tx => 1,
+        });
+        out.push(self.kind as u8);
+        out.push(self.pkt_type.map(u8::from).unwrap_or(0));
+        out.push(self.seq.unwrap_or(0));
+        out.push(self.channel.unwrap_or(0));
+        out.push(self.opcode.map(|op| op as u8).unwrap_or(0));
+        out.push(u8::from(self.decrypted));
+        out.extend_from_slice(&(self.raw.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.raw);
+        out.extend_from_slice(payload);
+        out
+    }
+}
+
+impl CaptureSession {
+    fn splitter(&mut self, dir: Direction) -> &mut FrameSplitter {
+        match dir {
+            Direction::Rx => &mut self.rx,
+            Direction::Tx => &mut self.tx,
+        }
+    }
+}
+
+impl CaptureWriter {
+    /// 写出收到的记录，距上次刷盘超过 FLUSH_INTERVAL 时刷盘，会话停止后刷盘退出
+    fn run(&mut self, records: &mpsc::Receiver<(Duration, Direction, Frame)>) -> Result<()> {
+        let mut last_flush = Instant::now();
+        let mut dirty = false;
+        loop {
+            match records.recv_timeout(FLUSH_INTERVAL) {
+                Ok((ts, dir, frame)) => {
+                    self.write(ts, dir, frame)?;
+                    dirty = true;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return self.flush(),
+            }
+            if dirty && last_flush.elapsed() >= FLUSH_INTERVAL {
+                self.flush()?;
+                last_flush = Instant::now();
+                dirty = false;
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.pcapng.get_mut().flush()?;
+        self.jsonl.flush()?;
+        Ok(())
+    }
+
+    fn write(&mut self, ts: Duration, dir: Direction, frame: Frame) -> Result<()> {
+        let data = frame.encode(dir);
+        self.pcapng.write_pcapng_block(EnhancedPacketBlock {
+            interface_id: 0,
+            timestamp: ts,
+            original_len: data.len() as u32,
+            data: data.into(),
+            options: vec![EnhancedPacketOption::Flags(match dir {
+                Direction::Rx => EPB_FLAG_INBOUND,
+                Direction::Tx => EPB_FLAG_OUTBOUND,
+            })],
+        })?;
+
+        let raw = to_hex_string(&frame.raw);
+        let record = CaptureRecord {
+            ts_us: ts.as_micros(),
+            dir,
+            kind: frame.kind.name(),
+            pkt_type: frame.pkt_type,
+            seq: frame.seq,
+            channel: frame
+                .channel
+                .map(|c| format!("{:?}", super::packet::Channel::from(c))),
+            opcode: frame.opcode.map(|op| format!("{:?}", op)),
+            decrypted: frame.decrypted,
+            raw: &raw,
+            payload: frame.payload.as_deref().map(to_hex_string),
+            error: frame.error,
+        };
+        serde_json::to_writer(&mut self.jsonl, &record)?;
+        self.jsonl.write_all(b"\n")?;
+        Ok(())
+    }
+}
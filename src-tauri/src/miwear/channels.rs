@@ -0,0 +1,244 @@
+//! 通道处理器注册表
+//!
+//! `btrecv` 把每个 Data 帧解密后的负载交给 [`dispatch`]，按通道分发给订阅者。
+//! 订阅者可以选择接收原始负载（每帧一次），或经该通道解码器拼好的完整数据块。
+//! Rust 模块直接调用 [`subscribe`]，插件通过 `AstroBox.channel` 订阅。
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+
+use super::packet::Channel;
+
+pub mod collector;
+
+/// 单个拼包缓冲区的上限，防止设备异常时无限增长
+const MAX_BLOCK_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadKind {
+    /// 每个 Data 帧的明文负载
+    Raw,
+    /// 经通道解码器拼好的完整数据块；通道没有解码器时与 Raw 相同
+    Reassembled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelPayload {
+    pub addr: String,
+    pub channel: Channel,
+    pub kind: PayloadKind,
+    pub data: Vec<u8>,
+}
+
+/// 在蓝牙接收任务中同步调用，返回后才会回复 ACK；处理器不能阻塞，
+/// 写盘等耗时操作需自行放到 `spawn_blocking`
+pub type ChannelHandler = Arc<dyn Fn(&ChannelPayload) + Send + Sync>;
+pub type HandlerId = u64;
+
+/// 每台设备、每个通道各持有一个解码器实例
+pub trait ChannelDecoder: Send {
+    /// 喂入一帧明文负载，拼出完整数据块时返回
+    fn feed(&mut self, data: &[u8]) -> Result<Option<Vec<u8>>>;
+}
+
+pub type DecoderFactory = fn() -> Box<dyn ChannelDecoder>;
+
+/// 与 Mass 相同的分块格式：total(u16 LE) | current(u16 LE，从 1 开始) | 分块数据
+#[derive(Debug, Default)]
+pub struct PartsDecoder {
+    buf: Vec<u8>,
+    next: u16,
+}
+
+impl PartsDecoder {
+    pub fn boxed() -> Box<dyn ChannelDecoder> {
+        Box::<Self>::default()
+    }
+
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.next = 0;
+    }
+}
+
+impl ChannelDecoder for PartsDecoder {
+    fn feed(&mut self, data: &[u8]) -> Result<Option<Vec<u8>>> {
+        if data.len() < 4 {
+            self.reset();
+            bail!("Part too short: {} bytes", data.len());
+        }
+        let total = u16::from_le_bytes([data[0], data[1]]);
+        let current = u16::from_le_bytes([data[2], data[3]]);
+
+        if current == 0 || current > total {
+            self.reset();
+            bail!("Invalid part {}/{}", current, total);
+        }
+        if current == 1 {
+            self.buf.clear();
+        } else if current != self.next {
+            let expected = self.next;
+            self.reset();
+            bail!(
+                "Out-of-order part {}/{}, expected {}",
+                current,
+                total,
+                expected
+            );
+        }
+        if self.buf.len() + data.len() - 4 > MAX_BLOCK_SIZE {
+            self.reset();
+            bail!("Block exceeds {} bytes", MAX_BLOCK_SIZE);
+        }
+
+        self.buf.extend_from_slice(&data[4..]);
+        if current == total {
+            self.next = 0;
+            return Ok(Some(std::mem::take(&mut self.buf)));
+        }
+        self.next = current + 1;
+        Ok(None)
+    }
+}
+
+struct Subscriber {
+    id: HandlerId,
+    kind: PayloadKind,
+    handler: ChannelHandler,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+static SUBSCRIBERS: Lazy<RwLock<HashMap<Channel, Vec<Subscriber>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// 文件类通道默认按分块格式拼包，其余通道每帧即一个完整数据块
+static DECODERS: Lazy<RwLock<HashMap<Channel, DecoderFactory>>> = Lazy::new(|| {
+    let mut decoders: HashMap<Channel, DecoderFactory> = HashMap::new();
+    decoders.insert(Channel::MassVoice, PartsDecoder::boxed);
+    decoders.insert(Channel::FileSensor, PartsDecoder::boxed);
+    decoders.insert(Channel::FileFitness, PartsDecoder::boxed);
+    decoders.insert(Channel::FileResearch, PartsDecoder::boxed);
+    RwLock::new(decoders)
+});
+
+/// (address, channel) → 拼包状态
+static STATES: Lazy<Mutex<HashMap<(String, Channel), Box<dyn ChannelDecoder>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 设置通道的解码器，None 表示不拼包；进行中的拼包状态会被丢弃
+pub fn set_decoder(channel: Channel, factory: Option<DecoderFactory>) {
+    match factory {
+        Some(factory) => DECODERS.write().insert(channel, factory),
+        None => DECODERS.write().remove(&channel),
+    };
+    STATES.lock().retain(|(_, ch), _| *ch != channel);
+}
+
+pub fn subscribe(channel: Channel, kind: PayloadKind, handler: ChannelHandler) -> HandlerId {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    SUBSCRIBERS
+        .write()
+        .entry(channel)
+        .or_default()
+        .push(Subscriber { id, kind, handler });
+    log::info!(
+        "[MiWearChannels] Handler #{} subscribed to {:?} ({:?})",
+        id,
+        channel,
+        kind
+    );
+    id
+}
+
+pub fn unsubscribe(id: HandlerId) -> bool {
+    let mut subscribers = SUBSCRIBERS.write();
+    for subs in subscribers.values_mut() {
+        if let Some(pos) = subs.iter().position(|s| s.id == id) {
+            subs.remove(pos);
+            return true;
+        }
+    }
+    false
+}
+
+pub fn is_handled(channel: Channel) -> bool {
+    SUBSCRIBERS
+        .read()
+        .get(&channel)
+        .is_some_and(|subs| !subs.is_empty())
+}
+
+/// 分发一帧明文负载，没有订阅者时返回 false
+pub fn dispatch(addr: &str, channel: Channel, data: &[u8]) -> bool {
+    // 复制出订阅者后再回调，回调里可以安全地订阅 / 取消订阅
+    let (raw, reassembled): (Vec<_>, Vec<_>) = match SUBSCRIBERS.read().get(&channel) {
+        Some(subs) if !subs.is_empty() => subs
+            .iter()
+            .map(|s| (s.kind, s.handler.clone()))
+            .partition(|(kind, _)| *kind == PayloadKind::Raw),
+        _ => return false,
+    };
+
+    if !raw.is_empty() {
+        let payload = ChannelPayload {
+            addr: addr.to_string(),
+            channel,
+            kind: PayloadKind::Raw,
+            data: data.to_vec(),
+        };
+        for (_, handler) in &raw {
+            handler(&payload);
+        }
+    }
+
+    if reassembled.is_empty() {
+        return true;
+    }
+
+    let block = match DECODERS.read().get(&channel).copied() {
+        None => Some(data.to_vec()),
+        Some(factory) => {
+            let mut states = STATES.lock();
+            let decoder = states
+                .entry((addr.to_string(), channel))
+                .or_insert_with(factory);
+            match decoder.feed(data) {
+                Ok(block) => block,
+                Err(e) => {
+                    log::warn!("[MiWearChannels] {:?} decoder error: {:#}", channel, e);
+                    None
+                }
+            }
+        }
+    };
+
+    if let Some(block) = block {
+        let payload = ChannelPayload {
+            addr: addr.to_string(),
+            channel,
+            kind: PayloadKind::Reassembled,
+            data: block,
+        };
+        for (_, handler) in &reassembled {
+            handler(&payload);
+        }
+    }
+    true
+}
+
+/// 设备断开时丢弃未拼完的数据
+pub fn reset(addr: &str) {
+    STATES.lock().retain(|(a, _), _| a != addr);
+}
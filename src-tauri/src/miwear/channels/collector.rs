@@ -0,0 +1,67 @@
+//! 把传感器 / 科研文件通道拼好的数据块落盘，供离线分析
+//!
+//! 由配置项 `collect_file_streams` 控制，文件写在
+//! `app_data_dir/collected/<address>/<channel>/<时间>.bin`
+
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use tauri::Manager;
+
+use super::{ChannelPayload, PayloadKind};
+use crate::miwear::packet::Channel;
+
+/// 收集的通道
+pub const COLLECTED_CHANNELS: [Channel; 2] = [Channel::FileSensor, Channel::FileResearch];
+
+pub fn init() {
+    for channel in COLLECTED_CHANNELS {
+        super::subscribe(channel, PayloadKind::Reassembled, Arc::new(on_block));
+    }
+}
+
+pub fn collect_dir() -> PathBuf {
+    crate::APP_HANDLE
+        .get()
+        .and_then(|app| app.path().app_data_dir().ok())
+        .unwrap_or_else(std::env::temp_dir)
+        .join("collected")
+}
+
+fn on_block(payload: &ChannelPayload) {
+    if !crate::config::read(|c| c.collect_file_streams) {
+        return;
+    }
+    let payload = payload.clone();
+    // 写盘放到阻塞线程，不占用蓝牙接收任务
+    tauri::async_runtime::spawn_blocking(move || match write_block(&payload) {
+        Ok(path) => log::info!(
+            "[MiWearCollector] Saved {:?} block ({} bytes) to {}",
+            payload.channel,
+            payload.data.len(),
+            path.display()
+        ),
+        Err(e) => log::error!(
+            "[MiWearCollector] Failed to save {:?} block: {:#}",
+            payload.channel,
+            e
+        ),
+    });
+}
+
+fn write_block(payload: &ChannelPayload) -> Result<PathBuf> {
+    let dir = collect_dir()
+        .join(payload.addr.replace(':', ""))
+        .join(format!("{:?}", payload.channel));
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("create collect dir: {}", dir.display()))?;
+
+    let path = dir.join(format!(
+        "{}.bin",
+        Local::now().format("%Y-%m-%d_%H-%M-%S%.3f")
+    ));
+    std::fs::write(&path, &payload.data)
+        .with_context(|| format!("write collected block: {}", path.display()))?;
+    Ok(path)
+}
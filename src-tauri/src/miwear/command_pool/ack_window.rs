@@ -0,0 +1,220 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+
+use super::retransmit::MAX_RTO;
+use crate::miwear::error::MiWearError;
+
+/// 同时等待 ACK 的数据帧上限
+pub const ACK_WINDOW_SIZE: usize = 8;
+
+/// 没有等待者的帧在表中保留的时长，超时后视为 ACK 丢失
+const UNTRACKED_TTL: Duration = Duration::from_secs(30);
+
+/// 窗口中的一个空位，发送前取得，登记 seq 时交还给窗口
+pub struct AckSlot(OwnedSemaphorePermit);
+
+struct InFlight {
+    /// 最近一次发出（含重传）的时间
+    sent_at: Instant,
+    /// 重传过的帧无法确定 ACK 对应哪一次发送，不产生 RTT 样本
+    retransmitted: bool,
+    waiter: Option<(oneshot::Sender<()>, OwnedSemaphorePermit)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AckWindowStats {
+    pub window_size: usize,
+    pub waiting: usize,
+    pub in_flight: Vec<u8>,
+    pub protocol_errors: u64,
+}
+
+/// 按 seq 跟踪已发送数据帧的 ACK
+///
+/// 所有数据帧发送时都会登记 seq；需要等待 ACK 的帧额外占用一个窗口位置，
+/// 窗口满时 reserve 会阻塞，从而限制流水线中未确认的帧数。
+/// 没有等待者的帧过期后只记下 seq，迟到的 ACK 直接忽略；
+/// 其余表中不存在的 seq 的 ACK 视为协议错误
+pub struct AckWindow {
+    slots: Arc<Semaphore>,
+    window_size: usize,
+    in_flight: Mutex<HashMap<u8, InFlight>>,
+    /// 已过期、尚未收到 ACK 的无等待者帧
+    expired: Mutex<HashSet<u8>>,
+    protocol_errors: AtomicU64,
+}
+
+impl AckWindow {
+    pub fn new(window_size: usize) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(window_size)),
+            window_size,
+            in_flight: Mutex::new(HashMap::new()),
+            expired: Mutex::new(HashSet::new()),
+            protocol_errors: AtomicU64::new(0),
+        }
+    }
+
+    /// 等待窗口空出一个位置
+    ///
+    /// 超过 timeout 仍未空出时，先清理等待超时的帧再重试一次
+    pub async fn reserve(&self, timeout: Duration) -> Result<AckSlot> {
+        if let Ok(Ok(permit)) =
+            tokio::time::timeout(timeout, self.slots.clone().acquire_owned()).await
+        {
+            return Ok(AckSlot(permit));
+        }
+
+        self.expire(timeout);
+
+        match self.slots.clone().try_acquire_owned() {
+            Ok(permit) => Ok(AckSlot(permit)),
+            Err(_) => Err(MiWearError::timeout(format!(
+                "ACK window stalled: {} frames still unacknowledged",
+                self.window_size
+            ))
+            .into()),
+        }
+    }
+
+    /// 登记一个不需要等待 ACK 的帧
+    pub fn track(&self, seq: u8) {
+        let mut map = self.in_flight.lock();
+        let mut expired = self.expired.lock();
+        map.retain(|seq, f| {
+            let keep = f.waiter.is_some() || f.sent_at.elapsed() < UNTRACKED_TTL;
+            if !keep {
+                expired.insert(*seq);
+            }
+            keep
+        });
+        expired.remove(&seq);
+        map.insert(
+            seq,
+            InFlight {
+                sent_at: Instant::now(),
+                retransmitted: false,
+                waiter: None,
+            },
+        );
+    }
+
+    /// 登记一个需要等待 ACK 的帧，返回 ACK 到达时完成的 receiver
+    pub fn register(&self, seq: u8, slot: AckSlot) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.expired.lock().remove(&seq);
+        let prev = self.in_flight.lock().insert(
+            seq,
+            InFlight {
+                sent_at: Instant::now(),
+                retransmitted: false,
+                waiter: Some((tx, slot.0)),
+            },
+        );
+        if let Some(InFlight {
+            waiter: Some(_), ..
+        }) = prev
+        {
+            log::warn!(
+                "[AckWindow] seq {} wrapped around while still waiting for ACK",
+                seq
+            );
+        }
+        rx
+    }
+
+    /// 处理一个 ACK，返回 RTT 样本（帧被重传过时为 None）；seq 不在表中时返回协议错误
+    pub fn ack(&self, seq: u8) -> Result<Option<Duration>> {
+        let entry = self.in_flight.lock().remove(&seq);
+        match entry {
+            Some(frame) => {
+                let rtt = (!frame.retransmitted).then(|| frame.sent_at.elapsed());
+                if let Some((tx, _permit)) = frame.waiter {
+                    let _ = tx.send(());
+                }
+                Ok(rtt)
+            }
+            None if self.expired.lock().remove(&seq) => {
+                log::debug!("[AckWindow] Late ACK for expired seq {}, ignored", seq);
+                Ok(None)
+            }
+            None => {
+                self.protocol_errors.fetch_add(1, Ordering::Relaxed);
+                Err(MiWearError::protocol(format!(
+                    "Unexpected ACK for seq {} (in flight: {:?})",
+                    seq,
+                    self.in_flight_seqs()
+                ))
+                .into())
+            }
+        }
+    }
+
+    pub fn mark_retransmitted(&self, seq: u8) {
+        if let Some(frame) = self.in_flight.lock().get_mut(&seq) {
+            frame.retransmitted = true;
+            frame.sent_at = Instant::now();
+        }
+    }
+
+    /// 放弃等待某个 seq（例如超时），释放其窗口位置
+    pub fn cancel(&self, seq: u8) {
+        self.in_flight.lock().remove(&seq);
+    }
+
+    /// 链路断开时清空，所有等待者会收到 RecvError
+    pub fn clear(&self) {
+        self.in_flight.lock().clear();
+        self.expired.lock().clear();
+    }
+
+    fn expire(&self, older_than: Duration) {
+        // 有等待者的帧由重传任务负责收尾，每次重发都会刷新 sent_at，
+        // 超过两倍最长 RTO 仍未刷新才说明重传任务已经不在了
+        let waiter_ttl = older_than.max(MAX_RTO * 2);
+        let mut map = self.in_flight.lock();
+        let mut expired = self.expired.lock();
+        map.retain(|seq, f| {
+            let ttl = if f.waiter.is_some() {
+                waiter_ttl
+            } else {
+                older_than
+            };
+            let keep = f.sent_at.elapsed() < ttl;
+            if !keep {
+                if f.waiter.is_some() {
+                    log::warn!("[AckWindow] Dropping stale waiter for seq {}", seq);
+                } else {
+                    expired.insert(*seq);
+                }
+            }
+            keep
+        });
+    }
+
+    fn in_flight_seqs(&self) -> Vec<u8> {
+        let mut seqs: Vec<u8> = self.in_flight.lock().keys().copied().collect();
+        seqs.sort_unstable();
+        seqs
+    }
+
+    pub fn stats(&self) -> AckWindowStats {
+        AckWindowStats {
+            window_size: self.window_size,
+            waiting: self.window_size - self.slots.available_permits(),
+            in_flight: self.in_flight_seqs(),
+            protocol_errors: self.protocol_errors.load(Ordering::Relaxed),
+        }
+    }
+}
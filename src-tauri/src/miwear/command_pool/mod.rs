@@ -1,39 +1,52 @@
-use once_cell::sync::Lazy;
-use parking_lot::RwLock;
 use std::{
-    collections::VecDeque,
+    fmt,
     sync::{Arc, Weak}, time::Duration,
 };
 use tokio::sync::{oneshot, Mutex, Notify};
 
-pub static CHANNEL_PRIORITY: Lazy<RwLock<Vec<Channel>>> =
-    Lazy::new(|| RwLock::new(vec![Channel::Mass, Channel::Pb, Channel::NetWork]));
+pub mod ack_window;
+pub mod retransmit;
+pub mod scheduler;
 
-pub fn set_channel_priority(priority: Vec<Channel>) {
-    *CHANNEL_PRIORITY.write() = priority;
-}
-
-fn channel_priority_index(channel: Channel) -> usize {
-    let guard = CHANNEL_PRIORITY.read();
-    let len = guard.len();
-    guard.iter().position(|&c| c == channel).unwrap_or(len)
-}
+use retransmit::{RetransmitPolicy, RetransmitSnapshot, RetransmitStats, SentFrame};
+use scheduler::Scheduler;
+pub use scheduler::{CommandPriority, SchedulerConfig};
 
 use super::{
     device::{MiWearDevice, REQ_TIMEOUT},
     packet::{Channel, OpCode},
 };
 
-#[derive(Debug)]
 pub enum CommandKind {
     Send,
+    /// 发送前用分配到的 seq 回调，供调用方在回应到达前登记等待，发送后回复 `Sent(seq)`
+    Request {
+        on_seq: Box<dyn FnOnce(&MiWearDevice, u8) + Send>,
+    },
     WaitAck,
-    RegisterAck { unlocked: bool },
+    RegisterAck {
+        unlocked: bool,
+    },
+}
+
+impl fmt::Debug for CommandKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Send => f.write_str("Send"),
+            Self::Request { .. } => f.write_str("Request"),
+            Self::WaitAck => f.write_str("WaitAck"),
+            Self::RegisterAck { unlocked } => f
+                .debug_struct("RegisterAck")
+                .field("unlocked", unlocked)
+                .finish(),
+        }
+    }
 }
 
 pub enum CommandResponse {
     Done,
     AckReceiver(oneshot::Receiver<()>),
+    Sent(u8),
 }
 
 pub struct Command {
@@ -42,21 +55,27 @@ pub struct Command {
     pub payload: Vec<u8>,
     pub kind: CommandKind,
     pub timeout: Option<std::time::Duration>,
+    pub priority: CommandPriority,
     pub responder: oneshot::Sender<anyhow::Result<CommandResponse>>,
 }
 
 pub struct CommandPool {
-    queue: Mutex<VecDeque<Command>>,
+    queue: Mutex<Scheduler>,
     notify: Notify,
+    /// 有命令出队（队列腾出空间）时通知等待中的 push
+    space: Notify,
     device: Weak<MiWearDevice>,
+    retry_stats: Arc<RetransmitStats>,
 }
 
 impl CommandPool {
-    pub fn new(device: Weak<MiWearDevice>) -> Arc<Self> {
+    pub fn new(device: Weak<MiWearDevice>, config: SchedulerConfig) -> Arc<Self> {
         let pool = Arc::new(Self {
-            queue: Mutex::new(VecDeque::new()),
+            queue: Mutex::new(Scheduler::new(config)),
             notify: Notify::new(),
+            space: Notify::new(),
             device,
+            retry_stats: Arc::new(RetransmitStats::default()),
         });
         let worker = pool.clone();
         tokio::spawn(async move {
@@ -65,88 +84,162 @@ impl CommandPool {
         pool
     }
 
+    /// 加入队列；所在通道达到队列上限时等待，以此向上游（如网络栈）施加背压
     pub async fn push(&self, cmd: Command) {
-        let mut q = self.queue.lock().await;
-
-        log::info!("[CommandPool] Push new command: channel={} timeout={}", cmd.channel as u8, cmd.timeout.unwrap_or(Duration::from_secs(99999)).as_millis());
-
-        let cmd_prio = channel_priority_index(cmd.channel);
-        let pos = q
-            .iter()
-            .rposition(|c| channel_priority_index(c.channel) <= cmd_prio)
-            .map_or(0, |i| i + 1);
-        q.insert(pos, cmd);
+        log::info!(
+            "[CommandPool] Push new command: channel={} priority={:?} timeout={}",
+            cmd.channel as u8,
+            cmd.priority,
+            cmd.timeout
+                .unwrap_or(Duration::from_secs(99999))
+                .as_millis()
+        );
 
-        self.notify.notify_one();
+        loop {
+            let space = self.space.notified();
+            tokio::pin!(space);
+            {
+                let mut q = self.queue.lock().await;
+                if q.has_room(&cmd) {
+                    q.push(cmd);
+                    self.notify.notify_one();
+                    return;
+                }
+                // 先登记再释放锁，避免错过出队通知
+                space.as_mut().enable();
+            }
+            space.await;
+        }
     }
 
     async fn pop(&self) -> Command {
         loop {
-            if let Some(cmd) = self.queue.lock().await.pop_front() {
+            if let Some(cmd) = self.queue.lock().await.pop() {
+                self.space.notify_waiters();
                 return cmd;
             }
             self.notify.notified().await;
         }
     }
 
+    pub async fn config(&self) -> SchedulerConfig {
+        self.queue.lock().await.config().clone()
+    }
+
+    pub async fn set_config(&self, config: SchedulerConfig) {
+        self.queue.lock().await.set_config(config);
+        // 上限可能被调高
+        self.space.notify_waiters();
+    }
+
     async fn run(self: Arc<Self>) {
         loop {
             let cmd = self.pop().await;
             if let Some(device) = self.device.upgrade() {
-                let _ = self.process(device, cmd).await;
+                self.process(device, cmd).await;
             } else {
                 break;
             }
         }
     }
 
-    async fn process(&self, device: Arc<MiWearDevice>, cmd: Command) -> anyhow::Result<()> {
-        match cmd.kind {
-            CommandKind::Send => {
-                let frame = device
-                    .build_frame(cmd.channel, cmd.op, &cmd.payload)
-                    .await?
-                    .1;
-                let _guard = device.send_lock.lock().await;
-                device.send_fragments(frame).await?;
-                let _ = cmd.responder.send(Ok(CommandResponse::Done));
-            }
-            CommandKind::WaitAck => {
-                let (_seq, frame) = device
-                    .build_frame(cmd.channel, cmd.op, &cmd.payload)
-                    .await?;
-                let (tx, rx) = oneshot::channel();
-                device.pending_ack.insert((), tx);
-                let _guard = device.send_lock.lock().await;
-                device.send_fragments(frame).await?;
-                match tokio::time::timeout(cmd.timeout.unwrap_or(REQ_TIMEOUT), rx).await {
-                    Ok(Ok(())) => {
-                        let _ = cmd.responder.send(Ok(CommandResponse::Done));
-                    }
-                    Ok(Err(e)) => {
-                        let _ = cmd.responder.send(Err(anyhow::anyhow!(e)));
-                    }
-                    Err(_) => {
-                        let _ = cmd.responder.send(Err(anyhow::anyhow!("Ack timeout")));
-                    }
-                }
-            }
-            CommandKind::RegisterAck { unlocked } => {
-                let (_seq, frame) = device
-                    .build_frame(cmd.channel, cmd.op, &cmd.payload)
-                    .await?;
-                let (tx, rx) = oneshot::channel();
-                device.pending_ack.insert((), tx);
-                if !unlocked {
+    /// 执行一条命令，无论成功失败都会回复 responder，调用方不会因为发送失败而一直等待
+    async fn process(&self, device: Arc<MiWearDevice>, cmd: Command) {
+        let Command {
+            channel,
+            op,
+            payload,
+            kind,
+            timeout,
+            responder,
+            ..
+        } = cmd;
+
+        let result = async {
+            match kind {
+                CommandKind::Send => {
+                    let frame = device.build_frame(channel, op, &payload).await?.1;
                     let _guard = device.send_lock.lock().await;
                     device.send_fragments(frame).await?;
-                } else {
+                    Ok(CommandResponse::Done)
+                }
+                CommandKind::Request { on_seq } => {
+                    let (seq, frame) = device.build_frame(channel, op, &payload).await?;
+                    on_seq(&device, seq);
+                    let _guard = device.send_lock.lock().await;
                     device.send_fragments(frame).await?;
+                    Ok(CommandResponse::Sent(seq))
+                }
+                CommandKind::WaitAck => {
+                    let mut policy = RetransmitPolicy::from_config();
+                    if let Some(timeout) = timeout {
+                        policy.rto = timeout;
+                    }
+                    let slot = device.ack_window.reserve(REQ_TIMEOUT).await?;
+                    let (seq, frame) = device.build_frame(channel, op, &payload).await?;
+                    let rx = device.ack_window.register(seq, slot);
+                    let sent = SentFrame {
+                        channel,
+                        seq,
+                        frame,
+                    };
+                    let _guard = device.send_lock.lock().await;
+                    device.send_fragments(sent.frame.clone()).await?;
+                    retransmit::await_ack(&device, &sent, rx, policy, false, &self.retry_stats)
+                        .await?;
+                    Ok(CommandResponse::Done)
+                }
+                CommandKind::RegisterAck { unlocked } => {
+                    let slot = device
+                        .ack_window
+                        .reserve(timeout.unwrap_or(REQ_TIMEOUT))
+                        .await?;
+                    let (seq, frame) = device.build_frame(channel, op, &payload).await?;
+                    let rx = device.ack_window.register(seq, slot);
+                    let sent = SentFrame {
+                        channel,
+                        seq,
+                        frame,
+                    };
+                    if !unlocked {
+                        let _guard = device.send_lock.lock().await;
+                        device.send_fragments(sent.frame.clone()).await?;
+                    } else {
+                        device.send_fragments(sent.frame.clone()).await?;
+                    }
+
+                    // 由后台任务负责超时重传，调用方拿到的 receiver 在 ACK 到达后完成，
+                    // 重传次数用尽时 sender 被丢弃
+                    let (acked_tx, acked_rx) = oneshot::channel();
+                    let stats = self.retry_stats.clone();
+                    let policy = RetransmitPolicy::from_config();
+                    let device = device.clone();
+                    tokio::spawn(async move {
+                        match retransmit::await_ack(&device, &sent, rx, policy, !unlocked, &stats)
+                            .await
+                        {
+                            Ok(()) => {
+                                let _ = acked_tx.send(());
+                            }
+                            Err(e) => {
+                                log::error!("[CommandPool] {:?} frame lost: {}", sent.channel, e);
+                            }
+                        }
+                    });
+                    Ok(CommandResponse::AckReceiver(acked_rx))
                 }
-                let _ = cmd.responder.send(Ok(CommandResponse::AckReceiver(rx)));
             }
         }
-        Ok(())
+        .await;
+
+        if let Err(e) = &result {
+            log::error!("[CommandPool] {:?} command failed: {:#}", channel, e);
+        }
+        let _ = responder.send(result);
+    }
+
+    pub fn retry_stats(&self) -> RetransmitSnapshot {
+        self.retry_stats.snapshot()
     }
 
     pub async fn to_json_table(&self) -> Vec<serde_json::Value> {
@@ -157,7 +250,7 @@ impl CommandPool {
             .map(|(idx, cmd)| {
                 serde_json::json!({
                     "Packet": {
-                        "label": format!("{:?} {:?}", cmd.channel, cmd.op),
+                        "label": format!("{:?} {:?} ({:?})", cmd.channel, cmd.op, cmd.priority),
                     },
                     "Info": {
                         "label": format!("CMD #{}", idx + 1),
@@ -0,0 +1,171 @@
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+use crate::miwear::{device::MiWearDevice, error::MiWearError, packet::Channel};
+
+/// 保留最近多少个帧的重传记录
+const RECENT_FRAMES_CAP: usize = 128;
+
+/// 单次等待 ACK 的上限，避免指数退避后等待过久
+pub(super) const MAX_RTO: Duration = Duration::from_secs(8);
+
+/// 重传策略：首次等待 rto，之后每次翻倍，最多重传 max_retries 次
+#[derive(Debug, Clone, Copy)]
+pub struct RetransmitPolicy {
+    pub rto: Duration,
+    pub max_retries: u32,
+}
+
+impl RetransmitPolicy {
+    pub fn from_config() -> Self {
+        crate::config::read(|c| Self {
+            rto: Duration::from_millis(c.retransmit_rto_ms.into()),
+            max_retries: c.retransmit_max_retries,
+        })
+    }
+
+    fn rto_for(&self, attempt: u32) -> Duration {
+        self.rto
+            .saturating_mul(1u32 << attempt.min(16))
+            .min(MAX_RTO)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameRetryStats {
+    pub seq: u8,
+    pub channel: u8,
+    pub retransmissions: u32,
+    pub acked: bool,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetransmitTotals {
+    pub frames: u64,
+    pub retransmissions: u64,
+    pub failures: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RetransmitSnapshot {
+    pub totals: RetransmitTotals,
+    pub recent: Vec<FrameRetryStats>,
+}
+
+#[derive(Default)]
+pub struct RetransmitStats {
+    totals: Mutex<RetransmitTotals>,
+    recent: Mutex<VecDeque<FrameRetryStats>>,
+}
+
+impl RetransmitStats {
+    fn record(&self, frame: FrameRetryStats) {
+        {
+            let mut totals = self.totals.lock();
+            totals.frames += 1;
+            totals.retransmissions += frame.retransmissions as u64;
+            if !frame.acked {
+                totals.failures += 1;
+            }
+        }
+
+        let mut recent = self.recent.lock();
+        if recent.len() == RECENT_FRAMES_CAP {
+            recent.pop_front();
+        }
+        recent.push_back(frame);
+    }
+
+    pub fn snapshot(&self) -> RetransmitSnapshot {
+        RetransmitSnapshot {
+            totals: self.totals.lock().clone(),
+            recent: self.recent.lock().iter().cloned().collect(),
+        }
+    }
+}
+
+/// 已发出、等待 ACK 的帧
+pub(super) struct SentFrame {
+    pub channel: Channel,
+    pub seq: u8,
+    pub frame: Vec<u8>,
+}
+
+/// 已发出的帧在 RTO 内没有收到 ACK 时，以相同 seq 重发原始帧
+///
+/// `lock_on_resend` 为 false 时表示调用方已持有 send_lock（或有意绕过它）
+pub(super) async fn await_ack(
+    device: &Arc<MiWearDevice>,
+    sent: &SentFrame,
+    mut rx: oneshot::Receiver<()>,
+    policy: RetransmitPolicy,
+    lock_on_resend: bool,
+    stats: &RetransmitStats,
+) -> Result<()> {
+    let SentFrame { channel, seq, .. } = *sent;
+    let started = Instant::now();
+    let mut attempt = 0;
+
+    loop {
+        let result = tokio::time::timeout(policy.rto_for(attempt), &mut rx).await;
+
+        let record = |acked: bool| FrameRetryStats {
+            seq,
+            channel: channel as u8,
+            retransmissions: attempt,
+            acked,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        };
+
+        match result {
+            Ok(Ok(())) => {
+                stats.record(record(true));
+                return Ok(());
+            }
+            Ok(Err(_)) => {
+                stats.record(record(false));
+                return Err(MiWearError::transport(format!(
+                    "Ack waiter for seq {} was dropped",
+                    seq
+                ))
+                .into());
+            }
+            Err(_) if attempt >= policy.max_retries => {
+                device.ack_window.cancel(seq);
+                stats.record(record(false));
+                return Err(MiWearError::timeout(format!(
+                    "Ack (seq {}, {} retransmissions)",
+                    seq, attempt
+                ))
+                .into());
+            }
+            Err(_) => {
+                attempt += 1;
+                device.ack_window.mark_retransmitted(seq);
+                device.pacer.on_loss();
+                log::warn!(
+                    "[CommandPool] No ACK for seq {} on channel {:?}, retransmitting ({}/{})",
+                    seq,
+                    channel,
+                    attempt,
+                    policy.max_retries
+                );
+                if lock_on_resend {
+                    let _guard = device.send_lock.lock().await;
+                    device.send_fragments(sent.frame.clone()).await?;
+                } else {
+                    device.send_fragments(sent.frame.clone()).await?;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use super::Command;
+use crate::miwear::packet::{Channel, HEADER_LEN};
+
+/// 单条命令相对所在通道的优先级
+///
+/// High 绕过加权调度与队列上限立即发送，Low 只在其他队列都空闲时发送
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+/// 通道间的加权公平调度参数，可在全局配置或单个设备上设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    pub pb_weight: u32,
+    pub mass_weight: u32,
+    pub network_weight: u32,
+    /// OTA、文件等其余通道
+    pub other_weight: u32,
+    /// 每一轮每单位权重可发送的字节数
+    pub quantum_bytes: usize,
+    /// 各通道排队命令数上限，0 表示不限；达到上限时 push 会等待
+    pub pb_queue_limit: usize,
+    pub mass_queue_limit: usize,
+    pub network_queue_limit: usize,
+    pub other_queue_limit: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            pb_weight: 4,
+            mass_weight: 2,
+            network_weight: 1,
+            other_weight: 1,
+            quantum_bytes: 1024,
+            pb_queue_limit: 0,
+            mass_queue_limit: 0,
+            network_queue_limit: 32,
+            other_queue_limit: 0,
+        }
+    }
+}
+
+impl SchedulerConfig {
+    pub fn weight(&self, channel: Channel) -> u32 {
+        let weight = match channel {
+            Channel::Pb => self.pb_weight,
+            Channel::Mass => self.mass_weight,
+            Channel::NetWork => self.network_weight,
+            _ => self.other_weight,
+        };
+        weight.max(1)
+    }
+
+    pub fn queue_limit(&self, channel: Channel) -> Option<usize> {
+        let limit = match channel {
+            Channel::Pb => self.pb_queue_limit,
+            Channel::Mass => self.mass_queue_limit,
+            Channel::NetWork => self.network_queue_limit,
+            _ => self.other_queue_limit,
+        };
+        (limit > 0).then_some(limit)
+    }
+
+    fn quantum(&self, channel: Channel) -> usize {
+        self.weight(channel) as usize * self.quantum_bytes.max(1)
+    }
+}
+
+#[derive(Default)]
+struct ChannelQueue {
+    commands: VecDeque<Command>,
+    deficit: usize,
+}
+
+/// Deficit Round Robin：每个通道每轮获得 weight * quantum_bytes 的发送额度，
+/// 额度不足以发送队首命令时轮到下一个通道
+pub(super) struct Scheduler {
+    config: SchedulerConfig,
+    high: VecDeque<Command>,
+    low: VecDeque<Command>,
+    queues: HashMap<Channel, ChannelQueue>,
+    /// 有待发命令的通道，队首为当前轮到的通道
+    active: VecDeque<Channel>,
+    /// Normal + Low 的排队数，用于队列上限
+    depth: HashMap<Channel, usize>,
+}
+
+fn cost(cmd: &Command) -> usize {
+    HEADER_LEN + 2 + cmd.payload.len()
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            config,
+            high: VecDeque::new(),
+            low: VecDeque::new(),
+            queues: HashMap::new(),
+            active: VecDeque::new(),
+            depth: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: SchedulerConfig) {
+        self.config = config;
+    }
+
+    pub fn has_room(&self, cmd: &Command) -> bool {
+        if cmd.priority == CommandPriority::High {
+            return true;
+        }
+        match self.config.queue_limit(cmd.channel) {
+            Some(limit) => self.depth.get(&cmd.channel).copied().unwrap_or(0) < limit,
+            None => true,
+        }
+    }
+
+    pub fn push(&mut self, cmd: Command) {
+        match cmd.priority {
+            CommandPriority::High => self.high.push_back(cmd),
+            CommandPriority::Low => {
+                *self.depth.entry(cmd.channel).or_default() += 1;
+                self.low.push_back(cmd);
+            }
+            CommandPriority::Normal => {
+                *self.depth.entry(cmd.channel).or_default() += 1;
+                let channel = cmd.channel;
+                let queue = self.queues.entry(channel).or_default();
+                if queue.commands.is_empty() {
+                    self.active.push_back(channel);
+                }
+                queue.commands.push_back(cmd);
+            }
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Command> {
+        if let Some(cmd) = self.high.pop_front() {
+            return Some(cmd);
+        }
+        let cmd = self.pop_weighted().or_else(|| self.low.pop_front())?;
+        if let Some(depth) = self.depth.get_mut(&cmd.channel) {
+            *depth = depth.saturating_sub(1);
+        }
+        Some(cmd)
+    }
+
+    fn pop_weighted(&mut self) -> Option<Command> {
+        while let Some(&channel) = self.active.front() {
+            let quantum = self.config.quantum(channel);
+            let queue = self.queues.entry(channel).or_default();
+
+            let Some(cost) = queue.commands.front().map(cost) else {
+                queue.deficit = 0;
+                self.active.pop_front();
+                continue;
+            };
+
+            if queue.deficit < cost {
+                queue.deficit += quantum;
+                self.active.rotate_left(1);
+                continue;
+            }
+
+            queue.deficit -= cost;
+            let cmd = queue.commands.pop_front();
+            if queue.commands.is_empty() {
+                queue.deficit = 0;
+                self.active.pop_front();
+            }
+            return cmd;
+        }
+        None
+    }
+
+    /// 按大致的发送顺序列出排队中的命令
+    pub fn iter(&self) -> impl Iterator<Item = &Command> {
+        self.high
+            .iter()
+            .chain(
+                self.active
+                    .iter()
+                    .filter_map(|ch| self.queues.get(ch))
+                    .flat_map(|q| q.commands.iter()),
+            )
+            .chain(self.low.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::oneshot;
+
+    use super::*;
+    use crate::miwear::{command_pool::CommandKind, packet::OpCode};
+
+    /// 每条命令恰好消耗一个单位权重的额度
+    const UNIT: usize = 32;
+
+    fn command(channel: Channel, priority: CommandPriority, cost: usize) -> Command {
+        Command {
+            channel,
+            op: OpCode::Plain,
+            payload: vec![0; cost - HEADER_LEN - 2],
+            kind: CommandKind::Send,
+            timeout: None,
+            priority,
+            responder: oneshot::channel().0,
+        }
+    }
+
+    fn unit_config() -> SchedulerConfig {
+        SchedulerConfig {
+            quantum_bytes: UNIT,
+            network_queue_limit: 0,
+            ..Default::default()
+        }
+    }
+
+    /// 依次推入 Mass / Pb / NetWork 各 `n` 条 Normal 命令，交错排队
+    fn interleaved(scheduler: &mut Scheduler, n: usize) {
+        for _ in 0..n {
+            for channel in [Channel::Mass, Channel::Pb, Channel::NetWork] {
+                scheduler.push(command(channel, CommandPriority::Normal, UNIT));
+            }
+        }
+    }
+
+    fn drain(scheduler: &mut Scheduler) -> Vec<Channel> {
+        std::iter::from_fn(|| scheduler.pop())
+            .map(|cmd| cmd.channel)
+            .collect()
+    }
+
+    #[test]
+    fn interleaved_pushes_dequeue_in_weighted_rounds() {
+        let mut scheduler = Scheduler::new(unit_config());
+        interleaved(&mut scheduler, 8);
+
+        let order = drain(&mut scheduler);
+        let round = [
+            Channel::Mass,
+            Channel::Mass,
+            Channel::Pb,
+            Channel::Pb,
+            Channel::Pb,
+            Channel::Pb,
+            Channel::NetWork,
+        ];
+        assert_eq!(order[..7], round);
+        assert_eq!(order[7..14], round);
+        assert_eq!(order.len(), 24);
+    }
+
+    #[test]
+    fn dequeue_ratio_follows_weights() {
+        let mut scheduler = Scheduler::new(unit_config());
+        interleaved(&mut scheduler, 20);
+
+        let order = drain(&mut scheduler);
+        let count = |channel| order[..21].iter().filter(|&&c| c == channel).count();
+        assert_eq!(count(Channel::Pb), 12);
+        assert_eq!(count(Channel::Mass), 6);
+        assert_eq!(count(Channel::NetWork), 3);
+    }
+
+    #[test]
+    fn per_device_weights_override_defaults() {
+        let mut scheduler = Scheduler::new(SchedulerConfig {
+            pb_weight: 1,
+            mass_weight: 3,
+            network_weight: 2,
+            ..unit_config()
+        });
+        interleaved(&mut scheduler, 6);
+
+        let order = drain(&mut scheduler);
+        assert_eq!(
+            order[..6],
+            [
+                Channel::Mass,
+                Channel::Mass,
+                Channel::Mass,
+                Channel::Pb,
+                Channel::NetWork,
+                Channel::NetWork,
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_weight_still_gets_a_turn() {
+        let config = SchedulerConfig {
+            network_weight: 0,
+            ..unit_config()
+        };
+        assert_eq!(config.weight(Channel::NetWork), 1);
+    }
+
+    #[test]
+    fn high_and_low_priority_bypass_weighted_queues() {
+        let mut scheduler = Scheduler::new(unit_config());
+        scheduler.push(command(Channel::Mass, CommandPriority::Low, UNIT));
+        interleaved(&mut scheduler, 2);
+        scheduler.push(command(Channel::NetWork, CommandPriority::High, UNIT));
+        scheduler.push(command(Channel::Pb, CommandPriority::Low, UNIT));
+
+        let order = drain(&mut scheduler);
+        assert_eq!(order.len(), 9);
+        // High 后到也先发
+        assert_eq!(order[0], Channel::NetWork);
+        // Low 等所有 Normal 发完，彼此之间先进先出
+        assert_eq!(order[7..], [Channel::Mass, Channel::Pb]);
+    }
+
+    #[test]
+    fn queue_limit_applies_back_pressure() {
+        let mut scheduler = Scheduler::new(SchedulerConfig {
+            quantum_bytes: UNIT,
+            ..Default::default()
+        });
+        let limit = scheduler.config().queue_limit(Channel::NetWork).unwrap();
+        for _ in 0..limit - 1 {
+            scheduler.push(command(Channel::NetWork, CommandPriority::Normal, UNIT));
+        }
+        // Low 同样计入排队数
+        let low = command(Channel::NetWork, CommandPriority::Low, UNIT);
+        assert!(scheduler.has_room(&low));
+        scheduler.push(low);
+
+        assert!(!scheduler.has_room(&command(Channel::NetWork, CommandPriority::Normal, UNIT)));
+        assert!(!scheduler.has_room(&command(Channel::NetWork, CommandPriority::Low, UNIT)));
+        // 上限只针对所在通道，High 不受限
+        assert!(scheduler.has_room(&command(Channel::Pb, CommandPriority::Normal, UNIT)));
+        assert!(scheduler.has_room(&command(Channel::NetWork, CommandPriority::High, UNIT)));
+
+        assert_eq!(scheduler.pop().unwrap().channel, Channel::NetWork);
+        assert!(scheduler.has_room(&command(Channel::NetWork, CommandPriority::Normal, UNIT)));
+    }
+
+    #[test]
+    fn busy_mass_queue_does_not_starve_pb() {
+        let mut scheduler = Scheduler::new(SchedulerConfig::default());
+        // 大块 Mass 分片，单条就超过一轮的额度
+        let mass_cost = 4 * SchedulerConfig::default().quantum_bytes;
+        for _ in 0..64 {
+            scheduler.push(command(Channel::Mass, CommandPriority::Normal, mass_cost));
+        }
+        for _ in 0..4 {
+            assert_eq!(scheduler.pop().unwrap().channel, Channel::Mass);
+        }
+
+        scheduler.push(command(Channel::Pb, CommandPriority::Normal, 64));
+        let next: Vec<_> = (0..2).map(|_| scheduler.pop().unwrap().channel).collect();
+        assert!(
+            next.contains(&Channel::Pb),
+            "pb waited behind mass: {:?}",
+            next
+        );
+    }
+}
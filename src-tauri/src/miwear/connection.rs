@@ -0,0 +1,240 @@
+use std::{collections::HashMap, fmt};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+use super::MiWearError;
+
+pub const CONNECTION_STATE_EVENT: &str = "device-connection-state";
+
+/// 插件侧通过 `event.addEventListener` 监听的事件名
+pub const PLUGIN_CONNECTION_STATE_EVENT: &str = "onConnectionStateChanged";
+
+/// 设备连接的生命周期
+///
+/// ```text
+/// Disconnected ─→ Connecting ─→ Hello ─→ Authenticating ─→ Ready ⇄ Transferring
+///      ↑ │            ↑                                      │
+///      │ └─→ Reconnecting                                    │
+///      └──────────────── 任意状态断开 / 失败 ←───────────────┘
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Disconnected,
+    Connecting,
+    Hello,
+    Authenticating,
+    Ready,
+    Transferring,
+    Reconnecting,
+    Failed,
+}
+
+impl ConnectionState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Disconnected => "disconnected",
+            Self::Connecting => "connecting",
+            Self::Hello => "hello",
+            Self::Authenticating => "authenticating",
+            Self::Ready => "ready",
+            Self::Transferring => "transferring",
+            Self::Reconnecting => "reconnecting",
+            Self::Failed => "failed",
+        }
+    }
+
+    pub fn can_transition_to(self, to: ConnectionState) -> bool {
+        use ConnectionState::*;
+
+        // 断开与失败在任何阶段都可能发生
+        if matches!(to, Disconnected | Failed) {
+            return true;
+        }
+
+        matches!(
+            (self, to),
+            (Disconnected | Failed | Reconnecting, Connecting)
+                | (Disconnected | Failed, Reconnecting)
+                | (Connecting, Hello)
+                // 认证失败后允许重新握手
+                | (Failed, Hello)
+                | (Hello, Authenticating)
+                | (Authenticating, Ready)
+                | (Ready, Transferring)
+                | (Transferring, Ready)
+        )
+    }
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionStateEvent {
+    pub addr: String,
+    pub from: ConnectionState,
+    pub to: ConnectionState,
+    pub reason: Option<String>,
+}
+
+/// address → 当前状态，未出现过的设备视为 Disconnected
+static STATES: Lazy<Mutex<HashMap<String, ConnectionState>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn current(addr: &str) -> ConnectionState {
+    STATES
+        .lock()
+        .get(addr)
+        .copied()
+        .unwrap_or(ConnectionState::Disconnected)
+}
+
+/// 所有曾出现过的设备的当前状态
+pub fn all() -> HashMap<String, ConnectionState> {
+    STATES.lock().clone()
+}
+
+/// 校验并切换状态，切换到当前状态视为成功且不发事件
+pub fn transition(addr: &str, to: ConnectionState) -> Result<()> {
+    transition_with_reason(addr, to, None)
+}
+
+pub fn transition_with_reason(
+    addr: &str,
+    to: ConnectionState,
+    reason: Option<String>,
+) -> Result<()> {
+    if let Some(event) = apply(addr, to, reason)? {
+        emit(event);
+    }
+    Ok(())
+}
+
+/// 只切换状态，返回需要发出的事件，由调用方在释放自己的锁之后 emit
+fn apply(
+    addr: &str,
+    to: ConnectionState,
+    reason: Option<String>,
+) -> Result<Option<ConnectionStateEvent>> {
+    let mut states = STATES.lock();
+    let from = states
+        .get(addr)
+        .copied()
+        .unwrap_or(ConnectionState::Disconnected);
+    if from == to {
+        return Ok(None);
+    }
+    if !from.can_transition_to(to) {
+        return Err(MiWearError::protocol(format!(
+            "Invalid connection state transition for {}: {} -> {}",
+            addr, from, to
+        ))
+        .into());
+    }
+    states.insert(addr.to_string(), to);
+    Ok(Some(ConnectionStateEvent {
+        addr: addr.to_string(),
+        from,
+        to,
+        reason,
+    }))
+}
+
+/// 切换到 Failed，附带失败原因
+pub fn fail(addr: &str, reason: impl fmt::Display) {
+    let _ = transition_with_reason(addr, ConnectionState::Failed, Some(reason.to_string()));
+}
+
+/// 要求设备处于给定状态，否则返回 Protocol 错误
+pub fn expect(addr: &str, expected: &[ConnectionState], operation: &str) -> Result<()> {
+    let state = current(addr);
+    if expected.contains(&state) {
+        return Ok(());
+    }
+    Err(MiWearError::protocol(format!(
+        "Cannot {} while device {} is {}",
+        operation, addr, state
+    ))
+    .into())
+}
+
+/// address → 进行中的 TransferGuard 数量
+static TRANSFERS: Lazy<Mutex<HashMap<String, usize>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Mass 传输期间保持 Transferring，drop 时回到 Ready
+///
+/// 同一设备可能有多个传输并行排队，按设备计数，最后一个守卫释放时才恢复状态
+pub struct TransferGuard {
+    addr: String,
+}
+
+impl TransferGuard {
+    pub fn begin(addr: &str) -> Result<Self> {
+        // 事件在释放 TRANSFERS 之后发出，监听者里再开始或结束传输不会死锁
+        let event = {
+            let mut transfers = TRANSFERS.lock();
+            let event = apply(addr, ConnectionState::Transferring, None)?;
+            *transfers.entry(addr.to_string()).or_default() += 1;
+            event
+        };
+        if let Some(event) = event {
+            emit(event);
+        }
+        Ok(Self {
+            addr: addr.to_string(),
+        })
+    }
+}
+
+impl Drop for TransferGuard {
+    fn drop(&mut self) {
+        let event = {
+            let mut transfers = TRANSFERS.lock();
+            let Some(count) = transfers.get_mut(&self.addr) else {
+                return;
+            };
+            *count -= 1;
+            if *count > 0 {
+                return;
+            }
+            transfers.remove(&self.addr);
+
+            // 传输中断开时状态已是 Disconnected，不再恢复
+            if current(&self.addr) != ConnectionState::Transferring {
+                return;
+            }
+            apply(&self.addr, ConnectionState::Ready, None)
+        };
+        if let Ok(Some(event)) = event {
+            emit(event);
+        }
+    }
+}
+
+fn emit(event: ConnectionStateEvent) {
+    log::info!(
+        "[ConnectionState] {}: {} -> {}{}",
+        event.addr,
+        event.from,
+        event.to,
+        event
+            .reason
+            .as_ref()
+            .map(|r| format!(" ({})", r))
+            .unwrap_or_default()
+    );
+
+    if let Some(app) = crate::APP_HANDLE.get() {
+        let _ = app.emit(CONNECTION_STATE_EVENT, event.clone());
+    }
+
+    crate::pluginsystem::notify_plugins(PLUGIN_CONNECTION_STATE_EVENT, &event);
+}
@@ -1,6 +1,6 @@
 use std::{
     sync::{
-        atomic::{AtomicU8, Ordering},
+        atomic::{AtomicBool, AtomicU8, Ordering},
         Arc,
     },
     time::Duration,
@@ -8,29 +8,45 @@ use std::{
 
 use anyhow::{anyhow, bail, Context};
 use dashmap::DashMap;
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
-use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+use tokio::sync::{mpsc, oneshot, watch, Mutex, RwLock};
 use uuid::Uuid;
 
 use crate::{
-    bt::device::{BTDevice, ConnectType},
+    bt::device::BTDevice,
     community::provider::official::OfficialProvider,
-    miwear::{device::thirdpartyapp::on_thirdparty_app, network_stack},
+    miwear::{
+        capture::{self, Direction},
+        channels,
+        connection::{self, ConnectionState},
+        device::thirdpartyapp::on_thirdparty_app,
+        error::MiWearError,
+        network_stack, replay,
+    },
     pb::{self},
-    tools::{to_hex_string, uuid_contains},
 };
 
 use super::{
-    bleuuids::{BLE_UUID_KEYWORD_XIAOMI_RECV, BLE_UUID_KEYWORD_XIAOMI_SENT},
-    packet::{self, Channel, OpCode, PacketData},
+    command_pool::{
+        ack_window::{AckWindow, ACK_WINDOW_SIZE},
+        CommandPriority, SchedulerConfig,
+    },
+    device::{operation::OperationRegistry, pacer::FragmentPacer},
+    packet::{self, Channel, FrameReassembler, MiWearPacket, OpCode, PacketData, HELLO_MAGIC},
+    transport::{self, Transport},
 };
 
 pub mod auth;
+pub mod download;
 pub mod firmware;
 pub mod hello;
 pub mod mass;
 pub mod models;
+pub mod operation;
+pub mod pacer;
 pub mod resutils;
+pub mod session;
 pub mod system;
 pub mod thirdpartyapp;
 pub mod watchface;
@@ -47,45 +63,67 @@ pub struct MiWearState {
     pub sec_keys: Option<SecurityKeys>,
     pub network_mtu: u16,
     pub codename: String,
+    /// 自适应分片节奏学习到的分片间隔（微秒）
+    pub fragment_delay_us: Option<u32>,
+    /// 该设备的通道调度参数，为空时使用全局配置
+    pub scheduler: Option<SchedulerConfig>,
+    /// 设备在 session config 中声明可接收的最大帧长
+    pub max_tx_frame_len: Option<usize>,
 }
 
 /// 公共常量：MiWear 请求默认超时时间
 pub(super) const REQ_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(5_000);
 
+/// 认证前等待设备回应 hello 的最长时间
+const HELLO_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 快速认证时探测会话密钥是否有效的超时
+const FAST_REAUTH_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
 type ProtoKey = (u32, u32);
 
 /// 核心设备结构
 pub struct MiWearDevice {
+    /// 设备地址，连接期间不变，也是设备表的键
+    pub addr: String,
     /// 设备信息（RwLock ⇒ 读并发，写独占且短暂）
     pub state: RwLock<MiWearState>,
     /// 待回应表：seq → oneshot sender
     pub pending_seq: DashMap<u8, oneshot::Sender<PacketData>>,
-    /// 待 ACK 表：btrecv -> oneshot sender
-    pub pending_ack: DashMap<(), oneshot::Sender<()>>,
+    /// 待 ACK 表：seq → 等待者（滑动窗口）
+    pub ack_window: AckWindow,
     /// 待回应表：protokey → oneshot sender
     pub pending_proto: DashMap<ProtoKey, oneshot::Sender<pb::protocol::WearPacket>>,
     /// 订阅回调表：type → Vec<callback>
     pub proto_subscribers: DashMap<u32, Vec<Arc<dyn Fn(pb::protocol::WearPacket) + Send + Sync>>>,
     /// 序列号计数器
     pub seq: AtomicU8,
-    /// 蓝牙抽象（BLE / SPP）
-    pub btdevice: BTDevice,
+    /// 传输层抽象（BLE / SPP / 回环）
+    pub transport: Arc<dyn Transport>,
     /// 用于向网络栈发送数据的 Sender
     pub network_tx: Mutex<Option<mpsc::Sender<Vec<u8>>>>,
     /// 已知第三方应用信息表：包名 → AppInfo
     pub app_info_table: DashMap<String, crate::miwear::device::thirdpartyapp::AppInfo>,
-    /// 上行数据包临时存储buffer
-    pub recv_buffer: Mutex<Vec<u8>>,
-    /// 发送锁，用于在 Mass 传输时阻塞其他数据包
+    /// 上行数据流式解帧器
+    pub recv_buffer: Mutex<FrameReassembler>,
+    /// 发送锁，保证同一帧的各分片连续发出
     pub send_lock: Mutex<()>,
     /// 是否处于mass传输状态,
-    pub is_sending_mass: Mutex<bool>,
-    /// 命令池，用于按优先级顺序发送数据包
+    pub is_sending_mass: AtomicBool,
+    /// 命令池，按通道权重公平调度待发数据包
     pub cmd_pool: Arc<crate::miwear::command_pool::CommandPool>,
+    /// 分片发送节奏
+    pub pacer: FragmentPacer,
+    /// 正在进行的可取消操作
+    pub operations: OperationRegistry,
+    /// 设备是否已回应 hello
+    pub hello_acked: watch::Sender<bool>,
     /// 下行速度
     pub network_write_speed: Mutex<f64>,
     /// 上行速度
     pub network_read_speed: Mutex<f64>, 
+    /// 录制或回放会话时注入的认证输入，正常连接为空
+    pub session_inputs: OnceCell<Arc<dyn replay::SessionInputs>>,
 }
 
 impl MiWearDevice {
@@ -93,58 +131,86 @@ impl MiWearDevice {
     pub async fn connect(addr: String, name: String) -> anyhow::Result<Arc<Self>> {
         let connect_type = crate::config::read(|c| c.clone().connect_type);
 
+        connection::transition(&addr, ConnectionState::Connecting)?;
+
         /* 1. 建立物理连接 */
-        let device = match BTDevice::connect(&addr, connect_type.clone())
+        let device = match BTDevice::connect(&addr, connect_type)
             .await
             .map_err(|e| e.to_string())
         {
             Ok(device) => device,
-            Err(e) => return Err(anyhow!("连接失败: {}", e)),
+            Err(e) => {
+                connection::fail(&addr, &e);
+                return Err(MiWearError::transport(format!("连接失败: {}", e)).into());
+            }
         };
 
+        let core = Self::connect_with_transport(transport::from_bt_device(device), name).await?;
+
+        /* 6. 记录为当前设备 / 已配对设备 */
+        let state_clone = core.state.read().await.clone();
+
+        let need_push = !crate::config::read(|c| {
+            c.paired_devices
+                .iter()
+                .any(|dev| dev.addr == state_clone.addr)
+        });
+
+        // 当前设备只在登记为 primary 时写入
+        if need_push {
+            crate::config::write(|c| c.paired_devices.push(state_clone));
+        }
+
+        log::info!("[MiWearDevice] Connected to device successfully!");
+
+        Ok(core)
+    }
+
+    /// 在任意传输层上建立 MiWear 会话（BLE / SPP / 回环）
+    ///
+    /// 不会写入配置文件，调用方自行决定是否把设备记为已配对
+    pub async fn connect_with_transport(
+        transport: Arc<dyn Transport>,
+        name: String,
+    ) -> anyhow::Result<Arc<Self>> {
         /* 2. 提取名字 / 地址 */
-        let (mut device_name, device_address) = match &device.handle {
-            crate::bt::device::Connection::BLE(ble) => (ble.name.clone(), ble.address.clone()),
-            crate::bt::device::Connection::SPP(spp) => {
-                (spp.name.clone().unwrap_or_default(), spp.address.clone())
-            }
-        };
+        let mut device_name = transport.name();
+        let device_address = transport.address();
+
+        connection::transition(&device_address, ConnectionState::Connecting)?;
 
-        if device_name.is_empty() || device_name == "" {
+        if device_name.is_empty() {
             device_name = name.clone();
         }
 
-        /* 3. 枚举 BLE 服务（Android SPP 无需） */
-        let mut charas = MiWearBleCharaUuid {
-            recv: Uuid::nil(),
-            sent: Uuid::nil(),
-        };
-        if let Some(services) = device.ble_services.as_ref() {
-            for service in services {
-                for chara in &service.characteristics {
-                    if uuid_contains(&service.uuid, "fe95", true)
-                        && uuid_contains(&chara.uuid, BLE_UUID_KEYWORD_XIAOMI_RECV, false)
-                    {
-                        charas.recv = chara.uuid;
-                    }
-                    if uuid_contains(&service.uuid, "fe95", true)
-                        && uuid_contains(&chara.uuid, BLE_UUID_KEYWORD_XIAOMI_SENT, false)
-                    {
-                        charas.sent = chara.uuid;
-                    }
-                }
-            }
-        }
+        /* 3. BLE 特征值（非 BLE 链路为 nil） */
+        let charas = transport.ble_charas();
+        let max_frame_size = transport.max_frame_size();
 
-        let recv_uuid = charas.recv;
+        /* 分片节奏：优先使用上次连接学习到的间隔 */
+        let (adaptive_pacing, default_delay_ms) =
+            crate::config::read(|c| (c.adaptive_pacing, c.fragments_send_delay));
+        let learned_delay_us = crate::config::read(|c| {
+            c.paired_devices
+                .iter()
+                .find(|dev| dev.addr == device_address)
+                .and_then(|dev| dev.fragment_delay_us)
+        });
 
-        let mut max_frame_size = 244;
-        if connect_type == ConnectType::SPP {
-            max_frame_size = 977; // 1004(max size) - 27(bluetooth packet header)
-        }
+        /* 通道调度：设备单独设置优先于全局配置 */
+        let device_scheduler = crate::config::read(|c| {
+            c.paired_devices
+                .iter()
+                .find(|dev| dev.addr == device_address)
+                .and_then(|dev| dev.scheduler.clone())
+        });
+        let scheduler_config = device_scheduler
+            .clone()
+            .unwrap_or_else(|| crate::config::read(|c| c.command_scheduler.clone()));
 
         /* 4. 构造核心对象 */
         let core = Arc::new_cyclic(|weak| Self {
+            addr: device_address.clone(),
             state: RwLock::new(MiWearState {
                 name: device_name,
                 addr: device_address,
@@ -154,23 +220,51 @@ impl MiWearDevice {
                 sec_keys: None,
                 network_mtu: 800, /* 默认800 此值过大会导致表端buffer溢出 极限大概在900左右 设置为900会导致不稳定 */
                 codename: String::new(),
+                fragment_delay_us: learned_delay_us,
+                scheduler: device_scheduler,
+                max_tx_frame_len: None,
             }),
             pending_seq: DashMap::new(),
-            pending_ack: DashMap::new(),
+            ack_window: AckWindow::new(ACK_WINDOW_SIZE),
             pending_proto: DashMap::new(),
             proto_subscribers: DashMap::new(),
             seq: AtomicU8::new(0),
-            btdevice: device.clone(),
+            transport: transport.clone(),
             network_tx: Mutex::new(None),
             app_info_table: DashMap::new(),
-            recv_buffer: Mutex::new(Vec::new()),
+            recv_buffer: Mutex::new(FrameReassembler::with_max_body_len(
+                hello::MAX_RX_FRAME_LEN,
+            )),
             send_lock: Mutex::new(()),
-            is_sending_mass: Mutex::new(false),
-            cmd_pool: crate::miwear::command_pool::CommandPool::new(weak.clone()),
+            is_sending_mass: AtomicBool::new(false),
+            cmd_pool: crate::miwear::command_pool::CommandPool::new(weak.clone(), scheduler_config),
+            pacer: FragmentPacer::new(
+                adaptive_pacing,
+                learned_delay_us.unwrap_or(default_delay_ms.saturating_mul(1000)),
+            ),
+            operations: OperationRegistry::default(),
+            hello_acked: watch::channel(false).0,
             network_write_speed: Mutex::new(0.0),
             network_read_speed: Mutex::new(0.0),
+            session_inputs: OnceCell::new(),
         });
 
+        if crate::config::read(|c| c.session_recording) && !replay::is_replaying(&core.addr) {
+            let name = core.state.read().await.name.clone();
+            if let Err(e) = replay::start_recording(&name, &core.addr, max_frame_size) {
+                log::error!("[MiWearDevice] Failed to start session recording: {:#}", e);
+            }
+        }
+        if let Some(inputs) = replay::recording_inputs(&core.addr) {
+            let _ = core.session_inputs.set(inputs);
+        }
+
+        if crate::config::read(|c| c.protocol_capture) {
+            if let Err(e) = capture::start(&core.addr) {
+                log::error!("[MiWearDevice] Failed to start protocol capture: {:#}", e);
+            }
+        }
+
         let network_sender = network_stack::start_network_stack(core.clone());
         *core.network_tx.lock().await = Some(network_sender);
 
@@ -178,67 +272,53 @@ impl MiWearDevice {
 
         /* 5. 启动订阅 → 将数据投递到 handle_bt_packet */
         let dev_clone = Arc::clone(&core);
-        device
-            .subscribe(
-                Arc::new(move |data_result| {
-                    let dev2 = Arc::clone(&dev_clone);
-                    tauri::async_runtime::spawn(async move {
-                        match data_result {
-                            Ok(data) => {
-                                if data.starts_with(&[0xBA, 0xDC]) {
-                                    super::btrecv::handle_bt_packet(dev2, data).await;
-                                } else {
-                                    dev2.push_recv_data(data).await;
-
-                                    match dev2.pop_bt_packet().await {
-                                        Some(value) => {
-                                            super::btrecv::handle_bt_packet(dev2, value).await;
-                                        }
-                                        None => {}
-                                    }
+        let subscribed = transport
+            .subscribe(Arc::new(move |data_result| {
+                let dev2 = Arc::clone(&dev_clone);
+                tauri::async_runtime::spawn(async move {
+                    match data_result {
+                        Ok(data) => {
+                            capture::record(&dev2, Direction::Rx, &data).await;
+                            replay::record(&dev2.addr, Direction::Rx, &data);
+                            if data.starts_with(&HELLO_MAGIC) {
+                                super::btrecv::handle_bt_packet(dev2, data).await;
+                            } else {
+                                dev2.push_recv_data(data).await;
+
+                                while let Some(pkt) = dev2.pop_packet().await {
+                                    super::btrecv::handle_miwear_packet(dev2.clone(), pkt)
+                                        .await;
                                 }
                             }
-                            Err(e) => {
-                                log::error!("BT subscription error: {}", e);
-                                crate::miwear::set_connected_device(None).await;
-                                log::info!(
-                                    "Device disconnected due to error, cleaned CONNECTED_DEVICE"
-                                );
-                                let _ = dev2.disconnect().await;
-                            }
                         }
-                    });
-                }),
-                Some(recv_uuid),
-            )
-            .await?;
+                        Err(e) => {
+                            log::error!("BT subscription error on {}: {}", dev2.addr, e);
+                            // 链路异常断开，交给重连监督任务处理
+                            super::reconnect::mark_link_lost(&dev2.addr);
+                            let _ = dev2.disconnect().await;
+                        }
+                    }
+                });
+            }))
+            .await;
+        if let Err(e) = subscribed {
+            connection::fail(&core.addr, &e);
+            return Err(e);
+        }
 
         log::info!("Listening messages from device...");
 
-        let state_clone = core.state.read().await.clone();
+        let addr = core.state.read().await.addr.clone();
 
         let mtu = crate::config::read(|c| {
             c.paired_devices
                 .iter()
-                .find(|dev| dev.addr == state_clone.addr)
+                .find(|dev| dev.addr == addr)
                 .map_or(0, |dev| dev.network_mtu)
         });
 
         core.state.write().await.network_mtu = mtu;
 
-        let need_push = !crate::config::read(|c| {
-            c.paired_devices
-                .iter()
-                .any(|dev| dev.addr == state_clone.addr)
-        });
-
-        crate::config::write(|c| {
-            c.current_device = Some(state_clone.clone());
-            if need_push {
-                c.paired_devices.push(state_clone);
-            }
-        });
-
         let dev_clone_proto = Arc::clone(&core);
         core.subscribe_proto(
             pb::protocol::wear_packet::Type::ThirdpartyApp as u32,
@@ -247,40 +327,68 @@ impl MiWearDevice {
             }),
         );
 
-        log::info!("[MiWearDevice] Connected to device successfully!");
-
         Ok(core)
     }
 
     /* ───────────── 断开连接 ───────────── */
     pub async fn disconnect(&self) -> anyhow::Result<()> {
         *self.network_tx.lock().await = None;
+        self.operations.abandon_all();
+        self.ack_window.clear();
+
+        let device_addr = self.addr.clone();
 
-        let device_addr = self.state.read().await.addr.clone();
+        if let Some(delay_us) = self.pacer.learned_delay_us() {
+            self.state.write().await.fragment_delay_us = Some(delay_us);
+            crate::config::write(|c| {
+                if let Some(dev) = c.paired_devices.iter_mut().find(|d| d.addr == device_addr) {
+                    dev.fragment_delay_us = Some(delay_us);
+                }
+            });
+            log::info!(
+                "MiWearDevice::disconnect: Learned fragment delay {}us for {}",
+                delay_us,
+                &device_addr
+            );
+        }
 
-        self.btdevice.disconnect().await?;
+        // 即使链路断开失败也要从设备表中移除，避免残留不可用的设备
+        let ret = self.transport.disconnect().await;
+        if !crate::miwear::unregister_device(self).await {
+            // 该地址已重连为新的连接，按地址索引的状态都属于新连接
+            log::info!(
+                "MiWearDevice::disconnect: {} was superseded by a newer connection",
+                &device_addr
+            );
+            return ret;
+        }
         log::info!(
-            "MiWearDevice::disconnect: Successfully disconnected from Bluetooth device {}",
+            "MiWearDevice::disconnect: Removed {} from device registry",
             &device_addr
         );
+        let _ = connection::transition(&device_addr, ConnectionState::Disconnected);
+        capture::stop(&device_addr);
+        replay::stop_recording(&device_addr);
+        channels::reset(&device_addr);
+        crate::miwear::notify_disconnect(&device_addr);
+        ret?;
 
-        crate::miwear::set_connected_device(None).await;
         log::info!(
-            "MiWearDevice::disconnect: Cleared CONNECTED_DEVICE for {}",
+            "MiWearDevice::disconnect: Successfully disconnected from Bluetooth device {}",
             &device_addr
         );
-        crate::miwear::notify_disconnect();
 
         Ok(())
     }
 
     /* ───────────── 发送 ───────────── */
     pub async fn send(&self, payload: Vec<u8>) -> anyhow::Result<()> {
-        let chara = { self.state.read().await.bleservice.sent };
-        match &self.btdevice.handle {
-            crate::bt::device::Connection::BLE(_) => self.btdevice.send(payload, Some(chara)).await,
-            crate::bt::device::Connection::SPP(_) => self.btdevice.send(payload, None).await,
-        }
+        capture::record(self, Direction::Tx, &payload).await;
+        replay::record(&self.addr, Direction::Tx, &payload);
+        self.transport
+            .send(payload)
+            .await
+            .map_err(|e| MiWearError::transport(format!("{:#}", e)).into())
     }
 
     pub(super) async fn send_fragments(&self, frame: Vec<u8>) -> anyhow::Result<()> {
@@ -290,11 +398,9 @@ impl MiWearDevice {
             self.send(frame).await
         } else {
             for chunk in frame.chunks(state.max_frame_size) {
-                tokio::time::sleep(Duration::from_millis(
-                    crate::config::read(|c| c.clone().fragments_send_delay).into(),
-                ))
-                .await;
+                tokio::time::sleep(self.pacer.delay()).await;
                 self.send(chunk.to_vec()).await?;
+                self.pacer.on_sent(chunk.len());
             }
             Ok(())
         }
@@ -314,6 +420,7 @@ impl MiWearDevice {
                 payload: payload.to_vec(),
                 kind: crate::miwear::command_pool::CommandKind::Send,
                 timeout: None,
+                priority: CommandPriority::Normal,
                 responder: tx,
             })
             .await;
@@ -336,6 +443,7 @@ impl MiWearDevice {
                 payload: payload.to_vec(),
                 kind: crate::miwear::command_pool::CommandKind::WaitAck,
                 timeout,
+                priority: CommandPriority::Normal,
                 responder: tx,
             })
             .await;
@@ -357,6 +465,7 @@ impl MiWearDevice {
                 payload: payload.to_vec(),
                 kind: crate::miwear::command_pool::CommandKind::RegisterAck { unlocked: false },
                 timeout: None,
+                priority: CommandPriority::Normal,
                 responder: tx,
             })
             .await;
@@ -380,6 +489,7 @@ impl MiWearDevice {
                 payload: payload.to_vec(),
                 kind: crate::miwear::command_pool::CommandKind::RegisterAck { unlocked: true },
                 timeout: None,
+                priority: CommandPriority::Normal,
                 responder: tx,
             })
             .await;
@@ -389,6 +499,24 @@ impl MiWearDevice {
         }
     }
 
+    /// 只等待命令进入队列而不等待发送完成，供网络栈等高吞吐的调用方使用
+    ///
+    /// 通道达到队列上限时会一直等待，由此把背压传递给上游
+    pub async fn enqueue_miwear_pkt(&self, channel: Channel, op: OpCode, payload: Vec<u8>) {
+        let (tx, _rx) = oneshot::channel();
+        self.cmd_pool
+            .push(crate::miwear::command_pool::Command {
+                channel,
+                op,
+                payload,
+                kind: crate::miwear::command_pool::CommandKind::Send,
+                timeout: None,
+                priority: CommandPriority::Normal,
+                responder: tx,
+            })
+            .await;
+    }
+
     pub async fn request(
         &self,
         channel: Channel,
@@ -396,12 +524,39 @@ impl MiWearDevice {
         payload: &[u8],
         timeout: Option<Duration>,
     ) -> anyhow::Result<PacketData> {
-        let (seq, frame) = self.build_frame(channel, op, payload).await?;
         let (tx, rx) = oneshot::channel();
-        self.pending_seq.insert(seq, tx);
-        let _guard = self.send_lock.lock().await;
-        self.send_fragments(frame).await?;
-        Ok(tokio::time::timeout(timeout.unwrap_or(REQ_TIMEOUT), rx).await??)
+        let (sent_tx, sent_rx) = oneshot::channel();
+        self.cmd_pool
+            .push(crate::miwear::command_pool::Command {
+                channel,
+                op,
+                payload: payload.to_vec(),
+                // 在帧发出前登记 seq，避免回应先于登记到达
+                kind: crate::miwear::command_pool::CommandKind::Request {
+                    on_seq: Box::new(move |device: &MiWearDevice, seq: u8| {
+                        device.pending_seq.insert(seq, tx);
+                    }),
+                },
+                timeout: None,
+                priority: CommandPriority::Normal,
+                responder: sent_tx,
+            })
+            .await;
+        let seq = match sent_rx.await?? {
+            crate::miwear::command_pool::CommandResponse::Sent(seq) => seq,
+            _ => return Err(anyhow!("unexpected response")),
+        };
+        match tokio::time::timeout(timeout.unwrap_or(REQ_TIMEOUT), rx).await {
+            Ok(ret) => Ok(ret?),
+            Err(_) => {
+                self.pending_seq.remove(&seq);
+                Err(MiWearError::timeout(format!(
+                    "response to seq {} on {:?}",
+                    seq, channel
+                ))
+                .into())
+            }
+        }
     }
 
     pub async fn request_proto(
@@ -413,12 +568,59 @@ impl MiWearDevice {
         expect_id: u32,
         timeout: Option<Duration>,
     ) -> anyhow::Result<pb::protocol::WearPacket> {
-        let (_seq, frame) = self.build_frame(channel, op, payload).await?;
+        self.request_proto_with_priority(
+            channel,
+            op,
+            payload,
+            expect_type,
+            expect_id,
+            timeout,
+            CommandPriority::Normal,
+        )
+        .await
+    }
+
+    /// 与 `request_proto` 相同，但可指定命令在所在通道中的优先级
+    #[allow(clippy::too_many_arguments)]
+    pub async fn request_proto_with_priority(
+        &self,
+        channel: Channel,
+        op: OpCode,
+        payload: &[u8],
+        expect_type: u32,
+        expect_id: u32,
+        timeout: Option<Duration>,
+        priority: CommandPriority,
+    ) -> anyhow::Result<pb::protocol::WearPacket> {
         let (tx, rx) = oneshot::channel();
         self.pending_proto.insert((expect_type, expect_id), tx);
-        let _guard = self.send_lock.lock().await;
-        self.send_fragments(frame).await?;
-        Ok(tokio::time::timeout(timeout.unwrap_or(REQ_TIMEOUT), rx).await??)
+        let (sent_tx, sent_rx) = oneshot::channel();
+        self.cmd_pool
+            .push(crate::miwear::command_pool::Command {
+                channel,
+                op,
+                payload: payload.to_vec(),
+                kind: crate::miwear::command_pool::CommandKind::Send,
+                timeout: None,
+                priority,
+                responder: sent_tx,
+            })
+            .await;
+        if let Err(e) = sent_rx.await.map_err(anyhow::Error::from).and_then(|r| r) {
+            self.pending_proto.remove(&(expect_type, expect_id));
+            return Err(e);
+        }
+        match tokio::time::timeout(timeout.unwrap_or(REQ_TIMEOUT), rx).await {
+            Ok(ret) => Ok(ret?),
+            Err(_) => {
+                self.pending_proto.remove(&(expect_type, expect_id));
+                Err(MiWearError::timeout(format!(
+                    "response for ProtoKey ({}, {})",
+                    expect_type, expect_id
+                ))
+                .into())
+            }
+        }
     }
 
     pub fn subscribe_proto(
@@ -469,22 +671,23 @@ impl MiWearDevice {
 
                 Ok(packet)
             }
-            Ok(Err(e)) => {
+            Ok(Err(_)) => {
+                // sender 被丢弃说明设备对象已释放，按链路错误处理
                 self.pending_proto.remove(&proto_key);
-                Err(anyhow!(
-                    "Failed to receive proto: sender was dropped for ProtoKey ({}, {}). Error: {}",
-                    expect_type,
-                    expect_id,
-                    e
+                Err(MiWearError::transport(format!(
+                    "Connection dropped while waiting for ProtoKey ({}, {})",
+                    expect_type, expect_id
                 ))
+                .into())
             }
             Err(_) => {
                 self.pending_proto.remove(&proto_key);
-                Err(anyhow!(
-                    "Timeout waiting for ProtoKey ({}, {}) (大狗大狗叫叫叫，你的资源疑似没装上。试着重启一下手环？)",
+                Err(MiWearError::timeout(format!(
+                    "waiting for ProtoKey ({}, {}) (大狗大狗叫叫叫，你的资源疑似没装上。试着重启一下手环？)",
                     expect_type,
                     expect_id
                 ))
+                .into())
             }
         }
     }
@@ -514,6 +717,7 @@ impl MiWearDevice {
         let frame =
             packet::MiWearPacket::new_data(seq, channel, op, &std::borrow::Cow::Borrowed(&pdata))
                 .encode();
+        self.ack_window.track(seq);
         Ok((seq, frame))
     }
 
@@ -523,120 +727,149 @@ impl MiWearDevice {
 
     /* ───────────── 功能性函数 ───────────── */
     pub async fn start_hello(self: &Arc<Self>) -> anyhow::Result<()> {
-        hello::hello_packet(self.clone()).await
+        connection::transition(&self.addr, ConnectionState::Hello)?;
+        self.hello_acked.send_replace(false);
+
+        let ret = hello::hello_packet(self.clone()).await;
+        if let Err(e) = &ret {
+            connection::fail(&self.addr, e);
+        }
+        ret
+    }
+
+    /// 等待设备回应 hello，超时返回 `MiWearError::Timeout`
+    async fn wait_hello_acked(&self) -> anyhow::Result<()> {
+        let mut rx = self.hello_acked.subscribe();
+        match tokio::time::timeout(HELLO_TIMEOUT, rx.wait_for(|acked| *acked)).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(_)) => Err(MiWearError::transport("Device dropped during hello").into()),
+            Err(_) => Err(MiWearError::timeout("hello").into()),
+        }
     }
 
     pub async fn start_auth(self: &Arc<Self>, auth_key: String) -> anyhow::Result<()> {
-        {
-            let mut st = self.state.write().await;
-            if st.sec_keys.is_some() {
-                bail!("The device has been authenticated");
-            }
-            st.authkey = auth_key.clone();
+        if self.state.read().await.sec_keys.is_some() {
+            bail!("The device has been authenticated");
+        }
+
+        // 必须在 hello 完成之后才能开始认证
+        connection::expect(&self.addr, &[ConnectionState::Hello], "authenticate")?;
+        if let Err(e) = self.wait_hello_acked().await {
+            connection::fail(&self.addr, &e);
+            return Err(e);
+        }
+        connection::transition(&self.addr, ConnectionState::Authenticating)?;
+
+        self.state.write().await.authkey = auth_key.clone();
+        log::info!(
+            "[MiWearDevice.start_auth] auth_key={}…",
+            auth_key.get(..4).unwrap_or_default()
+        );
+        let ret = match self.try_fast_reauth(&auth_key).await {
+            Ok(true) => Ok(()),
+            Ok(false) => self.full_auth(&auth_key).await,
+            Err(e) => Err(e),
+        };
+
+        match &ret {
+            Ok(()) => connection::transition(&self.addr, ConnectionState::Ready)?,
+            Err(e) => connection::fail(&self.addr, e),
         }
-        log::info!("[MiWearDevice.start_auth] auth_key={}", &auth_key);
-        let ret = auth::do_auth(self.clone()).await;
 
         let state = self.state.read().await.clone();
         crate::config::write(|c| {
-            for dev in &mut c.paired_devices {
-                if dev.name == state.name {
+            for dev in c
+                .paired_devices
+                .iter_mut()
+                .chain(c.current_device.iter_mut())
+            {
+                if dev.addr == state.addr {
                     *dev = state.clone();
                 }
             }
-            c.current_device = Some(state.clone());
         });
 
         ret
     }
 
-    pub async fn push_recv_data(self: &Arc<Self>, data: Vec<u8>) {
-        self.recv_buffer.lock().await.extend(data);
-    }
-
-    pub async fn pop_bt_packet(self: &Arc<Self>) -> Option<Vec<u8>> {
-        let mut recv: tokio::sync::MutexGuard<'_, Vec<u8>> = self.recv_buffer.lock().await;
-
-        let buf: Vec<u8> = recv.clone();
-
-        let mut result: Vec<u8> = Vec::new();
-
-        let mut idx = 0;
-
-        #[cfg(debug_assertions)]
-        {
-            log::info!(
-                "[MiWearDevice] pop_bt_packet start recv_buffer(size: {})",
-                recv.len()
-            );
-            let hex_recv = to_hex_string(&buf.clone());
-            log::info!("[MiWearDevice] pop_bt_packet hex_recv: {}", hex_recv);
-            log::info!("[MiWearDevice] pop_bt_packet while: {}", buf.len() - idx);
+    /// 完整的两步认证，成功后缓存会话密钥供下次快速认证
+    async fn full_auth(self: &Arc<Self>, auth_key: &str) -> anyhow::Result<()> {
+        auth::do_auth(self.clone())
+            .await
+            .map_err(|e| match MiWearError::from(e) {
+                // 链路层错误保持原样，其余都视为认证失败
+                e @ (MiWearError::Transport { .. } | MiWearError::Timeout { .. }) => e.into(),
+                e => anyhow::Error::from(MiWearError::auth(e)),
+            })?;
+
+        if let Some(keys) = self.state.read().await.sec_keys.clone() {
+            session::save(&self.addr, auth_key, &keys);
         }
+        Ok(())
+    }
 
-        while buf.len() - idx > 8 {
-            #[cfg(debug_assertions)]
-            {
-                log::info!("[MiWearDevice] pop_bt_packet while: {}", buf.len() - idx);
+    /// 尝试复用缓存的会话密钥，返回 false 时需要走完整认证
+    ///
+    /// 设备不认旧密钥时无法解密请求，探测会超时或收到无法解析的回应
+    async fn try_fast_reauth(self: &Arc<Self>, auth_key: &str) -> anyhow::Result<bool> {
+        let load = || {
+            if crate::config::read(|c| c.fast_reauth) {
+                session::load(&self.addr, auth_key)
+            } else {
+                None
             }
+        };
+        let cached = match self.session_inputs.get() {
+            Some(inputs) => inputs.cached_session(&load),
+            None => load(),
+        };
+        let Some(keys) = cached else {
+            return Ok(false);
+        };
 
-            if buf[idx..].starts_with(&[0xA5, 0xA5]) {
-                let len = u16::from_le_bytes(buf[idx + 4..idx + 6].try_into().unwrap()) as usize;
-                let end_idx = idx + len + 8;
-
-                #[cfg(debug_assertions)]
-                {
-                    log::info!("[MiWearDevice] pop_bt_packet idx: {}", idx);
-                    log::info!("[MiWearDevice] pop_bt_packet packet len: {}", len);
-                    log::info!("[MiWearDevice] pop_bt_packet packet end: {}", end_idx);
-                    log::info!(
-                        "[MiWearDevice] pop_bt_packet packet succ: {}",
-                        buf.len() >= end_idx
-                    );
-                    log::info!("[MiWearDevice] pop_bt_packet buf len: {}", buf.len());
-                }
-
-                if buf.len() >= end_idx {
-                    result.extend(buf[idx..end_idx].to_vec());
-                    idx = end_idx;
-
-                    #[cfg(debug_assertions)]
-                    {
-                        let hex = to_hex_string(&result.clone());
-
-                        log::info!("[MiWearDevice] pop_bt_packet packet: {}", hex);
-                        log::info!("[MiWearDevice] pop_bt_packet idx: {}", idx);
-                    }
-                } else {
-                    break;
-                }
-            } else {
-                #[cfg(debug_assertions)]
-                {
-                    log::info!("[MiWearDevice] pop_bt_packet skip: {}", buf.len() - idx);
+        self.state.write().await.sec_keys = Some(keys);
+        match system::system_probe(self.clone(), FAST_REAUTH_PROBE_TIMEOUT).await {
+            Ok(()) => {
+                log::info!("[MiWearDevice] Reused cached session keys for {}", self.addr);
+                Ok(true)
+            }
+            Err(e) => {
+                self.state.write().await.sec_keys = None;
+                if let Some(MiWearError::Transport { .. }) = e.downcast_ref::<MiWearError>() {
+                    return Err(e);
                 }
-
-                idx += 1;
+                log::warn!(
+                    "[MiWearDevice] Device {} rejected cached session keys ({:#}), falling back to full auth",
+                    self.addr,
+                    e
+                );
+                session::clear(&self.addr);
+                Ok(false)
             }
         }
+    }
 
-        if idx != 0 {
-            recv.drain(0..=idx - 1);
+    pub async fn push_recv_data(self: &Arc<Self>, data: Vec<u8>) {
+        self.recv_buffer.lock().await.push(&data);
+    }
 
-            #[cfg(debug_assertions)]
-            {
-                log::info!(
-                    "[MiWearDevice] pop_bt_packet end recv_buffer(size: {})",
-                    recv.len()
-                );
-            }
-        }
+    /// 从接收缓冲区中取出下一个完整的 MiWear 包
+    pub async fn pop_packet(self: &Arc<Self>) -> Option<MiWearPacket> {
+        let mut recv = self.recv_buffer.lock().await;
+        let pkt = recv.next_packet();
 
-        if result.len() > 0 {
-            return Some(result);
+        #[cfg(debug_assertions)]
+        if let Some(pkt) = &pkt {
+            log::info!(
+                "[MiWearDevice] pop_packet seq={} type={:?} body={} remaining={}",
+                pkt.seq,
+                pkt.pkt_type,
+                pkt.body.len(),
+                recv.buffered()
+            );
         }
 
-        None
+        pkt
     }
 
     pub async fn get_codename(self: &Arc<Self>) -> anyhow::Result<String> {
@@ -13,7 +13,10 @@ use prost::Message;
 use sha2::Sha256;
 
 pub async fn do_auth(device: Arc<MiWearDevice>) -> Result<()> {
-    let nonce = crate::tools::generate_random_bytes(16);
+    let nonce = match device.session_inputs.get() {
+        Some(inputs) => inputs.auth_nonce(),
+        None => crate::tools::generate_random_bytes(16),
+    };
 
     let app_verify = build_auth_step_1(&nonce);
     let ret = device
@@ -196,7 +199,7 @@ async fn build_auth_step_2(
     Ok(pkt)
 }
 
-fn string_to_u8_16(s: &String) -> Option<[u8; 16]> {
+pub(crate) fn string_to_u8_16(s: &String) -> Option<[u8; 16]> {
     if s.len() != 32 {
         return None;
     }
@@ -212,7 +215,11 @@ fn string_to_u8_16(s: &String) -> Option<[u8; 16]> {
     Some(result)
 }
 
-fn kdf_miwear(secret_key: &[u8; 16], phone_nonce: &[u8; 16], watch_nonce: &[u8; 16]) -> [u8; 64] {
+pub(crate) fn kdf_miwear(
+    secret_key: &[u8; 16],
+    phone_nonce: &[u8; 16],
+    watch_nonce: &[u8; 16],
+) -> [u8; 64] {
     type HmacSha256 = Hmac<Sha256>;
 
     // 1) hmac_key = HMAC(init_key, secret_key)
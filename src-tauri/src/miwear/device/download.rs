@@ -0,0 +1,238 @@
+//! 设备 → 本机的文件下载，相当于 `send_mass` 的接收端
+//!
+//! 手表在 FileFitness / FileSensor 通道上按与 Mass 相同的格式分块发送文件：
+//! 每帧 total(u16 LE) | current(u16 LE，从 1 开始) | 分块数据，
+//! 拼好后为 comp_data | data_type | md5 | length | data | crc32。
+//! 长度与 CRC 校验通过后保存到 `app_data_dir/downloads`，元数据记在其中的 `index.json`。
+
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    miwear::{
+        channels::{self, ChannelPayload, PayloadKind},
+        device::mass::packet::parse_mass_inner,
+        packet::Channel,
+    },
+    tools::to_hex_string,
+};
+
+/// 接收文件的通道
+pub const DOWNLOAD_CHANNELS: [Channel; 2] = [Channel::FileFitness, Channel::FileSensor];
+
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "file-download-progress";
+pub const DOWNLOAD_EVENT: &str = "file-downloaded";
+pub const PLUGIN_DOWNLOAD_EVENT: &str = "onFileDownloaded";
+
+/// 一个已保存的下载文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadedFile {
+    /// 解压后数据的 md5（hex），同一文件重复下载时覆盖
+    pub id: String,
+    pub addr: String,
+    /// 通道名，如 "FileFitness"
+    pub channel: String,
+    pub data_type: u8,
+    pub compress_mode: u8,
+    /// 解压后的大小
+    pub size: usize,
+    /// 包头 md5 与数据一致
+    pub md5_ok: bool,
+    /// unix 时间戳（秒）
+    pub received_at: u64,
+    /// 相对下载目录的文件名
+    pub file_name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub addr: String,
+    pub channel: Channel,
+    pub current: u16,
+    pub total: u16,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct DownloadIndex {
+    files: Vec<DownloadedFile>,
+}
+
+static DOWNLOAD_DIR: OnceCell<PathBuf> = OnceCell::new();
+static INDEX: Lazy<Mutex<DownloadIndex>> = Lazy::new(|| Mutex::new(DownloadIndex::default()));
+
+pub async fn init(app: &AppHandle) -> Result<()> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .context("app_data_dir unavailable; did you call after app was ready?")?
+        .join("downloads");
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .with_context(|| format!("create download dir: {}", dir.display()))?;
+    DOWNLOAD_DIR.set(dir.clone()).ok();
+
+    let path = dir.join("index.json");
+    if path.exists() {
+        let json = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("read download index: {}", path.display()))?;
+        let mut index: DownloadIndex = match serde_json::from_str(&json) {
+            Ok(index) => index,
+            Err(e) => {
+                log::warn!(
+                    "[FileDownload] Download index is corrupted, rebuilding: {}",
+                    e
+                );
+                DownloadIndex::default()
+            }
+        };
+        // 文件被手动删掉的记录不再保留
+        index.files.retain(|f| dir.join(&f.file_name).exists());
+        *INDEX.lock() = index;
+    }
+
+    // 拼包由 channels 中注册的分块解码器完成，这里只读分块头上报进度
+    for channel in DOWNLOAD_CHANNELS {
+        channels::subscribe(channel, PayloadKind::Raw, Arc::new(on_part));
+        channels::subscribe(channel, PayloadKind::Reassembled, Arc::new(on_block));
+    }
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn download_dir() -> Result<&'static PathBuf> {
+    DOWNLOAD_DIR
+        .get()
+        .context("File download store is not initialized")
+}
+
+fn persist(index: &DownloadIndex) -> Result<()> {
+    let path = download_dir()?.join("index.json");
+    let data = serde_json::to_string_pretty(index)?;
+    std::fs::write(&path, data).with_context(|| format!("write download index: {}", path.display()))
+}
+
+/// 按接收时间从新到旧列出
+pub fn list() -> Vec<DownloadedFile> {
+    let mut files = INDEX.lock().files.clone();
+    files.sort_by(|a, b| b.received_at.cmp(&a.received_at));
+    files
+}
+
+pub fn get(id: &str) -> Option<DownloadedFile> {
+    INDEX.lock().files.iter().find(|f| f.id == id).cloned()
+}
+
+pub fn path_of(id: &str) -> Result<PathBuf> {
+    let file = get(id).with_context(|| format!("Download {} not found", id))?;
+    Ok(download_dir()?.join(file.file_name))
+}
+
+pub fn read(id: &str) -> Result<Vec<u8>> {
+    let path = path_of(id)?;
+    std::fs::read(&path).with_context(|| format!("read download: {}", path.display()))
+}
+
+pub fn remove(id: &str) -> Result<bool> {
+    let mut index = INDEX.lock();
+    let Some(pos) = index.files.iter().position(|f| f.id == id) else {
+        return Ok(false);
+    };
+    let file = index.files.remove(pos);
+    let path = download_dir()?.join(&file.file_name);
+    if let Err(e) = std::fs::remove_file(&path) {
+        log::warn!("[FileDownload] Failed to remove {}: {}", path.display(), e);
+    }
+    persist(&index)?;
+    Ok(true)
+}
+
+fn on_part(payload: &ChannelPayload) {
+    let Some(header) = payload.data.get(..4) else {
+        return;
+    };
+    let progress = DownloadProgress {
+        addr: payload.addr.clone(),
+        channel: payload.channel,
+        total: u16::from_le_bytes([header[0], header[1]]),
+        current: u16::from_le_bytes([header[2], header[3]]),
+    };
+    if let Some(app) = crate::APP_HANDLE.get() {
+        let _ = app.emit(DOWNLOAD_PROGRESS_EVENT, progress);
+    }
+}
+
+fn on_block(payload: &ChannelPayload) {
+    let addr = payload.addr.clone();
+    let channel = payload.channel;
+    let block = payload.data.clone();
+    // 解压、写盘放到阻塞线程，不占用蓝牙接收任务
+    tauri::async_runtime::spawn_blocking(move || match save(&addr, channel, &block) {
+        Ok(file) => {
+            log::info!(
+                "[FileDownload] Saved {} ({} bytes) from {} on {:?}",
+                file.file_name,
+                file.size,
+                addr,
+                channel
+            );
+            crate::health::on_downloaded(&file);
+            if let Some(app) = crate::APP_HANDLE.get() {
+                let _ = app.emit(DOWNLOAD_EVENT, file.clone());
+            }
+            crate::pluginsystem::notify_plugins(PLUGIN_DOWNLOAD_EVENT, &file);
+        }
+        Err(e) => log::error!(
+            "[FileDownload] Failed to save {:?} file from {}: {:#}",
+            channel,
+            addr,
+            e
+        ),
+    });
+}
+
+fn save(addr: &str, channel: Channel, block: &[u8]) -> Result<DownloadedFile> {
+    let received = parse_mass_inner(block, None)?;
+    received
+        .ensure_crc()
+        .with_context(|| format!("{} bytes discarded", block.len()))?;
+
+    let id = to_hex_string(&crate::tools::calc_md5(&received.data));
+    let file = DownloadedFile {
+        file_name: format!("{}.bin", id),
+        id,
+        addr: addr.to_string(),
+        channel: format!("{:?}", channel),
+        data_type: received.data_type,
+        compress_mode: received.compress_mode,
+        size: received.data.len(),
+        md5_ok: received.md5_ok,
+        received_at: now(),
+    };
+
+    let path = download_dir()?.join(&file.file_name);
+    std::fs::write(&path, &received.data)
+        .with_context(|| format!("write download: {}", path.display()))?;
+
+    let mut index = INDEX.lock();
+    index.files.retain(|f| f.id != file.id);
+    index.files.push(file.clone());
+    persist(&index)?;
+    Ok(file)
+}
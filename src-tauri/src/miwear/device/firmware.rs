@@ -1,30 +1,51 @@
 use anyhow::{bail, Result};
 use prost::Message;
 use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
 
 use crate::{
-    miwear::packet::{Channel, OpCode},
+    miwear::{
+        error::MiWearError,
+        packet::{Channel, OpCode},
+    },
     pb::{self},
 };
 
 use super::{
-    mass::{packet::MassDataType, SendMassCallbackData},
+    mass::{packet::MassDataType, SendMassCallbackData, SendMassOptions},
+    operation::cancellable,
     MiWearDevice,
 };
 
 pub async fn install_firmware<F>(
     device: Arc<MiWearDevice>,
     file_path: &String,
+    cancel: CancellationToken,
     progress_cb: F,
 ) -> Result<()>
 where
     F: Fn(SendMassCallbackData) + Send + Sync,
 {
     let file_data = crate::fs::read_file_cross_platform(file_path).await?;
+
+    install_firmware_data(device, file_data, cancel, progress_cb).await
+}
+
+/// 安装已读入内存的固件数据（不经过文件系统）
+pub async fn install_firmware_data<F>(
+    device: Arc<MiWearDevice>,
+    file_data: Vec<u8>,
+    cancel: CancellationToken,
+    progress_cb: F,
+) -> Result<()>
+where
+    F: Fn(SendMassCallbackData) + Send + Sync,
+{
     let file_md5 = crate::tools::calc_md5(&file_data);
 
-    let firmware_install_ret = device
-        .request_proto(
+    let firmware_install_ret = cancellable(
+        &cancel,
+        device.request_proto(
             Channel::Pb,
             OpCode::Encrypted,
             &build_firmware_install_request(
@@ -36,28 +57,33 @@ where
             pb::protocol::wear_packet::Type::System as u32,
             pb::protocol::system::SystemId::PrepareOta as u32,
             None,
-        )
-        .await?;
+        ),
+    )
+    .await?;
 
     match firmware_install_ret.__OPENSOURCE_DELETED__.unwrap() {
         pb::protocol::wear_packet::Payload::System(system) => match system.__OPENSOURCE_DELETED__.unwrap() {
             pb::protocol::system::Payload::PrepareOtaResponse(response) => {
                 if response.__OPENSOURCE_DELETED__ == pb::protocol::PrepareStatus::Ready as i32 {
-                    super::mass::send_mass(&device, file_data, MassDataType::FIRMWARE, progress_cb)
-                        .await?;
+                    super::mass::send_mass(
+                        &device,
+                        file_data,
+                        MassDataType::FIRMWARE,
+                        SendMassOptions::wait_for_acks().with_cancel(cancel.clone()),
+                        progress_cb,
+                    )
+                    .await?
+                    .ensure_complete()?;
                 } else {
-                    #[cfg(debug_assertions)] {
-                        bail!(
-                            "Prepare not READY! Full resp: {}",
-                            serde_json::to_string(&response).unwrap()
-                        );
-                    }
-                    #[cfg(not(debug_assertions))] {
-                        bail!(
-                            "Prepare not READY! Error Info={}",
-                            super::error::get_prepare_error_info(response.__OPENSOURCE_DELETED__ as i32)
-                        );
+                    #[cfg(debug_assertions)]
+                    log::error!(
+                        "[MiWearDevice] Prepare not READY! Full resp: {}",
+                        serde_json::to_string(&response).unwrap()
+                    );
+                    return Err(MiWearError::PrepareRejected {
+                        status: response.__OPENSOURCE_DELETED__ as i32,
                     }
+                    .into());
                 }
             }
             _ => {
@@ -6,6 +6,9 @@ use crate::tools::hex_stream_to_bytes;
 
 use super::MiWearDevice;
 
+/// session config 中 App 声明的最大帧长（TLV type 2 = 0xFC00），设备发来的帧不应超过它
+pub const MAX_RX_FRAME_LEN: usize = 0xFC00;
+
 pub async fn hello_packet(device: Arc<MiWearDevice>) -> Result<()> {
     let pkt = hex_stream_to_bytes("badcfe00c00300000100ef").unwrap();
     device.send(pkt).await?;
@@ -20,3 +23,20 @@ pub async fn session_config_packet(device: Arc<MiWearDevice>) -> Result<()> {
 
     Ok(())
 }
+
+/// 从设备回应的 session config body 中取出设备可接收的最大帧长（TLV type 2）
+///
+/// body 首字节之后为 type u8 | len u16 | value 的 TLV 序列，格式不符时返回 None
+pub fn negotiated_frame_len(body: &[u8]) -> Option<usize> {
+    let mut rest = body.get(1..)?;
+    while rest.len() >= 3 {
+        let tlv_type = rest[0];
+        let len = u16::from_le_bytes([rest[1], rest[2]]) as usize;
+        let value = rest.get(3..3 + len)?;
+        if tlv_type == 2 && len == 2 {
+            return Some(u16::from_le_bytes([value[0], value[1]]) as usize);
+        }
+        rest = &rest[3 + len..];
+    }
+    None
+}
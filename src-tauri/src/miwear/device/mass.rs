@@ -1,30 +1,32 @@
-use super::MiWearDevice;
+use super::{operation::cancellable, MiWearDevice};
 use crate::{
-    miwear::packet::{Channel, OpCode},
+    miwear::{
+        error::MiWearError,
+        packet::{Channel, OpCode},
+    },
     pb::{self},
 };
 use anyhow::{bail, Context, Result};
 use byteorder::{LittleEndian, WriteBytesExt};
-use once_cell::sync::Lazy;
-use packet::{MassDataType, MassPacket};
+use packet::{CompressMode, MassDataType, MassPacket};
 use prost::Message;
 use serde::Serialize;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::{
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
 pub mod packet;
-
-#[derive(Clone)]
-struct ResumeState {
-    device_addr: String,
-    mass_id: Vec<u8>,
-    current_part: u16,
-}
-
-static RESUME_STATE: Lazy<Mutex<Option<ResumeState>>> = Lazy::new(|| Mutex::new(None));
+pub mod resume;
 
 pub async fn clear_resume_state() {
-    *RESUME_STATE.lock().await = None;
+    resume::clear();
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -35,51 +37,114 @@ pub struct SendMassCallbackData {
     pub actual_data_payload_len: usize,
 }
 
+#[derive(Debug, Clone)]
+pub struct SendMassOptions {
+    /// 是否等待所有分片的 ACK 后再返回
+    pub wait_for_acks: bool,
+    /// 所有分片发出后等待 ACK 的最长时间
+    pub ack_deadline: Duration,
+    /// 取消后在分片之间停止发送，并通知设备放弃本次传输
+    pub cancel: CancellationToken,
+}
+
+impl Default for SendMassOptions {
+    fn default() -> Self {
+        Self {
+            wait_for_acks: false,
+            ack_deadline: Duration::from_secs(60),
+            cancel: CancellationToken::new(),
+        }
+    }
+}
+
+impl SendMassOptions {
+    pub fn wait_for_acks() -> Self {
+        Self {
+            wait_for_acks: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MassTransferReport {
+    pub total_parts: u16,
+    /// 本次从第几个分片开始发送（断点续传时大于 1）
+    pub start_part: u16,
+    pub bytes_sent: usize,
+    pub duration_ms: u64,
+    /// 传输期间发生的重传次数
+    pub retries: u64,
+    /// 字节/秒
+    pub throughput: f64,
+    /// 截止时仍未收到 ACK 的分片（未等待 ACK 时为空）
+    pub unacked_parts: Vec<u16>,
+    pub compress_mode: CompressMode,
+    /// 压缩前的文件长度
+    pub original_length: usize,
+    pub waited_for_acks: bool,
+}
+
+impl MassTransferReport {
+    /// 等待了 ACK 但仍有分片未确认时返回错误
+    pub fn ensure_complete(self) -> Result<Self> {
+        if self.waited_for_acks && !self.unacked_parts.is_empty() {
+            bail!(
+                "Mass transfer incomplete: {} of {} parts were never acknowledged (first: {})",
+                self.unacked_parts.len(),
+                self.total_parts,
+                self.unacked_parts[0]
+            );
+        }
+        Ok(self)
+    }
+}
+
 pub async fn send_mass<F>(
     device: &Arc<MiWearDevice>,
     file_data: Vec<u8>,
     data_type: MassDataType,
+    options: SendMassOptions,
     progress_cb: F,
-) -> Result<()>
+) -> Result<MassTransferReport>
 where
     F: Fn(SendMassCallbackData) + Send + Sync,
 {
     let file_md5_for_prepare = crate::tools::calc_md5(&file_data);
+    let file_data_len = file_data.len();
+    let support_compress = crate::config::read(|c| c.mass_compression);
 
     let mut disconnect_rx = crate::miwear::subscribe_disconnect();
 
-    let device_addr = device.state.read().await.addr.clone();
-    let mut start_part: u16 = 1;
-    {
-        let mut guard = RESUME_STATE.lock().await;
-        match guard.as_mut() {
-            Some(state)
-                if state.mass_id == file_md5_for_prepare && state.device_addr == device_addr =>
-            {
-                start_part = state.current_part;
-            }
-            _ => {
-                *guard = Some(ResumeState {
-                    device_addr,
-                    mass_id: file_md5_for_prepare.clone(),
-                    current_part: 1,
-                });
-            }
-        }
-    }
+    let device_addr = device.addr.clone();
+
+    // 只有认证完成的设备才能发起 Mass 传输，返回前恢复为 Ready
+    let _transferring = crate::miwear::connection::TransferGuard::begin(&device_addr)?;
 
-    let prepare_ret = device
-        .request_proto(
+    let prepare_ret = cancellable(
+        &options.cancel,
+        device.request_proto(
             Channel::Pb,
             OpCode::Encrypted,
-            &build_mass_prepare_request(data_type, &file_md5_for_prepare, file_data.len())
-                .encode_to_vec(),
+            &build_mass_prepare_request(
+                data_type,
+                &file_md5_for_prepare,
+                file_data.len(),
+                support_compress,
+            )
+            .encode_to_vec(),
             pb::protocol::wear_packet::Type::Mass as u32,
             pb::protocol::mass::MassId::Prepare as u32,
             None,
-        )
-        .await
-        .context("Mass prepare request_proto call failed")?;
+        ),
+    )
+    .await
+    .context("Mass prepare request_proto call failed")?;
 
     match prepare_ret
         .payload
@@ -95,7 +160,10 @@ where
             };
 
             if prepare_resp.prepare_status != pb::protocol::PrepareStatus::Ready as i32 {
-                bail!("Mass data prepare was not READY. 请检查你的设备是否有充足的存储空间，然后再试。");
+                return Err(MiWearError::PrepareRejected {
+                    status: prepare_resp.prepare_status,
+                }
+                .into());
             }
 
             log::info!(
@@ -103,15 +171,38 @@ where
                 prepare_resp.expected_slice_length()
             );
 
+            // 设备不支持或选择了未知的压缩方式时按原始数据发送
+            let compress_mode = if support_compress {
+                let selected = prepare_resp.select_compress_mode();
+                CompressMode::from_u32(selected).unwrap_or_else(|| {
+                    log::warn!(
+                        "[MiWearMassSend] Device selected unknown compress mode {}, sending raw data",
+                        selected
+                    );
+                    CompressMode::None
+                })
+            } else {
+                CompressMode::None
+            };
+
+            let mass_packet = MassPacket::build_compressed(file_data, data_type, compress_mode)?;
+            let compress_mode = mass_packet.compress_mode;
+
             // (comp_data | type | md5 | len | data | crc32)
-            let mass_inner_payload_with_crc32 =
-                MassPacket::build(file_data, data_type)?.encode_with_crc32();
+            let mass_inner_payload_with_crc32 = mass_packet.encode_with_crc32();
             log::info!(
-                "[MiWearMassSend] Encoded MassInnerData with CRC32, total length: {}",
-                mass_inner_payload_with_crc32.len()
+                "[MiWearMassSend] Encoded MassInnerData with CRC32, total length: {}, compress mode: {:?} ({} -> {} bytes)",
+                mass_inner_payload_with_crc32.len(),
+                compress_mode,
+                file_data_len,
+                mass_packet.length
             );
 
-            let miwear_packet_body_max_len = prepare_resp.expected_slice_length() as usize;
+            // 分片同时受 prepare 回应与 session config 中设备声明的接收上限约束
+            let mut miwear_packet_body_max_len = prepare_resp.expected_slice_length() as usize;
+            if let Some(len) = device.state.read().await.max_tx_frame_len {
+                miwear_packet_body_max_len = miwear_packet_body_max_len.min(len);
+            }
             if miwear_packet_body_max_len == 0 {
                 bail!("Device reported expected_slice_length of 0, cannot proceed.");
             }
@@ -144,8 +235,24 @@ where
                 mass_fragment_max_len
             );
 
-            let _send_lock = device.send_lock.lock().await;
-            *(device.is_sending_mass.lock().await) = true;
+            // 只有编码结果（含压缩方式）与分片布局都与上次一致时才能续传
+            let start_part = resume::resume_point(
+                &device_addr,
+                &file_md5_for_prepare,
+                data_type as u32,
+                mass_inner_payload_with_crc32.len(),
+                miwear_packet_body_max_len,
+                total_parts,
+            );
+
+            let started = Instant::now();
+            let retries_before = device.cmd_pool.retry_stats().totals.retransmissions;
+            let mut bytes_sent = 0usize;
+            let (acked_tx, mut acked_rx) = mpsc::unbounded_channel::<u16>();
+
+            // 不再独占 send_lock，由 CommandPool 在 Mass 与其他通道之间公平调度，
+            // 以免传输期间 Pb 请求被阻塞
+            let _sending = SendingMassGuard::begin(&device.is_sending_mass);
 
             for i in (start_part - 1)..total_parts {
                 let current_part_num = i + 1; // 1-indexed
@@ -176,12 +283,29 @@ where
                     actual_data_payload_len: actual_data_payload.len(),
                 });
 
-                if disconnect_rx.try_recv().is_ok() {
-                    bail!("Device disconnected during mass send");
+                if crate::miwear::has_disconnected(&mut disconnect_rx, &device_addr) {
+                    return Err(
+                        MiWearError::transport("Device disconnected during mass send").into(),
+                    );
+                }
+
+                if options.cancel.is_cancelled() {
+                    log::info!(
+                        "[MiWearMassSend] Cancelled before part {}/{}",
+                        current_part_num,
+                        total_parts
+                    );
+                    return Err(on_cancelled(
+                        device,
+                        &device_addr,
+                        &file_md5_for_prepare,
+                        current_part_num - 1,
+                        total_parts,
+                    ));
                 }
 
                 let ack_rx = device
-                    .send_miwear_pkt_register_ack_unlocked(
+                    .send_miwear_pkt_register_ack(
                         Channel::Mass,
                         OpCode::Plain,
                         &actual_data_payload,
@@ -194,46 +318,167 @@ where
                         )
                     })?;
 
-                let total_parts_clone = total_parts;
+                bytes_sent += actual_data_payload.len();
+
+                let resume_addr = device_addr.clone();
+                let resume_id = file_md5_for_prepare.clone();
+                let acked_tx = acked_tx.clone();
                 tokio::spawn(async move {
                     let mut ack_disconnect_rx = crate::miwear::subscribe_disconnect();
                     tokio::select! {
-                        _ = ack_rx => {
-                            let mut guard = RESUME_STATE.lock().await;
-                            if let Some(state) = guard.as_mut() {
-                                state.current_part = current_part_num + 1;
-                                if state.current_part > total_parts_clone {
-                                    *guard = None;
-                                }
+                        res = ack_rx => {
+                            if res.is_err() {
+                                log::warn!("ACK waiter of part {} dropped", current_part_num);
+                                return;
                             }
+                            let _ = acked_tx.send(current_part_num);
+                            resume::mark_acked(&resume_addr, &resume_id, current_part_num);
                         }
-                        _ = ack_disconnect_rx.recv() => {
+                        _ = crate::miwear::wait_disconnect(&mut ack_disconnect_rx, &resume_addr) => {
                             log::warn!("Device disconnected before ACK of part {}", current_part_num);
                         }
                     }
                 });
             }
+            drop(acked_tx);
 
-            *(device.is_sending_mass.lock().await) = false;
-            log::info!(
-                "[MiWearMassSend] All mass data parts queued; returning without waiting for ACKs."
-            );
-            Ok(())
+            let mut unacked_parts = Vec::new();
+            if options.wait_for_acks {
+                let mut pending: BTreeSet<u16> = (start_part..=total_parts).collect();
+                let deadline = tokio::time::sleep(options.ack_deadline);
+                tokio::pin!(deadline);
+
+                while !pending.is_empty() {
+                    tokio::select! {
+                        part = acked_rx.recv() => match part {
+                            Some(part) => {
+                                pending.remove(&part);
+                            }
+                            // 所有 ACK 任务都已结束，剩下的分片不会再被确认
+                            None => break,
+                        },
+                        _ = &mut deadline => {
+                            log::warn!(
+                                "[MiWearMassSend] ACK deadline of {:?} exceeded, {} parts pending",
+                                options.ack_deadline,
+                                pending.len()
+                            );
+                            break;
+                        }
+                        _ = options.cancel.cancelled() => {
+                            log::info!(
+                                "[MiWearMassSend] Cancelled while waiting for ACKs, {} parts pending",
+                                pending.len()
+                            );
+                            return Err(on_cancelled(
+                                device,
+                                &device_addr,
+                                &file_md5_for_prepare,
+                                total_parts,
+                                total_parts,
+                            ));
+                        }
+                    }
+                }
+                unacked_parts = pending.into_iter().collect();
+                if unacked_parts.is_empty() {
+                    resume::finish(&device_addr, &file_md5_for_prepare);
+                }
+            }
+
+            let duration = started.elapsed();
+            let report = MassTransferReport {
+                total_parts,
+                start_part,
+                bytes_sent,
+                duration_ms: duration.as_millis() as u64,
+                retries: device
+                    .cmd_pool
+                    .retry_stats()
+                    .totals
+                    .retransmissions
+                    .saturating_sub(retries_before),
+                throughput: bytes_sent as f64 / duration.as_secs_f64().max(0.001),
+                unacked_parts,
+                compress_mode,
+                original_length: file_data_len,
+                waited_for_acks: options.wait_for_acks,
+            };
+
+            if options.wait_for_acks {
+                log::info!("[MiWearMassSend] Transfer finished: {:?}", report);
+            } else {
+                log::info!(
+                    "[MiWearMassSend] All mass data parts queued; returning without waiting for ACKs."
+                );
+            }
+            Ok(report)
         }
         None => bail!("Mass field was not present in the received protobuf."),
     }
 }
 
+/// 分片发送期间置位 `is_sending_mass`，任何路径退出时复位
+struct SendingMassGuard<'a>(&'a AtomicBool);
+
+impl<'a> SendingMassGuard<'a> {
+    fn begin(flag: &'a AtomicBool) -> Self {
+        flag.store(true, Ordering::Relaxed);
+        Self(flag)
+    }
+}
+
+impl Drop for SendingMassGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// 传输被取消后的收尾
+///
+/// 用户取消时删除续传记录；链路断开引起的取消保留记录，重连后从断点继续。
+/// 协议中没有已知的中止指令，设备无从得知传输已放弃，
+/// 因此返回 `MiWearError::MassAbandoned` 而不是 `Cancelled`
+fn on_cancelled(
+    device: &Arc<MiWearDevice>,
+    device_addr: &str,
+    mass_id: &[u8],
+    sent_parts: u16,
+    total_parts: u16,
+) -> anyhow::Error {
+    if device.operations.is_link_lost() {
+        log::info!(
+            "[MiWearMassSend] Link lost, keeping resume state of {}",
+            device_addr
+        );
+        return MiWearError::transport("Device disconnected during mass send").into();
+    }
+
+    resume::finish(device_addr, mass_id);
+    log::warn!(
+        "[MiWearMassSend] Cancelled after {}/{} parts, {} keeps a half-open mass session",
+        sent_parts,
+        total_parts,
+        device_addr
+    );
+    MiWearError::MassAbandoned {
+        sent_parts,
+        total_parts,
+    }
+    .into()
+}
+
 fn build_mass_prepare_request(
     data_type: MassDataType,
     file_md5: &Vec<u8>,
     file_length: usize,
+    support_compress: bool,
 ) -> pb::protocol::WearPacket {
     let mass_payload = pb::protocol::PrepareRequest {
         data_type: data_type as u32,
         data_id: file_md5.to_vec(),
         data_length: file_length as u32,
-        support_compress_mode: None,
+        support_compress_mode: support_compress.then_some(CompressMode::Deflate as u32),
     };
 
     let mass_pkt = pb::protocol::Mass {
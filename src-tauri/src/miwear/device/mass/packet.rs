@@ -1,5 +1,13 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use byteorder::{LittleEndian, WriteBytesExt};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::Serialize;
+use std::io::{Read, Write};
+
+use crate::miwear::error::MiWearError;
+
+/// 解压后数据的上限，防止设备发来的压缩数据膨胀耗尽内存
+pub const MAX_INFLATED_LEN: usize = 256 * 1024 * 1024;
 
 #[derive(Clone, Copy)]
 #[repr(u8)]
@@ -10,24 +18,74 @@ pub enum MassDataType {
     ThirdpartyApp = 64,
 }
 
+/// comp_data 字段取值，同时也是 PrepareRequest.support_compress_mode 的取值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[repr(u8)]
+pub enum CompressMode {
+    None = 0,
+    Deflate = 1,
+}
+
+impl CompressMode {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(CompressMode::None),
+            1 => Some(CompressMode::Deflate),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct MassPacket {
     pub data_type: MassDataType,
+    pub compress_mode: CompressMode,
     pub md5: Vec<u8>,                // 原始文件数据的MD5
-    pub length: u32,                 // 原始文件数据的长度
-    pub original_file_data: Vec<u8>, // 原始文件数据
+    pub length: u32,                 // 文件数据段的长度（压缩后则为压缩数据的长度）
+    pub original_file_data: Vec<u8>, // 文件数据段（可能已压缩）
 }
 
 impl MassPacket {
     pub fn build(original_file_data: Vec<u8>, data_type: MassDataType) -> Result<Self> {
         Ok(MassPacket {
             data_type,
+            compress_mode: CompressMode::None,
             md5: crate::tools::calc_md5(&original_file_data), // calc_md5 应返回 Vec<u8>
             length: original_file_data.len() as u32,
             original_file_data,
         })
     }
 
+    /// 按设备选定的压缩方式构建；压缩后没有变小时退回原始数据
+    pub fn build_compressed(
+        original_file_data: Vec<u8>,
+        data_type: MassDataType,
+        mode: CompressMode,
+    ) -> Result<Self> {
+        let compressed = match mode {
+            CompressMode::None => None,
+            CompressMode::Deflate => {
+                let mut encoder = DeflateEncoder::new(
+                    Vec::with_capacity(original_file_data.len() / 2),
+                    Compression::best(),
+                );
+                encoder.write_all(&original_file_data)?;
+                Some(encoder.finish()?)
+            }
+        };
+
+        match compressed {
+            Some(data) if data.len() < original_file_data.len() => Ok(MassPacket {
+                data_type,
+                compress_mode: mode,
+                md5: crate::tools::calc_md5(&original_file_data),
+                length: data.len() as u32,
+                original_file_data: data,
+            }),
+            _ => Self::build(original_file_data, data_type),
+        }
+    }
+
     /// 编码内部数据块并附加其CRC32。
     /// 输出格式: comp_data (1B) | data_type (1B) | md5 (16B) | length (4B LE) | original_file_data (...) | crc32_of_these_five_fields (4B LE)
     pub fn encode_with_crc32(&self) -> Vec<u8> {
@@ -35,8 +93,8 @@ impl MassPacket {
             1 + 1 + self.md5.len() + 4 + self.original_file_data.len() + 4, // 预估容量
         );
 
-        // 1. comp_data (固定为 0x00)
-        crc_payload_buf.push(0x00);
+        // 1. comp_data
+        crc_payload_buf.push(self.compress_mode as u8);
         // 2. data_type
         crc_payload_buf.push(self.data_type as u8);
         // 3. md5
@@ -63,3 +121,88 @@ impl MassPacket {
         crc_payload_buf
     }
 }
+
+/// 从 Mass 分片拼回的完整数据块
+#[derive(Debug, Clone)]
+pub struct ReceivedMass {
+    pub data_type: u8,
+    pub md5: Vec<u8>,
+    pub data: Vec<u8>,
+    pub compress_mode: u8,
+    pub crc_ok: bool,
+    /// 包尾给出的 CRC32
+    pub crc_expected: u32,
+    /// 按收到的数据计算出的 CRC32
+    pub crc_actual: u32,
+    pub md5_ok: bool,
+}
+
+impl ReceivedMass {
+    /// CRC 不一致时返回 `MiWearError::Crc`
+    pub fn ensure_crc(&self) -> Result<()> {
+        if !self.crc_ok {
+            return Err(MiWearError::Crc {
+                expected: self.crc_expected,
+                actual: self.crc_actual,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// 解析 encode_with_crc32 的输出，data 按 comp_data 解压
+///
+/// md5_ok 要求解压后数据的 MD5 与包头一致，且与 `expect_md5`（Prepare 中的 data_id）一致
+pub fn parse_mass_inner(inner: &[u8], expect_md5: Option<&[u8]>) -> Result<ReceivedMass> {
+    if inner.len() < 1 + 1 + 16 + 4 + 4 {
+        bail!("Mass payload too short: {} bytes", inner.len());
+    }
+
+    let data_type = inner[1];
+    let md5 = inner[2..18].to_vec();
+    let length = u32::from_le_bytes(inner[18..22].try_into().unwrap()) as usize;
+    // length 来自设备，32 位平台上可能溢出
+    if length.checked_add(22 + 4) != Some(inner.len()) {
+        bail!(
+            "Mass payload length mismatch: header says {}, got {}",
+            length,
+            inner.len() - 26
+        );
+    }
+
+    let compress_mode = inner[0];
+    let data = match CompressMode::from_u32(compress_mode as u32) {
+        Some(CompressMode::None) => inner[22..22 + length].to_vec(),
+        Some(CompressMode::Deflate) => {
+            let mut data = Vec::new();
+            DeflateDecoder::new(&inner[22..22 + length])
+                .take(MAX_INFLATED_LEN as u64 + 1)
+                .read_to_end(&mut data)
+                .context("Failed to inflate mass payload")?;
+            if data.len() > MAX_INFLATED_LEN {
+                bail!("Inflated mass payload exceeds {} bytes", MAX_INFLATED_LEN);
+            }
+            data
+        }
+        None => bail!("Unknown compress mode {}", compress_mode),
+    };
+    let crc_given = u32::from_le_bytes(inner[22 + length..].try_into().unwrap());
+    let crc_calc = u32::from_be_bytes(
+        crate::tools::calc_crc32_bytes(&inner[..22 + length])
+            .try_into()
+            .unwrap(),
+    );
+
+    Ok(ReceivedMass {
+        data_type,
+        md5_ok: expect_md5.map_or(true, |expect| expect == md5)
+            && crate::tools::calc_md5(&data) == md5,
+        md5,
+        data,
+        compress_mode,
+        crc_ok: crc_given == crc_calc,
+        crc_expected: crc_given,
+        crc_actual: crc_calc,
+    })
+}
@@ -0,0 +1,371 @@
+use anyhow::{Context, Result};
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Manager};
+
+/// 超过该时长未更新的续传记录会被丢弃
+const ENTRY_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// 每台设备最多保留的续传记录数
+const MAX_ENTRIES_PER_DEVICE: usize = 8;
+
+/// ACK 推进进度时的最小落盘间隔
+const PERSIST_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 一次未完成的 Mass 传输
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumeEntry {
+    pub device_addr: String,
+    /// 原始文件 md5（hex）
+    pub mass_id: String,
+    pub data_type: u32,
+    /// 编码后（可能已压缩）的 Mass 内部数据长度
+    pub data_length: usize,
+    pub slice_length: usize,
+    pub total_parts: u16,
+    /// 下一个需要发送的分片（1 起始），之前的分片均已收到 ACK
+    pub next_part: u16,
+    /// next_part 之后已乱序收到 ACK 的分片，补齐空缺后并入 next_part
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub acked: BTreeSet<u16>,
+    /// unix 时间戳（秒）
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ResumeJournal {
+    entries: Vec<ResumeEntry>,
+}
+
+struct JournalState {
+    journal: ResumeJournal,
+    last_persist: Option<Instant>,
+    /// 每次生成快照递增，用于丢弃乱序完成的旧写入
+    generation: u64,
+}
+
+/// 已序列化、等待写盘的日志
+struct Snapshot {
+    path: PathBuf,
+    generation: u64,
+    data: String,
+}
+
+static JOURNAL: Lazy<Mutex<JournalState>> = Lazy::new(|| {
+    Mutex::new(JournalState {
+        journal: ResumeJournal::default(),
+        last_persist: None,
+        generation: 0,
+    })
+});
+static JOURNAL_PATH: OnceCell<PathBuf> = OnceCell::new();
+/// 最近一次写盘的快照编号，写盘串行进行
+static LAST_WRITTEN: Mutex<u64> = parking_lot::const_mutex(0);
+
+pub async fn init(app: &AppHandle) -> Result<()> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .context("app_config_dir unavailable; did you call after app was ready?")?;
+    let path = dir.join("mass_resume.json");
+    JOURNAL_PATH.set(path.clone()).ok();
+
+    if path.exists() {
+        let json = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("read resume journal: {}", path.display()))?;
+        let journal: ResumeJournal = match serde_json::from_str(&json) {
+            Ok(journal) => journal,
+            Err(e) => {
+                log::warn!(
+                    "[MassResume] Resume journal is corrupted, discarding: {}",
+                    e
+                );
+                ResumeJournal::default()
+            }
+        };
+        let snapshot = {
+            let mut state = JOURNAL.lock();
+            state.journal = journal;
+            expire(&mut state.journal);
+            snapshot(&mut state)
+        };
+        save(snapshot);
+    }
+
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn expire(journal: &mut ResumeJournal) {
+    let now = now();
+    journal
+        .entries
+        .retain(|e| now.saturating_sub(e.updated_at) < ENTRY_TTL.as_secs());
+}
+
+/// 在持有锁时序列化日志，写盘交给 `save` 在锁外完成
+fn snapshot(state: &mut JournalState) -> Option<Snapshot> {
+    state.last_persist = Some(Instant::now());
+
+    // 没有调用 init（例如在模拟器中运行）时只保存在内存里
+    let path = JOURNAL_PATH.get()?;
+
+    match serde_json::to_string_pretty(&state.journal) {
+        Ok(data) => {
+            state.generation += 1;
+            Some(Snapshot {
+                path: path.clone(),
+                generation: state.generation,
+                data,
+            })
+        }
+        Err(e) => {
+            log::error!("failed to serialize resume journal: {e}");
+            None
+        }
+    }
+}
+
+/// 在 tokio 运行时中转到阻塞线程池写盘，避免阻塞传输任务
+fn save(snapshot: Option<Snapshot>) {
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn_blocking(move || snapshot.write());
+        }
+        Err(_) => snapshot.write(),
+    }
+}
+
+impl Snapshot {
+    fn write(self) {
+        let mut last = LAST_WRITTEN.lock();
+        if self.generation <= *last {
+            return;
+        }
+
+        let path = &self.path;
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| {
+                std::fs::write(path, &self.data)
+                    .with_context(|| format!("write resume journal: {}", path.display()))
+            });
+
+        match result {
+            Ok(()) => *last = self.generation,
+            Err(e) => log::error!("failed to save resume journal: {e:#}"),
+        }
+    }
+}
+
+/// 根据设备的 PrepareResponse 计算出的分片布局查询续传起点
+///
+/// 记录存在且分片布局一致时返回记录中的 next_part，否则重新登记并从第 1 片开始
+pub fn resume_point(
+    device_addr: &str,
+    mass_id: &[u8],
+    data_type: u32,
+    data_length: usize,
+    slice_length: usize,
+    total_parts: u16,
+) -> u16 {
+    let mass_id = crate::tools::to_hex_string(mass_id);
+    let mut state = JOURNAL.lock();
+    expire(&mut state.journal);
+
+    let existing = state
+        .journal
+        .entries
+        .iter_mut()
+        .find(|e| e.device_addr == device_addr && e.mass_id == mass_id);
+
+    let next_part = match existing {
+        Some(entry)
+            if entry.data_type == data_type
+                && entry.data_length == data_length
+                && entry.slice_length == slice_length
+                && entry.total_parts == total_parts
+                && entry.next_part <= total_parts =>
+        {
+            entry.updated_at = now();
+            log::info!(
+                "[MassResume] Resuming {} on {} from part {}/{}",
+                mass_id,
+                device_addr,
+                entry.next_part,
+                total_parts
+            );
+            entry.next_part
+        }
+        Some(entry) => {
+            log::info!(
+                "[MassResume] Slice layout of {} changed (slice {} -> {}, parts {} -> {}), restarting",
+                mass_id,
+                entry.slice_length,
+                slice_length,
+                entry.total_parts,
+                total_parts
+            );
+            entry.slice_length = slice_length;
+            entry.total_parts = total_parts;
+            entry.data_type = data_type;
+            entry.data_length = data_length;
+            entry.next_part = 1;
+            entry.acked.clear();
+            entry.updated_at = now();
+            1
+        }
+        None => {
+            state.journal.entries.push(ResumeEntry {
+                device_addr: device_addr.to_string(),
+                mass_id,
+                data_type,
+                data_length,
+                slice_length,
+                total_parts,
+                next_part: 1,
+                acked: BTreeSet::new(),
+                updated_at: now(),
+            });
+
+            // 同一设备的记录过多时丢弃最旧的
+            let mut of_device: Vec<(u64, String)> = state
+                .journal
+                .entries
+                .iter()
+                .filter(|e| e.device_addr == device_addr)
+                .map(|e| (e.updated_at, e.mass_id.clone()))
+                .collect();
+            if of_device.len() > MAX_ENTRIES_PER_DEVICE {
+                of_device.sort();
+                let drop_count = of_device.len() - MAX_ENTRIES_PER_DEVICE;
+                let stale: Vec<String> = of_device
+                    .into_iter()
+                    .take(drop_count)
+                    .map(|(_, id)| id)
+                    .collect();
+                state
+                    .journal
+                    .entries
+                    .retain(|e| e.device_addr != device_addr || !stale.contains(&e.mass_id));
+            }
+            1
+        }
+    };
+
+    let snapshot = snapshot(&mut state);
+    drop(state);
+    save(snapshot);
+    next_part
+}
+
+/// 记录某个分片收到了 ACK
+///
+/// 分片可能乱序确认（重传、窗口内并发），先记入 acked，
+/// 续传起点只沿连续确认的前缀推进
+pub fn mark_acked(device_addr: &str, mass_id: &[u8], part: u16) {
+    let mass_id = crate::tools::to_hex_string(mass_id);
+    let mut state = JOURNAL.lock();
+
+    let Some(idx) = state
+        .journal
+        .entries
+        .iter()
+        .position(|e| e.device_addr == device_addr && e.mass_id == mass_id)
+    else {
+        return;
+    };
+
+    let entry = &mut state.journal.entries[idx];
+    if part < entry.next_part || part > entry.total_parts || !entry.acked.insert(part) {
+        return;
+    }
+    let before = entry.next_part;
+    while entry.acked.remove(&entry.next_part) {
+        entry.next_part += 1;
+    }
+    entry.updated_at = now();
+
+    let snapshot = if entry.next_part > entry.total_parts {
+        state.journal.entries.remove(idx);
+        snapshot(&mut state)
+    } else {
+        let due = state
+            .last_persist
+            .map(|t| t.elapsed() >= PERSIST_INTERVAL)
+            .unwrap_or(true);
+        // 起点没有推进时没必要落盘
+        if due && state.journal.entries[idx].next_part != before {
+            snapshot(&mut state)
+        } else {
+            None
+        }
+    };
+    drop(state);
+    save(snapshot);
+}
+
+/// 传输完成（或确认无法续传）后删除记录
+pub fn finish(device_addr: &str, mass_id: &[u8]) {
+    let mass_id = crate::tools::to_hex_string(mass_id);
+    let snapshot = {
+        let mut state = JOURNAL.lock();
+        let before = state.journal.entries.len();
+        state
+            .journal
+            .entries
+            .retain(|e| !(e.device_addr == device_addr && e.mass_id == mass_id));
+        if state.journal.entries.len() == before {
+            return;
+        }
+        snapshot(&mut state)
+    };
+    save(snapshot);
+}
+
+/// 删除某台设备的全部续传记录（例如移除设备时）
+pub fn clear_device(device_addr: &str) {
+    let snapshot = {
+        let mut state = JOURNAL.lock();
+        state
+            .journal
+            .entries
+            .retain(|e| e.device_addr != device_addr);
+        snapshot(&mut state)
+    };
+    save(snapshot);
+}
+
+pub fn clear() {
+    let snapshot = {
+        let mut state = JOURNAL.lock();
+        state.journal.entries.clear();
+        snapshot(&mut state)
+    };
+    save(snapshot);
+}
+
+pub fn entries() -> Vec<ResumeEntry> {
+    let mut state = JOURNAL.lock();
+    expire(&mut state.journal);
+    state.journal.entries.clone()
+}
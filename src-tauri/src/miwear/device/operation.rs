@@ -0,0 +1,103 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::Result;
+use dashmap::DashMap;
+use tokio_util::sync::CancellationToken;
+
+use crate::miwear::error::MiWearError;
+
+struct RunningOperation {
+    name: String,
+    token: CancellationToken,
+}
+
+/// 设备上正在进行的可取消操作（安装表盘 / 快应用 / 固件等）
+#[derive(Default)]
+pub struct OperationRegistry {
+    next_id: AtomicU64,
+    running: Arc<DashMap<u64, RunningOperation>>,
+    /// 链路已断开，此后的取消都不是用户发起的
+    link_lost: AtomicBool,
+}
+
+/// 操作的生命周期句柄，drop 时自动从表中移除
+pub struct OperationHandle {
+    id: u64,
+    token: CancellationToken,
+    running: Arc<DashMap<u64, RunningOperation>>,
+}
+
+impl OperationHandle {
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for OperationHandle {
+    fn drop(&mut self) {
+        self.running.remove(&self.id);
+    }
+}
+
+impl OperationRegistry {
+    pub fn begin(&self, name: &str) -> OperationHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let token = CancellationToken::new();
+        self.running.insert(
+            id,
+            RunningOperation {
+                name: name.to_string(),
+                token: token.clone(),
+            },
+        );
+
+        OperationHandle {
+            id,
+            token,
+            running: self.running.clone(),
+        }
+    }
+
+    /// 取消全部正在进行的操作，返回被取消的操作数
+    pub fn cancel_all(&self) -> usize {
+        let mut cancelled = 0;
+        for op in self.running.iter() {
+            if !op.token.is_cancelled() {
+                log::info!("[MiWearDevice] Cancelling operation {}", op.name);
+                op.token.cancel();
+                cancelled += 1;
+            }
+        }
+        cancelled
+    }
+
+    /// 链路断开时取消全部操作
+    ///
+    /// 与用户取消不同，操作应保留可续传的状态，由 `is_link_lost` 区分
+    pub fn abandon_all(&self) -> usize {
+        self.link_lost.store(true, Ordering::Release);
+        self.cancel_all()
+    }
+
+    pub fn is_link_lost(&self) -> bool {
+        self.link_lost.load(Ordering::Acquire)
+    }
+}
+
+/// 等待 fut 完成，期间 token 被取消时返回 `MiWearError::Cancelled`
+pub async fn cancellable<T, Fut>(token: &CancellationToken, fut: Fut) -> Result<T>
+where
+    Fut: Future<Output = Result<T>>,
+{
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(MiWearError::Cancelled.into()),
+        ret = fut => ret,
+    }
+}
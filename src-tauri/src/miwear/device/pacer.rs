@@ -0,0 +1,158 @@
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::miwear::network_stack::meter::BandwidthMeter;
+
+/// 分片间隔的上下限（微秒）
+const MIN_DELAY_US: u32 = 0;
+const MAX_DELAY_US: u32 = 50_000;
+
+/// 每收到多少个 RTT 样本评估一次吞吐量
+const THROUGHPUT_SAMPLE_INTERVAL: u64 = 16;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PacerSnapshot {
+    pub adaptive: bool,
+    pub delay_us: u32,
+    pub srtt_ms: Option<f64>,
+    pub min_rtt_ms: Option<f64>,
+    pub write_speed: f64,
+    pub best_throughput: f64,
+    pub best_delay_us: u32,
+}
+
+#[derive(Default)]
+struct PacerState {
+    srtt_us: Option<f64>,
+    min_rtt_us: Option<f64>,
+    samples: u64,
+    best_throughput: f64,
+    best_delay_us: Option<u32>,
+}
+
+/// 自适应分片节奏控制
+///
+/// 用 ACK 往返时间判断链路是否排队：RTT 明显高于平滑 RTT 时拉长分片间隔，
+/// 否则逐步缩短；发生重传时直接翻倍。同时用 BandwidthMeter 统计写入吞吐，
+/// 记录吞吐最高时的间隔，断开连接时写回 paired_devices 供下次连接使用
+pub struct FragmentPacer {
+    adaptive: bool,
+    delay_us: AtomicU32,
+    meter: BandwidthMeter,
+    state: Mutex<PacerState>,
+}
+
+impl FragmentPacer {
+    /// `initial_delay_us` 优先使用该设备上次学习到的值
+    pub fn new(adaptive: bool, initial_delay_us: u32) -> Self {
+        Self {
+            adaptive,
+            delay_us: AtomicU32::new(initial_delay_us.clamp(MIN_DELAY_US, MAX_DELAY_US)),
+            meter: BandwidthMeter::new(Duration::from_secs(5)),
+            state: Mutex::new(PacerState::default()),
+        }
+    }
+
+    pub fn is_adaptive(&self) -> bool {
+        self.adaptive
+    }
+
+    /// 下一个分片发送前需要等待的时间
+    pub fn delay(&self) -> Duration {
+        if self.adaptive {
+            Duration::from_micros(self.delay_us.load(Ordering::Relaxed).into())
+        } else {
+            Duration::from_millis(crate::config::read(|c| c.fragments_send_delay).into())
+        }
+    }
+
+    pub fn on_sent(&self, bytes: usize) {
+        self.meter.add_written(bytes);
+    }
+
+    /// 收到一个未经重传的帧的 ACK
+    pub fn on_ack(&self, rtt: Duration) {
+        if !self.adaptive {
+            return;
+        }
+
+        let rtt_us = rtt.as_micros() as f64;
+        let mut state = self.state.lock();
+
+        // 与平滑 RTT 比较而不是最小 RTT：小包和 Mass 分片的 RTT 相差很大
+        let queued = state.srtt_us.is_some_and(|srtt| rtt_us > srtt * 1.5);
+        state.srtt_us = Some(match state.srtt_us {
+            Some(srtt) => srtt * 0.875 + rtt_us * 0.125,
+            None => rtt_us,
+        });
+        state.min_rtt_us = Some(state.min_rtt_us.map_or(rtt_us, |m| m.min(rtt_us)));
+
+        let cur = self.delay_us.load(Ordering::Relaxed);
+        let next = if queued {
+            // 设备侧开始排队，放慢
+            (cur + cur / 4).max(cur + 250)
+        } else {
+            cur - cur / 16
+        };
+        self.delay_us
+            .store(next.clamp(MIN_DELAY_US, MAX_DELAY_US), Ordering::Relaxed);
+
+        state.samples += 1;
+        if state.samples % THROUGHPUT_SAMPLE_INTERVAL == 0 {
+            let throughput = self.meter.write_speed();
+            if throughput > state.best_throughput {
+                state.best_throughput = throughput;
+                state.best_delay_us = Some(cur);
+            }
+        }
+    }
+
+    /// 发生重传，视为拥塞
+    pub fn on_loss(&self) {
+        if !self.adaptive {
+            return;
+        }
+        let cur = self.delay_us.load(Ordering::Relaxed);
+        let next = cur.saturating_mul(2).clamp(1_000, MAX_DELAY_US);
+        self.delay_us.store(next, Ordering::Relaxed);
+        log::info!(
+            "[FragmentPacer] Loss detected, fragment delay {}us -> {}us",
+            cur,
+            next
+        );
+    }
+
+    /// 需要持久化的间隔：有吞吐样本时取吞吐最高时的值，否则取当前值
+    pub fn learned_delay_us(&self) -> Option<u32> {
+        if !self.adaptive {
+            return None;
+        }
+        let state = self.state.lock();
+        if state.samples == 0 {
+            return None;
+        }
+        Some(
+            state
+                .best_delay_us
+                .unwrap_or_else(|| self.delay_us.load(Ordering::Relaxed)),
+        )
+    }
+
+    pub fn snapshot(&self) -> PacerSnapshot {
+        let state = self.state.lock();
+        PacerSnapshot {
+            adaptive: self.adaptive,
+            delay_us: self.delay().as_micros() as u32,
+            srtt_ms: state.srtt_us.map(|v| v / 1000.0),
+            min_rtt_ms: state.min_rtt_us.map(|v| v / 1000.0),
+            write_speed: self.meter.write_speed(),
+            best_throughput: state.best_throughput,
+            best_delay_us: state.best_delay_us.unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::SecurityKeys;
+
+/// 上次完整认证得到的会话密钥，保存在 secrets 中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedSession {
+    keys: SecurityKeys,
+    /// 完整认证时使用的 authkey，authkey 变化后缓存失效
+    authkey: String,
+    /// unix 时间戳（秒）
+    established_at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn secret_name(addr: &str) -> String {
+    crate::secrets::device_key(addr, "session")
+}
+
+/// 完整认证成功后记录会话密钥
+pub fn save(addr: &str, authkey: &str, keys: &SecurityKeys) {
+    let session = CachedSession {
+        keys: keys.clone(),
+        authkey: authkey.to_string(),
+        established_at: now(),
+    };
+    let ret = serde_json::to_string(&session)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| crate::secrets::set(&secret_name(addr), json));
+    if let Err(e) = ret {
+        log::warn!(
+            "[MiWearSession] Failed to cache session for {}: {:#}",
+            addr,
+            e
+        );
+    }
+}
+
+/// 取出仍在有效期内、且与当前 authkey 对应的会话密钥
+pub fn load(addr: &str, authkey: &str) -> Option<SecurityKeys> {
+    let json = crate::secrets::get(&secret_name(addr))?;
+    let session: CachedSession = match serde_json::from_str(&json) {
+        Ok(session) => session,
+        Err(e) => {
+            log::warn!(
+                "[MiWearSession] Cached session for {} is corrupted: {}",
+                addr,
+                e
+            );
+            clear(addr);
+            return None;
+        }
+    };
+
+    let ttl = crate::config::read(|c| c.session_cache_ttl_secs);
+    if session.authkey != authkey || now().saturating_sub(session.established_at) > ttl {
+        clear(addr);
+        return None;
+    }
+
+    Some(session.keys)
+}
+
+pub fn clear(addr: &str) {
+    if crate::secrets::get(&secret_name(addr)).is_none() {
+        return;
+    }
+    if let Err(e) = crate::secrets::remove(&secret_name(addr)) {
+        log::warn!(
+            "[MiWearSession] Failed to clear cached session for {}: {:#}",
+            addr,
+            e
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use crate::miwear::MiWearDevice;
 use crate::{
-    miwear::packet::{Channel, OpCode},
+    miwear::{
+        command_pool::CommandPriority,
+        packet::{Channel, OpCode},
+    },
     pb::{self},
 };
 use anyhow::{bail, Result};
@@ -154,6 +157,27 @@ pub async fn system_get_device_info(
     bail!("Packet doesn't exsist!");
 }
 
+/// 用较短的超时请求一次设备状态，用于确认当前会话密钥仍被设备接受
+pub async fn system_probe(device: Arc<MiWearDevice>, timeout: std::time::Duration) -> Result<()> {
+    let ret = device
+        // 探测超时很短，不能排在大文件或隧道流量之后
+        .request_proto_with_priority(
+            Channel::Pb,
+            OpCode::Encrypted,
+            &build_system_get_device_status().encode_to_vec(),
+            pb::protocol::wear_packet::Type::System as u32,
+            pb::protocol::system::SystemId::GetDeviceStatus as u32,
+            Some(timeout),
+            CommandPriority::High,
+        )
+        .await?;
+
+    match ret.__OPENSOURCE_DELETED__ {
+        Some(pb::protocol::wear_packet::Payload::System(_)) => Ok(()),
+        _ => bail!("Unexpected response to device status probe"),
+    }
+}
+
 fn build_system_get_device_status() -> pb::protocol::WearPacket {
 
     let pkt = pb::protocol::WearPacket {
@@ -1,14 +1,19 @@
 use crate::{
-    miwear::packet::{Channel, OpCode},
+    miwear::{
+        error::MiWearError,
+        packet::{Channel, OpCode},
+    },
     pb::{self},
 };
 use anyhow::{bail, Result};
 use prost::Message;
 use serde::{Deserialize, Serialize};
 use std::{str::FromStr, sync::Arc, time::Duration};
+use tokio_util::sync::CancellationToken;
 
 use super::{
-    mass::{packet::MassDataType, SendMassCallbackData},
+    mass::{packet::MassDataType, SendMassCallbackData, SendMassOptions},
+    operation::cancellable,
     MiWearDevice,
 };
 
@@ -33,6 +38,7 @@ pub async fn install_app<F>(
     file_path: &String,
     package_name: &String,
     version_code: u32,
+    cancel: CancellationToken,
     progress_cb: F,
 ) -> Result<()>
 where
@@ -40,8 +46,24 @@ where
 {
     let file_data = crate::fs::read_file_cross_platform(file_path).await?;
 
-    let thirdparty_app_install_ret = device
-        .request_proto(
+    install_app_data(device, file_data, package_name, version_code, cancel, progress_cb).await
+}
+
+/// 安装已读入内存的快应用数据（不经过文件系统）
+pub async fn install_app_data<F>(
+    device: Arc<MiWearDevice>,
+    file_data: Vec<u8>,
+    package_name: &String,
+    version_code: u32,
+    cancel: CancellationToken,
+    progress_cb: F,
+) -> Result<()>
+where
+    F: Fn(SendMassCallbackData) + Send + Sync,
+{
+    let thirdparty_app_install_ret = cancellable(
+        &cancel,
+        device.request_proto(
             Channel::Pb,
             OpCode::Encrypted,
             &build_thirdparty_app_install_request(package_name, version_code, file_data.len())
@@ -49,8 +71,9 @@ where
             pb::protocol::wear_packet::Type::ThirdpartyApp as u32,
             pb::protocol::thirdparty_app::ThirdpartyAppId::PrepareInstallApp as u32,
             None,
-        )
-        .await?;
+        ),
+    )
+    .await?;
 
     match thirdparty_app_install_ret.__OPENSOURCE_DELETED__.unwrap() {
         pb::protocol::wear_packet::Payload::ThirdpartyApp(thirdparty_app) => {
@@ -62,31 +85,32 @@ where
                             &device,
                             file_data,
                             MassDataType::ThirdpartyApp,
+                            SendMassOptions::wait_for_acks().with_cancel(cancel.clone()),
                             progress_cb,
                         )
-                        .await?;
+                        .await?
+                        .ensure_complete()?;
 
-                        device
-                            .wait_proto(
+                        cancellable(
+                            &cancel,
+                            device.wait_proto(
                                 pb::protocol::wear_packet::Type::ThirdpartyApp as u32,
                                 pb::protocol::thirdparty_app::ThirdpartyAppId::ReportInstallResult
                                     as u32,
                                 Some(Duration::from_secs(30)),
-                            )
-                            .await?;
+                            ),
+                        )
+                        .await?;
                     } else {
-                        #[cfg(debug_assertions)] {
-                            bail!(
-                                "Prepare not READY! Full resp: {}",
-                                serde_json::to_string(&response).unwrap()
-                            );
-                        }
-                        #[cfg(not(debug_assertions))] {
-                            bail!(
-                                "Prepare not READY! Error Info={}",
-                                super::error::get_prepare_error_info(response.__OPENSOURCE_DELETED__ as i32)
-                            );
+                        #[cfg(debug_assertions)]
+                        log::error!(
+                            "[MiWearDevice] Prepare not READY! Full resp: {}",
+                            serde_json::to_string(&response).unwrap()
+                        );
+                        return Err(MiWearError::PrepareRejected {
+                            status: response.__OPENSOURCE_DELETED__ as i32,
                         }
+                        .into());
                     }
                 }
                 _ => {
@@ -1,14 +1,19 @@
 use crate::{
-    miwear::packet::{Channel, OpCode},
+    miwear::{
+        error::MiWearError,
+        packet::{Channel, OpCode},
+    },
     pb::{self},
 };
 use anyhow::{bail, Result};
 use prost::Message;
 use std::{sync::Arc, time::Duration};
 use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 
 use super::{
-    mass::{packet::MassDataType, SendMassCallbackData},
+    mass::{packet::MassDataType, SendMassCallbackData, SendMassOptions},
+    operation::cancellable,
     MiWearDevice,
 };
 
@@ -31,6 +36,7 @@ pub async fn install_watchface<F>(
     file_path: &String,
     new_watchface_id: Option<&Vec<u8>>,
     id: &String,
+    cancel: CancellationToken,
     progress_cb: F,
 ) -> Result<()>
 where
@@ -44,16 +50,32 @@ where
         }
     }
 
-    let watchface_install_ret = device
-        .request_proto(
+    install_watchface_data(device, file_data, id, cancel, progress_cb).await
+}
+
+/// 安装已读入内存的表盘数据（不经过文件系统）
+pub async fn install_watchface_data<F>(
+    device: Arc<MiWearDevice>,
+    file_data: Vec<u8>,
+    id: &String,
+    cancel: CancellationToken,
+    progress_cb: F,
+) -> Result<()>
+where
+    F: Fn(SendMassCallbackData) + Send + Sync,
+{
+    let watchface_install_ret = cancellable(
+        &cancel,
+        device.request_proto(
             Channel::Pb,
             OpCode::Encrypted,
             &build_watchface_install_request(id, file_data.len()).encode_to_vec(),
             pb::protocol::wear_packet::Type::WatchFace as u32,
             pb::protocol::watch_face::WatchFaceId::PrepareInstallWatchFace as u32,
             None,
-        )
-        .await?;
+        ),
+    )
+    .await?;
 
     match watchface_install_ret.__OPENSOURCE_DELETED__.unwrap() {
         pb::protocol::wear_packet::Payload::WatchFace(watch_face) => match watch_face.__OPENSOURCE_DELETED__.unwrap() {
@@ -63,30 +85,31 @@ where
                         &device,
                         file_data,
                         MassDataType::WATCHFACE,
+                        SendMassOptions::wait_for_acks().with_cancel(cancel.clone()),
                         progress_cb,
                     )
-                    .await?;
+                    .await?
+                    .ensure_complete()?;
 
-                    device
-                        .wait_proto(
+                    cancellable(
+                        &cancel,
+                        device.wait_proto(
                             pb::protocol::wear_packet::Type::WatchFace as u32,
                             pb::protocol::watch_face::WatchFaceId::ReportInstallResult as u32,
                             Some(Duration::from_secs(10)),
-                        )
-                        .await?;
+                        ),
+                    )
+                    .await?;
                 } else {
-                    #[cfg(debug_assertions)] {
-                        bail!(
-                            "Prepare not READY! Full resp: {}",
-                            serde_json::to_string(&prepare_status).unwrap()
-                        );
-                    }
-                    #[cfg(not(debug_assertions))] {
-                        bail!(
-                            "Prepare not READY! Error Info={}",
-                            super::error::get_prepare_error_info(prepare_status)
-                        );
+                    #[cfg(debug_assertions)]
+                    log::error!(
+                        "[MiWearDevice] Prepare not READY! Full resp: {}",
+                        serde_json::to_string(&prepare_status).unwrap()
+                    );
+                    return Err(MiWearError::PrepareRejected {
+                        status: prepare_status,
                     }
+                    .into());
                 }
             }
             _ => {
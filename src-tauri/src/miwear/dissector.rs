@@ -0,0 +1,648 @@
+//! 离线解析抓到的 MiWear 流量
+//!
+//! 输入可以是十六进制文本、pcap / pcapng（包括 [`super::capture`] 写出的文件）
+//! 或 capture / replay 写出的 JSON Lines。按给定密钥解密 `OpCode::Encrypted` 负载，
+//! 把 `Channel::Pb` 解成 `WearPacket`，把 `Channel::Mass` 分片拼回原始文件并校验 CRC32 / MD5，
+//! 最后输出可读的时间线。
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use pcap_file::{
+    pcap::PcapReader,
+    pcapng::{blocks::enhanced_packet::EnhancedPacketOption, Block, PcapNgReader},
+    DataLink,
+};
+use prost::Message;
+use serde::Serialize;
+
+use super::{
+    capture::Direction,
+    device::{mass::packet::parse_mass_inner, SecurityKeys},
+    packet::{Channel, DecoderStats, FrameReassembler, MiWearPacket, OpCode, PktType, HELLO_MAGIC},
+};
+use crate::{
+    pb,
+    tools::{hex_stream_to_bytes, to_hex_string},
+};
+
+const PCAPNG_MAGIC: [u8; 4] = [0x0A, 0x0D, 0x0D, 0x0A];
+const PCAP_MAGICS: [[u8; 4]; 4] = [
+    [0xD4, 0xC3, 0xB2, 0xA1],
+    [0xA1, 0xB2, 0xC3, 0xD4],
+    [0x4D, 0x3C, 0xB2, 0xA1],
+    [0xA1, 0xB2, 0x3C, 0x4D],
+];
+
+/// 解密用的会话密钥（App 视角：enc 用于 App → 设备，dec 用于设备 → App）
+#[derive(Debug, Clone, Default)]
+pub struct DissectKeys {
+    pub enc_key: Option<[u8; 16]>,
+    pub dec_key: Option<[u8; 16]>,
+}
+
+impl DissectKeys {
+    pub fn from_hex(enc_key: Option<&str>, dec_key: Option<&str>) -> Result<Self> {
+        let parse = |hex: Option<&str>| -> Result<Option<[u8; 16]>> {
+            hex.map(|hex| -> Result<[u8; 16]> {
+                hex_stream_to_bytes(hex.trim())
+                    .map_err(anyhow::Error::msg)?
+                    .try_into()
+                    .map_err(|_| anyhow::anyhow!("Session key must be 16 bytes"))
+            })
+            .transpose()
+        };
+        Ok(Self {
+            enc_key: parse(enc_key)?,
+            dec_key: parse(dec_key)?,
+        })
+    }
+
+    pub fn from_security_keys(keys: &SecurityKeys) -> Self {
+        Self {
+            enc_key: crate::tools::vec_to_array_16_opt(&keys.enc_key),
+            dec_key: crate::tools::vec_to_array_16_opt(&keys.dec_key),
+        }
+    }
+
+    /// 按方向排好优先级的候选密钥，方向未知时两把都试
+    fn candidates(&self, dir: Option<Direction>) -> Vec<[u8; 16]> {
+        let ordered = match dir {
+            Some(Direction::Tx) => [self.enc_key, None],
+            Some(Direction::Rx) => [self.dec_key, None],
+            None => [self.enc_key, self.dec_key],
+        };
+        ordered.into_iter().flatten().collect()
+    }
+}
+
+/// 链路上的一段原始数据
+#[derive(Debug, Clone)]
+pub struct RawRecord {
+    /// 相对抓包开始或 unix 纪元的时间
+    pub ts: Option<Duration>,
+    pub dir: Option<Direction>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FrameDetail {
+    Hello {
+        raw: String,
+    },
+    Ack,
+    SessionConfig {
+        body: String,
+    },
+    Protobuf {
+        packet: serde_json::Value,
+    },
+    MassPart {
+        total: u16,
+        current: u16,
+        len: usize,
+    },
+    Data {
+        payload: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DissectedFrame {
+    pub ts: Option<Duration>,
+    pub dir: Option<Direction>,
+    pub pkt_type: Option<PktType>,
+    pub seq: Option<u8>,
+    pub channel: Option<String>,
+    pub opcode: Option<String>,
+    pub decrypted: bool,
+    pub detail: FrameDetail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DissectedMass {
+    pub dir: Option<Direction>,
+    pub data_type: u8,
+    pub compress_mode: u8,
+    pub md5: String,
+    pub len: usize,
+    pub crc_ok: bool,
+    pub md5_ok: bool,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Dissection {
+    pub frames: Vec<DissectedFrame>,
+    pub mass: Vec<DissectedMass>,
+    pub decoder: DecoderStats,
+}
+
+/* ─── 读取输入 ─────────────────────────────────────────────── */
+
+/// 按内容识别格式并读取文件
+pub fn load_records(path: impl AsRef<Path>) -> Result<Vec<RawRecord>> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    parse_records(&data)
+}
+
+pub fn parse_records(data: &[u8]) -> Result<Vec<RawRecord>> {
+    if data.starts_with(&PCAPNG_MAGIC) {
+        return parse_pcapng(data);
+    }
+    if PCAP_MAGICS.iter().any(|magic| data.starts_with(magic)) {
+        return parse_pcap(data);
+    }
+
+    let text = std::str::from_utf8(data).context("input is neither pcap nor text")?;
+    if text.trim_start().starts_with('{') {
+        parse_jsonl(text)
+    } else {
+        parse_hex_dump(text)
+    }
+}
+
+/// 每行一段数据，可带 `tx` / `rx` / `>` / `<` 前缀，字节之间可有空格或冒号
+fn parse_hex_dump(text: &str) -> Result<Vec<RawRecord>> {
+    let mut records = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let mut line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut dir = None;
+        let lower = line.to_ascii_lowercase();
+        for (prefix, d) in [
+            ("tx", Direction::Tx),
+            (">", Direction::Tx),
+            ("rx", Direction::Rx),
+            ("<", Direction::Rx),
+        ] {
+            if lower.starts_with(prefix) {
+                dir = Some(d);
+                line = line[prefix.len()..].trim_start_matches([':', ' ', '\t']);
+                break;
+            }
+        }
+
+        let hex: String = line
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '\t' | ':' | '-'))
+            .collect();
+        let data = hex_stream_to_bytes(hex.trim_start_matches("0x"))
+            .map_err(|e| anyhow::anyhow!("line {}: {}", idx + 1, e))?;
+        records.push(RawRecord {
+            ts: None,
+            dir,
+            data,
+        });
+    }
+    Ok(records)
+}
+
+/// capture 写出的 `{dir, raw, ts_us}` 或 replay 会话文件的 `{kind, data, t_us}`
+fn parse_jsonl(text: &str) -> Result<Vec<RawRecord>> {
+    let mut records = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: serde_json::Value = serde_json::from_str(line)
+            .with_context(|| format!("parse json on line {}", idx + 1))?;
+
+        let (dir, hex, ts_us) = match (value.get("raw"), value.get("data")) {
+            (Some(raw), _) => (value.get("dir"), raw, value.get("ts_us")),
+            (None, Some(data)) => (value.get("kind"), data, value.get("t_us")),
+            // 会话文件的 header / input 行
+            (None, None) => continue,
+        };
+        let dir = match dir.and_then(|d| d.as_str()) {
+            Some("tx") => Some(Direction::Tx),
+            Some("rx") => Some(Direction::Rx),
+            _ => continue,
+        };
+        let Some(hex) = hex.as_str() else {
+            continue;
+        };
+
+        records.push(RawRecord {
+            ts: ts_us.and_then(|t| t.as_u64()).map(Duration::from_micros),
+            dir,
+            data: hex_stream_to_bytes(hex)
+                .map_err(|e| anyhow::anyhow!("line {}: {}", idx + 1, e))?,
+        });
+    }
+    Ok(records)
+}
+
+fn parse_pcapng(data: &[u8]) -> Result<Vec<RawRecord>> {
+    let mut reader = PcapNgReader::new(Cursor::new(data))?;
+    let mut linktypes = Vec::new();
+    let mut records = Vec::new();
+
+    while let Some(block) = reader.next_block() {
+        match block? {
+            Block::InterfaceDescription(idb) => linktypes.push(idb.linktype),
+            Block::EnhancedPacket(epb) => {
+                let linktype = linktypes
+                    .get(epb.interface_id as usize)
+                    .copied()
+                    .unwrap_or(DataLink::USER0);
+                let dir = epb.options.iter().find_map(|opt| match opt {
+                    EnhancedPacketOption::Flags(flags) => match flags & 0b11 {
+                        0b01 => Some(Direction::Rx),
+                        0b10 => Some(Direction::Tx),
+                        _ => None,
+                    },
+                    _ => None,
+                });
+                records.push(pcap_record(linktype, epb.timestamp, dir, &epb.data));
+            }
+            Block::SimplePacket(spb) => {
+                let linktype = linktypes.first().copied().unwrap_or(DataLink::USER0);
+                let mut record = pcap_record(linktype, Duration::ZERO, None, &spb.data);
+                record.ts = None;
+                records.push(record);
+            }
+            _ => {}
+        }
+    }
+    Ok(records)
+}
+
+fn parse_pcap(data: &[u8]) -> Result<Vec<RawRecord>> {
+    let mut reader = PcapReader::new(Cursor::new(data))?;
+    let linktype = reader.header().datalink;
+    let mut records = Vec::new();
+    while let Some(pkt) = reader.next_packet() {
+        let pkt = pkt?;
+        records.push(pcap_record(linktype, pkt.timestamp, None, &pkt.data));
+    }
+    Ok(records)
+}
+
+/// capture 写出的包带有伪头部，其余链路类型按原始 MiWear 字节处理
+fn pcap_record(linktype: DataLink, ts: Duration, dir: Option<Direction>, data: &[u8]) -> RawRecord {
+    if linktype == DataLink::USER0 && data.len() >= 12 && data[0] == 1 {
+        let raw_len = u32::from_le_bytes(data[8..12].try_into().unwrap()) as usize;
+        if let Some(raw) = data.get(12..12 + raw_len) {
+            return RawRecord {
+                ts: Some(ts),
+                dir: Some(if data[1] == 0 {
+                    Direction::Rx
+                } else {
+                    Direction::Tx
+                }),
+                data: raw.to_vec(),
+            };
+        }
+    }
+    RawRecord {
+        ts: Some(ts),
+        dir,
+        data: data.to_vec(),
+    }
+}
+
+/* ─── 解析 ─────────────────────────────────────────────────── */
+
+#[derive(Default)]
+struct MassAssembly {
+    total: u16,
+    parts: BTreeMap<u16, Vec<u8>>,
+}
+
+/// 每个方向（Rx / Tx / 未知）各自的解帧与 Mass 拼接状态
+#[derive(Default)]
+struct DirState {
+    frames: FrameReassembler,
+    mass: MassAssembly,
+}
+
+fn slot(dir: Option<Direction>) -> usize {
+    match dir {
+        Some(Direction::Rx) => 0,
+        Some(Direction::Tx) => 1,
+        None => 2,
+    }
+}
+
+pub fn dissect(records: &[RawRecord], keys: &DissectKeys) -> Dissection {
+    let mut out = Dissection::default();
+    let mut states: [DirState; 3] = Default::default();
+
+    for record in records {
+        if record.data.starts_with(&HELLO_MAGIC) {
+            out.frames.push(DissectedFrame {
+                ts: record.ts,
+                dir: record.dir,
+                pkt_type: None,
+                seq: None,
+                channel: None,
+                opcode: None,
+                decrypted: false,
+                detail: FrameDetail::Hello {
+                    raw: to_hex_string(&record.data),
+                },
+            });
+            continue;
+        }
+
+        let state = &mut states[slot(record.dir)];
+        state.frames.push(&record.data);
+        while let Some(pkt) = state.frames.next_packet() {
+            let frame = dissect_frame(record, &pkt, keys, &mut state.mass, &mut out.mass);
+            out.frames.push(frame);
+        }
+    }
+
+    for state in &states {
+        let stats = state.frames.stats();
+        out.decoder.frames += stats.frames;
+        out.decoder.crc_errors += stats.crc_errors;
+        out.decoder.oversized += stats.oversized;
+        out.decoder.skipped_bytes += stats.skipped_bytes;
+    }
+    out
+}
+
+fn dissect_frame(
+    record: &RawRecord,
+    pkt: &MiWearPacket,
+    keys: &DissectKeys,
+    mass: &mut MassAssembly,
+    finished: &mut Vec<DissectedMass>,
+) -> DissectedFrame {
+    let mut frame = DissectedFrame {
+        ts: record.ts,
+        dir: record.dir,
+        pkt_type: Some(pkt.pkt_type),
+        seq: Some(pkt.seq),
+        channel: None,
+        opcode: None,
+        decrypted: false,
+        detail: FrameDetail::Ack,
+    };
+
+    match pkt.pkt_type {
+        PktType::Ack => return frame,
+        PktType::SessionConfig => {
+            frame.detail = FrameDetail::SessionConfig {
+                body: to_hex_string(&pkt.body),
+            };
+            return frame;
+        }
+        PktType::Data => {}
+    }
+
+    let Some(fields) = pkt.data_fields() else {
+        frame.detail = FrameDetail::Error {
+            message: format!("malformed data frame: {}", to_hex_string(&pkt.body)),
+        };
+        return frame;
+    };
+    frame.channel = Some(format!("{:?}", fields.channel));
+    frame.opcode = Some(format!("{:?}", fields.opcode));
+
+    // 明文帧只有一个候选；密文帧逐个尝试密钥，Pb 通道以能否解码判断密钥是否正确
+    let candidates: Vec<(Vec<u8>, bool)> = match fields.opcode {
+        OpCode::Plain => vec![(fields.data.to_vec(), false)],
+        OpCode::Encrypted => keys
+            .candidates(record.dir)
+            .iter()
+            .map(|key| {
+                (
+                    crate::crypto::aesctr::aes128_ctr_crypt(key, key, fields.data),
+                    true,
+                )
+            })
+            .collect(),
+    };
+    let Some((first, _)) = candidates.first() else {
+        frame.detail = FrameDetail::Error {
+            message: format!(
+                "encrypted payload without key: {}",
+                to_hex_string(fields.data)
+            ),
+        };
+        return frame;
+    };
+
+    match fields.channel {
+        Channel::Pb => {
+            let decoded = candidates.iter().find_map(|(content, decrypted)| {
+                pb::protocol::WearPacket::decode(content.as_slice())
+                    .ok()
+                    .map(|packet| (packet, *decrypted))
+            });
+            frame.detail = match decoded {
+                Some((packet, decrypted)) => {
+                    frame.decrypted = decrypted;
+                    match serde_json::to_value(&packet) {
+                        Ok(packet) => FrameDetail::Protobuf { packet },
+                        Err(e) => FrameDetail::Error {
+                            message: format!("serialize WearPacket: {}", e),
+                        },
+                    }
+                }
+                None => FrameDetail::Error {
+                    message: format!("undecodable protobuf: {}", to_hex_string(first)),
+                },
+            };
+        }
+        Channel::Mass => {
+            frame.decrypted = candidates[0].1;
+            frame.detail = mass_part(record.dir, first, mass, finished);
+        }
+        _ => {
+            frame.decrypted = candidates[0].1;
+            frame.detail = FrameDetail::Data {
+                payload: to_hex_string(first),
+            };
+        }
+    }
+    frame
+}
+
+/// Mass 分片：total(u16 LE) | current(u16 LE, 从 1 开始) | fragment
+fn mass_part(
+    dir: Option<Direction>,
+    content: &[u8],
+    mass: &mut MassAssembly,
+    finished: &mut Vec<DissectedMass>,
+) -> FrameDetail {
+    if content.len() < 4 {
+        return FrameDetail::Error {
+            message: format!("mass part too short: {}", to_hex_string(content)),
+        };
+    }
+    let total = u16::from_le_bytes([content[0], content[1]]);
+    let current = u16::from_le_bytes([content[2], content[3]]);
+    let fragment = &content[4..];
+
+    // 分片总数变化说明开始了新的传输
+    if mass.total != total {
+        mass.total = total;
+        mass.parts.clear();
+    }
+    mass.parts.insert(current, fragment.to_vec());
+
+    if mass.parts.len() == total as usize {
+        let inner: Vec<u8> = std::mem::take(&mut mass.parts)
+            .into_values()
+            .flatten()
+            .collect();
+        mass.total = 0;
+        match parse_mass_inner(&inner, None) {
+            Ok(received) => finished.push(DissectedMass {
+                dir,
+                data_type: received.data_type,
+                compress_mode: received.compress_mode,
+                md5: to_hex_string(&received.md5),
+                len: received.data.len(),
+                crc_ok: received.crc_ok,
+                md5_ok: received.md5_ok,
+                data: received.data,
+            }),
+            Err(e) => {
+                return FrameDetail::Error {
+                    message: format!("reassembled mass payload is invalid: {:#}", e),
+                }
+            }
+        }
+    }
+
+    FrameDetail::MassPart {
+        total,
+        current,
+        len: fragment.len(),
+    }
+}
+
+/* ─── 输出 ─────────────────────────────────────────────────── */
+
+impl Dissection {
+    /// 可读的时间线，时间相对第一个带时间戳的记录
+    pub fn timeline(&self) -> String {
+        let origin = self.frames.iter().find_map(|f| f.ts);
+        let mut out = String::new();
+
+        for (idx, frame) in self.frames.iter().enumerate() {
+            let ts = match (frame.ts, origin) {
+                (Some(ts), Some(origin)) => {
+                    format!("+{:.6}s", ts.saturating_sub(origin).as_secs_f64())
+                }
+                _ => "-".to_string(),
+            };
+            let dir = match frame.dir {
+                Some(Direction::Tx) => "TX",
+                Some(Direction::Rx) => "RX",
+                None => "??",
+            };
+            let seq = frame
+                .seq
+                .map(|s| format!("seq={:<3}", s))
+                .unwrap_or_else(|| "       ".to_string());
+            let kind = match (&frame.channel, &frame.opcode) {
+                (Some(ch), Some(op)) => format!(
+                    "{}/{}{}",
+                    ch,
+                    op,
+                    if frame.decrypted { " (decrypted)" } else { "" }
+                ),
+                _ => frame
+                    .pkt_type
+                    .map(|t| format!("{:?}", t))
+                    .unwrap_or_else(|| "Hello".to_string()),
+            };
+            let detail = match &frame.detail {
+                FrameDetail::Hello { raw } => raw.clone(),
+                FrameDetail::Ack => String::new(),
+                FrameDetail::SessionConfig { body } => body.clone(),
+                FrameDetail::Protobuf { packet } => packet.to_string(),
+                FrameDetail::MassPart {
+                    total,
+                    current,
+                    len,
+                } => format!("part {}/{} ({} bytes)", current, total, len),
+                FrameDetail::Data { payload } => payload.clone(),
+                FrameDetail::Error { message } => format!("ERROR: {}", message),
+            };
+            let _ = writeln!(
+                out,
+                "#{:<5} {:>12} {} {} {:<28} {}",
+                idx, ts, dir, seq, kind, detail
+            );
+        }
+
+        for (idx, mass) in self.mass.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "mass[{}] type={} compress={} len={} md5={} crc={} md5_check={}",
+                idx,
+                mass.data_type,
+                mass.compress_mode,
+                mass.len,
+                mass.md5,
+                if mass.crc_ok { "ok" } else { "BAD" },
+                if mass.md5_ok { "ok" } else { "BAD" }
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "{} frames, {} CRC errors, {} bytes skipped",
+            self.decoder.frames, self.decoder.crc_errors, self.decoder.skipped_bytes
+        );
+        out
+    }
+
+    /// 把拼好的 Mass 文件写到目录中，文件名为 `<序号>-<md5>.bin`
+    pub fn write_mass_files(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+        self.mass
+            .iter()
+            .enumerate()
+            .map(|(idx, mass)| {
+                let path = dir.join(format!("{}-{}.bin", idx, mass.md5));
+                std::fs::write(&path, &mass.data)
+                    .with_context(|| format!("write {}", path.display()))?;
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+/// 读取文件、解析并返回时间线，供 CLI 使用
+pub fn dissect_file(
+    path: impl AsRef<Path>,
+    keys: &DissectKeys,
+    mass_out: Option<&Path>,
+) -> Result<String> {
+    let records = load_records(path)?;
+    if records.is_empty() {
+        bail!("No MiWear data found in input");
+    }
+    let dissection = dissect(&records, keys);
+    let mut timeline = dissection.timeline();
+    if let Some(dir) = mass_out {
+        for path in dissection.write_mass_files(dir)? {
+            let _ = writeln!(timeline, "wrote {}", path.display());
+        }
+    }
+    Ok(timeline)
+}
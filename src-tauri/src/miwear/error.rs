@@ -0,0 +1,178 @@
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+use crate::pb::protocol::PrepareStatus;
+
+/// miwear 子系统的错误类型
+///
+/// 内部仍以 anyhow::Error 传递，需要区分错误种类的地方构造 MiWearError 后 `.into()`，
+/// 出口处（frontapi / 插件 API）用 `MiWearError::from` 从 anyhow 错误链中还原
+#[derive(Debug, Clone)]
+pub enum MiWearError {
+    NotConnected,
+    /// 底层链路（BLE / SPP）收发失败
+    Transport {
+        message: String,
+    },
+    Timeout {
+        operation: String,
+    },
+    /// 帧格式、ACK 序号等协议层错误
+    Protocol {
+        message: String,
+    },
+    Crc {
+        expected: u32,
+        actual: u32,
+    },
+    Auth {
+        message: String,
+    },
+    /// 设备在 Prepare 阶段拒绝了请求
+    PrepareRejected {
+        status: i32,
+    },
+    Cancelled,
+    /// Mass 传输在 Prepare 之后被取消，协议没有中止指令，设备侧仍保留未完成的会话
+    MassAbandoned {
+        sent_parts: u16,
+        total_parts: u16,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl MiWearError {
+    pub fn transport(message: impl ToString) -> Self {
+        Self::Transport {
+            message: message.to_string(),
+        }
+    }
+
+    pub fn timeout(operation: impl ToString) -> Self {
+        Self::Timeout {
+            operation: operation.to_string(),
+        }
+    }
+
+    pub fn protocol(message: impl ToString) -> Self {
+        Self::Protocol {
+            message: message.to_string(),
+        }
+    }
+
+    pub fn auth(message: impl ToString) -> Self {
+        Self::Auth {
+            message: message.to_string(),
+        }
+    }
+
+    /// 供前端 / 插件分支判断的错误种类
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NotConnected => "not_connected",
+            Self::Transport { .. } => "transport",
+            Self::Timeout { .. } => "timeout",
+            Self::Protocol { .. } => "protocol",
+            Self::Crc { .. } => "crc",
+            Self::Auth { .. } => "auth",
+            Self::PrepareRejected { .. } => "prepare_rejected",
+            Self::Cancelled => "cancelled",
+            Self::MassAbandoned { .. } => "mass_abandoned",
+            Self::Other { .. } => "other",
+        }
+    }
+
+    fn prepare_status_name(status: i32) -> String {
+        PrepareStatus::try_from(status)
+            .map(|s| format!("{:?}", s))
+            .unwrap_or_else(|_| status.to_string())
+    }
+}
+
+impl std::fmt::Display for MiWearError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotConnected => write!(f, "No devices are connected"),
+            Self::Transport { message } => write!(f, "Transport error: {}", message),
+            Self::Timeout { operation } => write!(f, "Timed out: {}", operation),
+            Self::Protocol { message } => write!(f, "Protocol error: {}", message),
+            Self::Crc { expected, actual } => write!(
+                f,
+                "CRC mismatch: expected {:08x}, got {:08x}",
+                expected, actual
+            ),
+            Self::Auth { message } => write!(f, "Auth failed: {}", message),
+            Self::PrepareRejected { status } => write!(
+                f,
+                "Prepare not READY! Error Info={}",
+                super::device::error::get_prepare_error_info(*status)
+            ),
+            Self::Cancelled => write!(f, "Operation cancelled"),
+            Self::MassAbandoned {
+                sent_parts,
+                total_parts,
+            } => write!(
+                f,
+                "Mass transfer cancelled after {}/{} parts; the device still holds the unfinished session",
+                sent_parts, total_parts
+            ),
+            Self::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for MiWearError {}
+
+/// 序列化为 `{ kind, message, ...详细字段 }`
+impl Serialize for MiWearError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        match self {
+            Self::Timeout { operation } => {
+                map.serialize_entry("operation", operation)?;
+            }
+            Self::Crc { expected, actual } => {
+                map.serialize_entry("expected", expected)?;
+                map.serialize_entry("actual", actual)?;
+            }
+            Self::PrepareRejected { status } => {
+                map.serialize_entry("status", status)?;
+                map.serialize_entry("status_name", &Self::prepare_status_name(*status))?;
+            }
+            Self::MassAbandoned {
+                sent_parts,
+                total_parts,
+            } => {
+                map.serialize_entry("sent_parts", sent_parts)?;
+                map.serialize_entry("total_parts", total_parts)?;
+            }
+            _ => {}
+        }
+        map.end()
+    }
+}
+
+impl From<anyhow::Error> for MiWearError {
+    fn from(err: anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if let Some(e) = cause.downcast_ref::<MiWearError>() {
+                return e.clone();
+            }
+            if cause.is::<tokio::time::error::Elapsed>() {
+                return Self::timeout(err.to_string());
+            }
+        }
+        Self::Other {
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<String> for MiWearError {
+    fn from(message: String) -> Self {
+        Self::Other { message }
+    }
+}
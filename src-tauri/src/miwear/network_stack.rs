@@ -6,7 +6,7 @@ use std::{fs};
 use ipstack::{IpNumber, IpStack, IpStackConfig};
 use pcap_file::pcap::{PcapPacket, PcapWriter};
 use serde::Serialize;
-use tauri::{Emitter, Manager};
+use tauri::Emitter;
 use std::fs::File;
 use std::io::{Error, ErrorKind, Result};
 use std::pin::Pin;
@@ -142,19 +142,15 @@ pub fn start_network_stack(device: Arc<MiWearDevice>) -> mpsc::Sender<Vec<u8>> {
     let (tx_send, mut rx_send) = mpsc::channel::<Vec<u8>>(100);
     let poll_tx_send = PollSender::new(tx_send.clone());
 
-    let base_path = format!(
-        "{}/rslogs",
-        crate::APP_HANDLE
-            .get()
-            .unwrap()
-            .path()
-            .app_log_dir()
-            .unwrap()
-            .to_string_lossy()
-    );
+    let base_path = crate::logger::rslogs_dir();
 
     fs::create_dir_all(&base_path).expect("Error creating directory");
-    let file_path = format!("{}/{}.pcap", base_path, &*START_TIME);
+    // 同时连接多台设备时每台设备各写一个文件
+    let file_path = base_path.join(format!(
+        "{}-{}.pcap",
+        &*START_TIME,
+        device.addr.replace(':', "")
+    ));
 
     let file_out = File::create(file_path).expect("Error creating file out");
 
@@ -171,6 +167,7 @@ pub fn start_network_stack(device: Arc<MiWearDevice>) -> mpsc::Sender<Vec<u8>> {
     tokio::spawn({
         /* let device = device.clone(); */
         let meter_clone = tun_device.meter.clone();
+        let addr = device.addr.clone();
         async move {
             let mut disconnect_rx = crate::miwear::subscribe_disconnect();
             let mut interval = tokio::time::interval(Duration::from_secs(1));
@@ -201,7 +198,7 @@ pub fn start_network_stack(device: Arc<MiWearDevice>) -> mpsc::Sender<Vec<u8>> {
                         *read_speed_lock = read_speed; */
 
                     }
-                    _ = disconnect_rx.recv() => {
+                    _ = crate::miwear::wait_disconnect(&mut disconnect_rx, &addr) => {
                         log::info!("[BandwidthMeter] Device disconnected, stopping network meter");
                         break;
                     }
@@ -219,17 +216,12 @@ pub fn start_network_stack(device: Arc<MiWearDevice>) -> mpsc::Sender<Vec<u8>> {
                     Some(packet) = rx_send.recv() => {
                         log::info!("[MiWearTunDevice] Sending IP packet ({} bytes) over Bluetooth.", packet.len());
                         tokio::select! {
-                            _ = disconnect_rx.recv() => {
+                            _ = crate::miwear::wait_disconnect(&mut disconnect_rx, &device.addr) => {
                                 log::info!("[MiWearTunDevice] Aborted send due to disconnect");
                             }
-                            res = device.send_miwear_pkt(Channel::NetWork, OpCode::Plain, &packet) => {
-                                if let Err(e) = res {
-                                    log::error!(
-                                        "[MiWearTunDevice] Error sending packet from send_loop: {}",
-                                        e
-                                    );
-                                }
-                            }
+                            // 只等待入队：NetWork 队列满时在此阻塞，rx_send 随之积压，
+                            // 背压一路传回 IP 栈；发送失败由 CommandPool 记录
+                            _ = device.enqueue_miwear_pkt(Channel::NetWork, OpCode::Plain, packet) => {}
                         }
                     }
                     else => {
@@ -263,7 +255,7 @@ pub fn start_network_stack(device: Arc<MiWearDevice>) -> mpsc::Sender<Vec<u8>> {
             let count = count.clone();
             let number = serial_number.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             tokio::select! {
-                _ = disconnect_rx.recv() => {
+                _ = crate::miwear::wait_disconnect(&mut disconnect_rx, &device.addr) => {
                     log::info!("[IpStack] Device disconnected, stopping network stack");
                     break;
                 }
@@ -1,10 +1,91 @@
 use std::convert::TryInto;
 
+use bytes::{Buf, BytesMut};
 use serde::Serialize;
+use tokio_util::codec::Decoder;
 
 use crate::pb::{self};
 
 pub const MAGIC: [u8; 2] = [0xA5, 0xA5];
+/// 握手 hello 数据的前缀，这类数据没有 MiWear 帧头
+pub const HELLO_MAGIC: [u8; 2] = [0xBA, 0xDC];
+/// magic(2) | type(1) | seq(1) | len(2) | crc16(2)
+pub const HEADER_LEN: usize = 8;
+/// len 字段为 u16，单帧 body 的上限
+pub const MAX_BODY_LEN: usize = u16::MAX as usize;
+
+/* ─── 解析错误 ─────────────────────────────────────────────── */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PacketError {
+    IncompleteHeader {
+        available: usize,
+    },
+    MagicMismatch,
+    /// 包头 type 不是 Ack / SessionConfig / Data，多半是 magic 误匹配
+    UnknownType {
+        value: u8,
+    },
+    IncompleteFrame {
+        expected: usize,
+        available: usize,
+    },
+    CrcMismatch {
+        expected: u16,
+        actual: u16,
+    },
+    /// body 超出 len 字段能表示的长度，无法编码
+    BodyTooLong {
+        len: usize,
+    },
+}
+
+impl std::fmt::Display for PacketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IncompleteHeader { available } => {
+                write!(f, "Incomplete header: {} bytes", available)
+            }
+            Self::MagicMismatch => write!(f, "Magic mismatch"),
+            Self::UnknownType { value } => write!(f, "Unknown packet type {:#04x}", value),
+            Self::IncompleteFrame {
+                expected,
+                available,
+            } => write!(
+                f,
+                "Incomplete frame: expected {} bytes, got {}",
+                expected, available
+            ),
+            Self::CrcMismatch { expected, actual } => write!(
+                f,
+                "CRC mismatch: expected {:04x}, got {:04x}",
+                expected, actual
+            ),
+            Self::BodyTooLong { len } => {
+                write!(f, "Body too long: {} > {} bytes", len, MAX_BODY_LEN)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PacketError {}
+
+/// `MiWearPacket::parse_all` 在某个偏移处遇到的错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PacketErrorAt {
+    pub offset: usize,
+    pub error: PacketError,
+}
+
+/// `MiWearPacket::parse_all` 的结果：损坏数据不会丢弃已解析出的帧
+#[derive(Debug, Clone, Default)]
+pub struct ParsedPackets {
+    pub packets: Vec<MiWearPacket>,
+    /// 未能解析的 magic 位置及原因
+    pub errors: Vec<PacketErrorAt>,
+    /// 不属于任何完整帧的字节数（含帧间垃圾与末尾残帧）
+    pub skipped_bytes: usize,
+}
 
 #[derive(Debug, Clone)]
 pub enum PacketData {
@@ -37,9 +118,20 @@ impl From<PktType> for u8 {
         t as u8
     }
 }
+impl PktType {
+    /// 严格解析包头 type，非法值返回 None（`From<u8>` 会回退为 Data）
+    pub fn from_wire(b: u8) -> Option<Self> {
+        match b & 0x0F {
+            1 => Some(Self::Ack),
+            2 => Some(Self::SessionConfig),
+            3 => Some(Self::Data),
+            _ => None,
+        }
+    }
+}
 
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
 pub enum Channel {
     Pb = 1,
     Mass = 2,
@@ -72,6 +164,27 @@ impl From<Channel> for u8 {
         c as u8
     }
 }
+impl Channel {
+    /// 按名称（大小写不敏感）或数字解析，供插件等外部输入使用
+    pub fn parse_name(name: &str) -> Option<Self> {
+        if let Ok(v) = name.parse::<u8>() {
+            return (1..=9).contains(&v).then(|| Self::from(v));
+        }
+        [
+            Self::Pb,
+            Self::Mass,
+            Self::MassVoice,
+            Self::FileSensor,
+            Self::FileFitness,
+            Self::OTA,
+            Self::NetWork,
+            Self::Lyra,
+            Self::FileResearch,
+        ]
+        .into_iter()
+        .find(|c| format!("{:?}", c).eq_ignore_ascii_case(name))
+    }
+}
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -98,56 +211,76 @@ pub struct MiWearPacket {
 
 impl MiWearPacket {
     /* -- 解析 ------------------------------------------------ */
-    pub fn parse(buf: &[u8]) -> Result<Self, &'static str> {
-        if buf.len() < 8 {
-            return Err("Incomplete header");
-        }
-        if &buf[..2] != MAGIC {
-            return Err("Magic mismatch");
+    /// 从 buf 头部解析一个帧，buf 可以比帧长；任意输入都不会 panic
+    pub fn parse(buf: &[u8]) -> Result<Self, PacketError> {
+        let Some(header) = buf.get(..HEADER_LEN) else {
+            return Err(PacketError::IncompleteHeader {
+                available: buf.len(),
+            });
+        };
+        if header[..2] != MAGIC {
+            return Err(PacketError::MagicMismatch);
         }
 
-        let pkt_type = PktType::from(buf[2]);
-        let seq = buf[3];
-        let len = u16::from_le_bytes(buf[4..6].try_into().unwrap()) as usize;
-        let frame_sz = 8 + len;
-        if buf.len() < frame_sz {
-            return Err("Incomplete frame");
-        }
+        let pkt_type =
+            PktType::from_wire(header[2]).ok_or(PacketError::UnknownType { value: header[2] })?;
+        let seq = header[3];
+        let len = u16::from_le_bytes([header[4], header[5]]) as usize;
+        let frame_sz = HEADER_LEN + len;
+        let Some(body) = buf.get(HEADER_LEN..frame_sz) else {
+            return Err(PacketError::IncompleteFrame {
+                expected: frame_sz,
+                available: buf.len(),
+            });
+        };
 
-        let crc_given = u16::from_le_bytes(buf[6..8].try_into().unwrap());
-        let crc_calc = crc16_arc(&buf[8..frame_sz]);
+        let crc_given = u16::from_le_bytes([header[6], header[7]]);
+        let crc_calc = crc16_arc(body);
         if crc_given != crc_calc {
-            return Err("CRC mismatch");
+            return Err(PacketError::CrcMismatch {
+                expected: crc_given,
+                actual: crc_calc,
+            });
         }
 
         Ok(Self {
             pkt_type,
             seq,
-            body: buf[8..frame_sz].to_vec(), // ← 拷贝进 Vec
+            body: body.to_vec(), // ← 拷贝进 Vec
         })
     }
 
     /* -- 批量解析 ------------------------------------------- */
-    pub fn parse_all(buf: &[u8]) -> Result<Vec<Self>, &'static str> {
-        let mut packets = Vec::new();
+    /// 解析 buf 中的所有帧
+    ///
+    /// 遇到损坏的帧时记录错误并跳过一个字节重新同步，帧间和末尾的垃圾数据
+    /// 只计入 `skipped_bytes`，已解析出的帧总会返回
+    pub fn parse_all(buf: &[u8]) -> ParsedPackets {
+        let mut out = ParsedPackets::default();
         let mut idx = 0;
 
         while idx < buf.len() {
-            if buf[idx..].starts_with(&MAGIC) {
-                // ok
-            } else if let Some(rel) = buf[idx + 1..].iter().position(|&b| b == MAGIC[0]) {
-                idx += rel + 1;
-                continue;
-            } else {
-                return Err("Magic not found");
-            }
+            let rest = &buf[idx..];
+            let Some(pos) = rest.windows(2).position(|w| w == MAGIC) else {
+                out.skipped_bytes += rest.len();
+                break;
+            };
+            out.skipped_bytes += pos;
+            idx += pos;
 
-            let pkt = Self::parse(&buf[idx..])?;
-            let frame_sz = 8 + pkt.body.len();
-            packets.push(pkt);
-            idx += frame_sz;
+            match Self::parse(&buf[idx..]) {
+                Ok(pkt) => {
+                    idx += HEADER_LEN + pkt.body.len();
+                    out.packets.push(pkt);
+                }
+                Err(error) => {
+                    out.errors.push(PacketErrorAt { offset: idx, error });
+                    out.skipped_bytes += 1;
+                    idx += 1;
+                }
+            }
         }
-        Ok(packets)
+        out
     }
 
     /* -- 解析 Data 帧字段 ----------------------------------- */
@@ -169,7 +302,19 @@ impl MiWearPacket {
     }
 
     /* -- 序列化 --------------------------------------------- */
+    /// 编码为链路帧，body 超过 `MAX_BODY_LEN` 时返回错误
+    pub fn try_encode(&self) -> Result<Vec<u8>, PacketError> {
+        if self.body.len() > MAX_BODY_LEN {
+            return Err(PacketError::BodyTooLong {
+                len: self.body.len(),
+            });
+        }
+        Ok(self.encode())
+    }
+
+    /// 调用方需保证 body 不超过 `MAX_BODY_LEN`，否则 len 字段会被截断
     pub fn encode(&self) -> Vec<u8> {
+        debug_assert!(self.body.len() <= MAX_BODY_LEN);
         let len = self.body.len() as u16;
         let crc = crc16_arc(&self.body);
 
@@ -198,6 +343,250 @@ impl MiWearPacket {
     }
 }
 
+/* ─── 流式解帧 ─────────────────────────────────────────────── */
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct DecoderStats {
+    pub frames: u64,
+    pub crc_errors: u64,
+    /// len 超过协商上限的包头，视为损坏
+    pub oversized: u64,
+    pub skipped_bytes: u64,
+}
+
+/// 增量解帧器：每次从缓冲区头部取出一个完整帧，边解边校验 CRC，
+/// 遇到损坏数据时逐字节重新同步，只移动游标不拷贝积压数据
+///
+/// len 超过 `max_body_len` 的包头直接判为损坏，否则一个坏包头会让解帧器
+/// 一直等待永远凑不齐的数据，把后面的正常帧全部压住
+#[derive(Debug)]
+pub struct MiWearFrameDecoder {
+    stats: DecoderStats,
+    max_body_len: usize,
+}
+
+impl Default for MiWearFrameDecoder {
+    fn default() -> Self {
+        Self::with_max_body_len(MAX_BODY_LEN)
+    }
+}
+
+impl MiWearFrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_body_len(max_body_len: usize) -> Self {
+        Self {
+            stats: DecoderStats::default(),
+            max_body_len: max_body_len.min(MAX_BODY_LEN),
+        }
+    }
+
+    /// 会话协商出帧长上限后调整
+    pub fn set_max_body_len(&mut self, max_body_len: usize) {
+        self.max_body_len = max_body_len.min(MAX_BODY_LEN);
+    }
+
+    pub fn max_body_len(&self) -> usize {
+        self.max_body_len
+    }
+
+    pub fn stats(&self) -> DecoderStats {
+        self.stats
+    }
+
+    fn skip(&mut self, src: &mut BytesMut, n: usize) {
+        src.advance(n);
+        self.stats.skipped_bytes += n as u64;
+    }
+}
+
+impl Decoder for MiWearFrameDecoder {
+    type Item = MiWearPacket;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            /* 1. 对齐到 magic */
+            match src.windows(2).position(|w| w == MAGIC) {
+                Some(0) => {}
+                Some(pos) => self.skip(src, pos),
+                None => {
+                    // 末尾的 0xA5 可能是下一个 magic 的前半部分，保留下来
+                    let keep = usize::from(src.last() == Some(&MAGIC[0]));
+                    let junk = src.len() - keep;
+                    if junk > 0 {
+                        self.skip(src, junk);
+                    }
+                    return Ok(None);
+                }
+            }
+
+            if src.len() < HEADER_LEN {
+                return Ok(None);
+            }
+
+            /* 2. 校验包头，类型非法说明 magic 是误匹配 */
+            if PktType::from_wire(src[2]).is_none() {
+                self.skip(src, 1);
+                continue;
+            }
+
+            let len = u16::from_le_bytes([src[4], src[5]]) as usize;
+            if len > self.max_body_len {
+                self.stats.oversized += 1;
+                log::warn!(
+                    "[MiWearFrameDecoder] Frame seq={} len={} exceeds limit {}, resyncing",
+                    src[3],
+                    len,
+                    self.max_body_len
+                );
+                self.skip(src, 1);
+                continue;
+            }
+
+            let frame_sz = HEADER_LEN + len;
+            if src.len() < frame_sz {
+                src.reserve(frame_sz - src.len());
+                return Ok(None);
+            }
+
+            /* 3. CRC 校验，失败则跳过一个字节重新同步 */
+            let crc_given = u16::from_le_bytes([src[6], src[7]]);
+            if crc16_arc(&src[HEADER_LEN..frame_sz]) != crc_given {
+                self.stats.crc_errors += 1;
+                log::warn!(
+                    "[MiWearFrameDecoder] CRC mismatch on frame seq={} len={}, resyncing",
+                    src[3],
+                    len
+                );
+                self.skip(src, 1);
+                continue;
+            }
+
+            let frame = src.split_to(frame_sz);
+            self.stats.frames += 1;
+
+            return Ok(Some(MiWearPacket {
+                pkt_type: PktType::from(frame[2]),
+                seq: frame[3],
+                body: frame[HEADER_LEN..].to_vec(),
+            }));
+        }
+    }
+}
+
+/// 带缓冲区的解帧器，供蓝牙通知这类“推”模式的数据源使用
+#[derive(Debug, Default)]
+pub struct FrameReassembler {
+    buf: BytesMut,
+    decoder: MiWearFrameDecoder,
+}
+
+impl FrameReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_body_len(max_body_len: usize) -> Self {
+        Self {
+            buf: BytesMut::new(),
+            decoder: MiWearFrameDecoder::with_max_body_len(max_body_len),
+        }
+    }
+
+    pub fn set_max_body_len(&mut self, max_body_len: usize) {
+        self.decoder.set_max_body_len(max_body_len);
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// 取出下一个完整帧，数据不足时返回 None
+    pub fn next_packet(&mut self) -> Option<MiWearPacket> {
+        // MiWearFrameDecoder 不会返回 Err，损坏数据已在内部跳过
+        self.decoder.decode(&mut self.buf).ok().flatten()
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn stats(&self) -> DecoderStats {
+        self.decoder.stats()
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+}
+
+/// 链路字节流中切出的一段
+pub enum Chunk {
+    /// 通过 CRC 校验的完整帧
+    Frame { raw: Vec<u8>, pkt: MiWearPacket },
+    /// 解帧器会丢弃的数据
+    Corrupt {
+        raw: Vec<u8>,
+        error: Option<PacketError>,
+    },
+}
+
+/// 与 `MiWearFrameDecoder` 同样的规则按帧切分字节流，但保留原始字节：
+/// 被丢弃的部分连同原因一起交给调用方，而不是悄悄跳过
+#[derive(Default)]
+pub struct FrameSplitter {
+    buf: Vec<u8>,
+}
+
+impl FrameSplitter {
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    pub fn next_chunk(&mut self) -> Option<Chunk> {
+        match self.buf.windows(2).position(|w| w == MAGIC) {
+            Some(0) => {}
+            Some(pos) => return Some(self.corrupt(pos, None)),
+            None => {
+                // 末尾的 0xA5 可能是下一个 magic 的前半部分，留到下次
+                let keep = usize::from(self.buf.last() == Some(&MAGIC[0]));
+                let junk = self.buf.len() - keep;
+                return (junk > 0).then(|| self.corrupt(junk, None));
+            }
+        }
+
+        match MiWearPacket::parse(&self.buf) {
+            Ok(pkt) => {
+                let raw = self.buf.drain(..HEADER_LEN + pkt.body.len()).collect();
+                Some(Chunk::Frame { raw, pkt })
+            }
+            Err(PacketError::IncompleteHeader { .. } | PacketError::IncompleteFrame { .. }) => None,
+            // 与解帧器一样从下一个 magic 处重新同步
+            Err(error) => {
+                let resync = self.buf[1..]
+                    .windows(2)
+                    .position(|w| w == MAGIC)
+                    .map_or(self.buf.len(), |pos| pos + 1);
+                Some(self.corrupt(resync, Some(error)))
+            }
+        }
+    }
+
+    fn corrupt(&mut self, len: usize, error: Option<PacketError>) -> Chunk {
+        Chunk::Corrupt {
+            raw: self.buf.drain(..len).collect(),
+            error,
+        }
+    }
+
+    /// 取出尚未凑成帧的残余数据
+    pub fn take_rest(&mut self) -> Option<Vec<u8>> {
+        (!self.buf.is_empty()).then(|| std::mem::take(&mut self.buf))
+    }
+}
+
 /* ─── CRC-16/ARC 实现 ──────────────────────────────────────── */
 fn crc16_arc(data: &[u8]) -> u16 {
     let mut crc: u16 = 0x0000;
@@ -213,3 +602,112 @@ fn crc16_arc(data: &[u8]) -> u16 {
     }
     crc
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(seq: u8, payload: &[u8]) -> Vec<u8> {
+        MiWearPacket::new_data(seq, Channel::Pb, OpCode::Plain, payload).encode()
+    }
+
+    /// 合法 magic 与 type、len 很大的损坏包头
+    fn bogus_header(len: u16) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(PktType::Data.into());
+        out.push(0x42);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&[0x00, 0x00]);
+        out
+    }
+
+    #[test]
+    fn decodes_back_to_back_frames() {
+        let mut rx = FrameReassembler::with_max_body_len(64);
+        let mut data = frame(1, b"hello");
+        data.extend(frame(2, b"world"));
+        rx.push(&data);
+
+        assert_eq!(rx.next_packet().unwrap().seq, 1);
+        assert_eq!(rx.next_packet().unwrap().seq, 2);
+        assert!(rx.next_packet().is_none());
+        assert_eq!(rx.buffered(), 0);
+        assert_eq!(rx.stats().frames, 2);
+    }
+
+    #[test]
+    fn oversized_header_does_not_hold_back_later_frames() {
+        let mut rx = FrameReassembler::with_max_body_len(64);
+        let mut data = bogus_header(0xF000);
+        data.extend(frame(7, b"payload"));
+        rx.push(&data);
+
+        let pkt = rx.next_packet().expect("frame after corrupt header");
+        assert_eq!(pkt.seq, 7);
+        assert_eq!(pkt.data_fields().unwrap().data, b"payload");
+        assert_eq!(rx.stats().oversized, 1);
+        assert_eq!(rx.stats().skipped_bytes, HEADER_LEN as u64);
+        assert_eq!(rx.buffered(), 0);
+    }
+
+    #[test]
+    fn frame_within_limit_waits_for_more_data() {
+        let mut rx = FrameReassembler::with_max_body_len(64);
+        let data = frame(3, &[0x55; 40]);
+        rx.push(&data[..20]);
+        assert!(rx.next_packet().is_none());
+        assert_eq!(rx.buffered(), 20);
+
+        rx.push(&data[20..]);
+        assert_eq!(rx.next_packet().unwrap().seq, 3);
+        assert_eq!(rx.stats().oversized, 0);
+    }
+
+    #[test]
+    fn limit_can_be_raised_after_negotiation() {
+        let mut rx = FrameReassembler::with_max_body_len(16);
+        let big = frame(4, &[0x11; 100]);
+
+        rx.push(&big);
+        assert!(rx.next_packet().is_none());
+        assert_eq!(rx.stats().oversized, 1);
+
+        rx.clear();
+        rx.set_max_body_len(256);
+        rx.push(&big);
+        assert_eq!(rx.next_packet().unwrap().body.len(), 102);
+    }
+
+    #[test]
+    fn default_limit_is_len_field_range() {
+        let decoder = MiWearFrameDecoder::new();
+        assert_eq!(decoder.max_body_len(), MAX_BODY_LEN);
+        assert_eq!(
+            MiWearFrameDecoder::with_max_body_len(usize::MAX).max_body_len(),
+            MAX_BODY_LEN
+        );
+    }
+
+    #[test]
+    fn splitter_keeps_junk_between_frames() {
+        let mut splitter = FrameSplitter::default();
+        let first = frame(5, b"one");
+        let second = frame(6, b"two");
+        let mut data = first.clone();
+        data.extend_from_slice(&[0x00, 0x01]);
+        data.extend_from_slice(&second[..4]);
+        splitter.push(&data);
+
+        assert!(matches!(splitter.next_chunk(), Some(Chunk::Frame { raw, .. }) if raw == first));
+        assert!(
+            matches!(splitter.next_chunk(), Some(Chunk::Corrupt { raw, error: None }) if raw == [0x00, 0x01])
+        );
+        assert!(splitter.next_chunk().is_none());
+
+        splitter.push(&second[4..]);
+        assert!(
+            matches!(splitter.next_chunk(), Some(Chunk::Frame { raw, pkt }) if raw == second && pkt.seq == 6)
+        );
+        assert!(splitter.take_rest().is_none());
+    }
+}
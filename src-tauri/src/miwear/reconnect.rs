@@ -0,0 +1,493 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tauri::Emitter;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    connection::{self, ConnectionState},
+    device::{mass::SendMassCallbackData, MiWearState},
+    MiWearDevice, MiWearError,
+};
+
+pub const RECONNECT_EVENT: &str = "device-reconnect";
+
+/// 重连过程中发给前端的事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ReconnectEvent {
+    Scheduled {
+        addr: String,
+        attempt: u32,
+        delay_ms: u64,
+    },
+    Attempting {
+        addr: String,
+        attempt: u32,
+    },
+    AttemptFailed {
+        addr: String,
+        attempt: u32,
+        error: MiWearError,
+    },
+    Connected {
+        addr: String,
+        attempt: u32,
+    },
+    GaveUp {
+        addr: String,
+        attempts: u32,
+    },
+    Cancelled {
+        addr: String,
+    },
+    ResumingTransfer {
+        addr: String,
+        file_path: String,
+    },
+    TransferProgress {
+        addr: String,
+        progress: f32,
+    },
+    TransferResumed {
+        addr: String,
+    },
+    TransferFailed {
+        addr: String,
+        error: MiWearError,
+    },
+}
+
+impl ReconnectEvent {
+    fn emit(self) {
+        log::info!("[Reconnect] {:?}", self);
+        if let Some(app) = crate::APP_HANDLE.get() {
+            let _ = app.emit(RECONNECT_EVENT, self);
+        }
+    }
+}
+
+/// 可在重连后重新发起的安装请求
+///
+/// Mass 层会根据续传记录跳过已确认的分片，所以重新发起即可从断点继续
+#[derive(Debug, Clone)]
+pub enum ResumableTransfer {
+    Watchface {
+        file_path: String,
+        new_watchface_id: Option<Vec<u8>>,
+        id: String,
+    },
+    ThirdPartyApp {
+        file_path: String,
+        package_name: String,
+        version_code: u32,
+    },
+    Firmware {
+        file_path: String,
+    },
+}
+
+impl ResumableTransfer {
+    pub fn file_path(&self) -> &str {
+        match self {
+            Self::Watchface { file_path, .. }
+            | Self::ThirdPartyApp { file_path, .. }
+            | Self::Firmware { file_path } => file_path,
+        }
+    }
+
+    pub async fn run<F>(
+        &self,
+        device: Arc<MiWearDevice>,
+        cancel: CancellationToken,
+        progress_cb: F,
+    ) -> Result<()>
+    where
+        F: Fn(SendMassCallbackData) + Send + Sync,
+    {
+        match self {
+            Self::Watchface {
+                file_path,
+                new_watchface_id,
+                id,
+            } => {
+                super::device::watchface::install_watchface(
+                    device,
+                    file_path,
+                    new_watchface_id.as_ref(),
+                    id,
+                    cancel,
+                    progress_cb,
+                )
+                .await
+            }
+            Self::ThirdPartyApp {
+                file_path,
+                package_name,
+                version_code,
+            } => {
+                super::device::thirdpartyapp::install_app(
+                    device,
+                    file_path,
+                    package_name,
+                    *version_code,
+                    cancel,
+                    progress_cb,
+                )
+                .await
+            }
+            Self::Firmware { file_path } => {
+                super::device::firmware::install_firmware(device, file_path, cancel, progress_cb)
+                    .await
+            }
+        }
+    }
+}
+
+/// 因链路异常（而非用户主动断开）断开的设备
+static LINK_LOST: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+/// 进行中或因断开而中断的传输：address → 请求
+///
+/// 传输开始时就登记，不依赖失败返回与重连完成的先后顺序；
+/// 成功、用户取消或非断开原因失败时移除，剩下的在重连后续传
+static INTERRUPTED: Lazy<Mutex<HashMap<String, TrackedTransfer>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_TRANSFER_ID: AtomicU64 = AtomicU64::new(1);
+/// 正在重连的设备：address → 取消令牌
+static RECONNECTING: Lazy<Mutex<HashMap<String, CancellationToken>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 在链路异常导致的 disconnect 之前调用，只有这样断开的设备才会自动重连
+pub fn mark_link_lost(addr: &str) {
+    let mut lost = LINK_LOST.lock();
+    if !lost.iter().any(|a| a == addr) {
+        lost.push(addr.to_string());
+    }
+}
+
+fn take_link_lost(addr: &str) -> bool {
+    let mut lost = LINK_LOST.lock();
+    let before = lost.len();
+    lost.retain(|a| a != addr);
+    lost.len() != before
+}
+
+struct TrackedTransfer {
+    /// 当前负责该传输的执行者，重连续传接管后更换
+    id: u64,
+    transfer: ResumableTransfer,
+}
+
+/// `track_transfer` 的凭据，传输结束时交给 `finish_transfer`
+pub struct TransferTicket {
+    addr: String,
+    id: u64,
+}
+
+/// 传输开始前调用，登记可在重连后重新发起的请求
+pub fn track_transfer(addr: &str, transfer: ResumableTransfer) -> TransferTicket {
+    let id = NEXT_TRANSFER_ID.fetch_add(1, Ordering::Relaxed);
+    INTERRUPTED
+        .lock()
+        .insert(addr.to_string(), TrackedTransfer { id, transfer });
+    TransferTicket {
+        addr: addr.to_string(),
+        id,
+    }
+}
+
+/// 传输结束后调用：只有因设备断开而失败的传输保留登记，重连成功后自动续传
+///
+/// 登记已被重连后的续传接管时不做任何改动
+pub async fn finish_transfer(ticket: TransferTicket, device: &Arc<MiWearDevice>, ret: &Result<()>) {
+    let interrupted = match ret {
+        Ok(()) => false,
+        Err(e) => {
+            let cancelled = e.chain().any(|cause| {
+                matches!(
+                    cause.downcast_ref::<MiWearError>(),
+                    Some(MiWearError::Cancelled | MiWearError::MassAbandoned { .. })
+                )
+            });
+            let still_connected = super::get_device(Some(&device.addr))
+                .await
+                .map(|d| Arc::ptr_eq(&d, device))
+                .unwrap_or(false);
+            !cancelled && !still_connected
+        }
+    };
+
+    let mut transfers = INTERRUPTED.lock();
+    let Some(tracked) = transfers.get(&ticket.addr) else {
+        return;
+    };
+    if tracked.id != ticket.id {
+        return;
+    }
+    if interrupted {
+        log::info!(
+            "[Reconnect] Transfer of {} to {} was interrupted by disconnect",
+            tracked.transfer.file_path(),
+            ticket.addr
+        );
+    } else {
+        transfers.remove(&ticket.addr);
+    }
+}
+
+/// 由重连后的续传接管登记的传输，旧连接上的传输之后结束时不再影响登记
+fn take_over(addr: &str) -> Option<(TransferTicket, ResumableTransfer)> {
+    let mut transfers = INTERRUPTED.lock();
+    let tracked = transfers.get_mut(addr)?;
+    tracked.id = NEXT_TRANSFER_ID.fetch_add(1, Ordering::Relaxed);
+    Some((
+        TransferTicket {
+            addr: addr.to_string(),
+            id: tracked.id,
+        },
+        tracked.transfer.clone(),
+    ))
+}
+
+/// 停止对某台设备的重连并丢弃其中断的传输（用户主动连接 / 移除设备时）
+pub fn cancel(addr: &str) {
+    if let Some(token) = RECONNECTING.lock().remove(addr) {
+        token.cancel();
+    }
+    INTERRUPTED.lock().remove(addr);
+    take_link_lost(addr);
+}
+
+/// 启动重连监督任务，监听所有设备的断开通知
+pub fn start_supervisor() {
+    tauri::async_runtime::spawn(async move {
+        let mut rx = super::subscribe_disconnect();
+        loop {
+            match rx.recv().await {
+                Ok(addr) => {
+                    if !take_link_lost(&addr) {
+                        // 主动断开的设备不会续传
+                        INTERRUPTED.lock().remove(&addr);
+                        continue;
+                    }
+                    if !crate::config::read(|c| c.auto_reconnect) {
+                        log::info!(
+                            "[Reconnect] Auto reconnect disabled, not reconnecting {}",
+                            addr
+                        );
+                        INTERRUPTED.lock().remove(&addr);
+                        continue;
+                    }
+
+                    let token = CancellationToken::new();
+                    if let Some(old) = RECONNECTING.lock().insert(addr.clone(), token.clone()) {
+                        old.cancel();
+                    }
+                    tauri::async_runtime::spawn(async move {
+                        reconnect(&addr, &token).await;
+                        let mut running = RECONNECTING.lock();
+                        if running
+                            .get(&addr)
+                            .map(|t| t.same_token(&token))
+                            .unwrap_or(false)
+                        {
+                            running.remove(&addr);
+                        }
+                    });
+                }
+                Err(RecvError::Lagged(n)) => {
+                    log::warn!("[Reconnect] Missed {} disconnect notifications", n);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+    max_attempts: u32,
+}
+
+impl Backoff {
+    fn from_config() -> Self {
+        crate::config::read(|c| Self {
+            initial: Duration::from_millis(c.reconnect_initial_delay_ms.into()),
+            max: Duration::from_millis(c.reconnect_max_delay_ms.into()),
+            max_attempts: c.reconnect_max_attempts,
+        })
+    }
+
+    /// attempt 从 1 开始
+    fn delay_for(&self, attempt: u32) -> Duration {
+        self.initial
+            .saturating_mul(1u32 << (attempt - 1).min(16))
+            .min(self.max)
+    }
+}
+
+async fn reconnect(addr: &str, token: &CancellationToken) {
+    let Some(state) = crate::config::read(|c| {
+        c.paired_devices
+            .iter()
+            .find(|dev| dev.addr == addr)
+            .cloned()
+    }) else {
+        log::info!("[Reconnect] {} is no longer paired, skipping", addr);
+        return;
+    };
+    if state.authkey.is_empty() {
+        log::warn!("[Reconnect] {} has no stored authkey, skipping", addr);
+        return;
+    }
+
+    let backoff = Backoff::from_config();
+    for attempt in 1..=backoff.max_attempts {
+        let delay = backoff.delay_for(attempt);
+        // 其他流程已经在连接这台设备时不再重连
+        if let Err(e) = connection::transition(addr, ConnectionState::Reconnecting) {
+            log::info!("[Reconnect] Stop reconnecting {}: {}", addr, e);
+            return;
+        }
+        ReconnectEvent::Scheduled {
+            addr: addr.to_string(),
+            attempt,
+            delay_ms: delay.as_millis() as u64,
+        }
+        .emit();
+
+        tokio::select! {
+            _ = token.cancelled() => {
+                if connection::current(addr) == ConnectionState::Reconnecting {
+                    let _ = connection::transition(addr, ConnectionState::Disconnected);
+                }
+                ReconnectEvent::Cancelled { addr: addr.to_string() }.emit();
+                return;
+            }
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        if super::get_device(Some(addr)).await.is_some() {
+            log::info!("[Reconnect] {} was connected by someone else", addr);
+            return;
+        }
+
+        ReconnectEvent::Attempting {
+            addr: addr.to_string(),
+            attempt,
+        }
+        .emit();
+
+        match connect_and_auth(&state).await {
+            Ok(device) => {
+                ReconnectEvent::Connected {
+                    addr: addr.to_string(),
+                    attempt,
+                }
+                .emit();
+                resume_transfer(device).await;
+                return;
+            }
+            Err(e) => ReconnectEvent::AttemptFailed {
+                addr: addr.to_string(),
+                attempt,
+                error: e.into(),
+            }
+            .emit(),
+        }
+    }
+
+    INTERRUPTED.lock().remove(addr);
+    connection::fail(
+        addr,
+        format!(
+            "Gave up reconnecting after {} attempts",
+            backoff.max_attempts
+        ),
+    );
+    ReconnectEvent::GaveUp {
+        addr: addr.to_string(),
+        attempts: backoff.max_attempts,
+    }
+    .emit();
+}
+
+/// 重新建立链路并用保存的 authkey 完成 hello / auth
+async fn connect_and_auth(state: &MiWearState) -> Result<Arc<MiWearDevice>> {
+    let device = MiWearDevice::connect(state.addr.clone(), state.name.clone()).await?;
+
+    let handshake = async {
+        device.start_hello().await?;
+        device.start_auth(state.authkey.clone()).await
+    };
+    if let Err(e) = handshake.await {
+        let _ = device.disconnect().await;
+        return Err(e);
+    }
+
+    // 重连的设备只在没有其他设备时成为 primary
+    super::insert_device(device.clone(), false).await;
+    if let Some(app) = crate::APP_HANDLE.get() {
+        let _ = app.emit("device-connected", state.addr.clone());
+    }
+    Ok(device)
+}
+
+async fn resume_transfer(device: Arc<MiWearDevice>) {
+    if !crate::config::read(|c| c.reconnect_resume_transfers) {
+        INTERRUPTED.lock().remove(&device.addr);
+        return;
+    }
+    // 传输开始时就已登记，旧连接上的传输即使还没返回失败也能在这里取到
+    let Some((ticket, transfer)) = take_over(&device.addr) else {
+        return;
+    };
+
+    let addr = device.addr.clone();
+    ReconnectEvent::ResumingTransfer {
+        addr: addr.clone(),
+        file_path: transfer.file_path().to_string(),
+    }
+    .emit();
+
+    let op = device.operations.begin("resume_transfer");
+    let progress_addr = addr.clone();
+    let ret = transfer
+        .run(device.clone(), op.token(), move |data| {
+            if let Some(app) = crate::APP_HANDLE.get() {
+                let _ = app.emit(
+                    RECONNECT_EVENT,
+                    ReconnectEvent::TransferProgress {
+                        addr: progress_addr.clone(),
+                        progress: data.progress,
+                    },
+                );
+            }
+        })
+        .await;
+    finish_transfer(ticket, &device, &ret).await;
+
+    match ret {
+        Ok(()) => ReconnectEvent::TransferResumed { addr }.emit(),
+        Err(e) => {
+            ReconnectEvent::TransferFailed {
+                addr,
+                error: e.into(),
+            }
+            .emit();
+        }
+    }
+}
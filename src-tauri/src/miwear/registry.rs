@@ -0,0 +1,87 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::RwLock;
+
+use super::MiWearDevice;
+
+/// 已连接设备表：address → 设备
+///
+/// 未指定目标地址的调用都落到 primary 设备上，以兼容只连接一台设备时的行为
+#[derive(Default)]
+pub struct DeviceRegistry {
+    devices: RwLock<HashMap<String, Arc<MiWearDevice>>>,
+    primary: RwLock<Option<String>>,
+}
+
+impl DeviceRegistry {
+    /// 登记一台设备，同一地址已存在时覆盖；`make_primary` 为 false 时仅在还没有 primary 时成为 primary
+    ///
+    /// 返回该设备是否成为了 primary
+    pub async fn insert(&self, dev: Arc<MiWearDevice>, make_primary: bool) -> bool {
+        let addr = dev.addr.clone();
+        self.devices.write().await.insert(addr.clone(), dev);
+
+        let mut primary = self.primary.write().await;
+        if make_primary || primary.is_none() {
+            *primary = Some(addr);
+            return true;
+        }
+        false
+    }
+
+    /// 移除一台设备，移除的是 primary 时从剩余设备中另选一台
+    ///
+    /// 按连接实例而不是地址匹配：同一地址已登记了新的连接时不做改动并返回 false，
+    /// 避免迟到的旧连接断开时把重连上的设备移除
+    pub async fn remove(&self, dev: &MiWearDevice) -> bool {
+        let mut devices = self.devices.write().await;
+        match devices.get(&dev.addr) {
+            Some(current) if !std::ptr::eq(current.as_ref(), dev) => return false,
+            Some(_) => {
+                devices.remove(&dev.addr);
+            }
+            None => {}
+        }
+
+        let mut primary = self.primary.write().await;
+        if primary.as_deref() == Some(dev.addr.as_str()) {
+            *primary = devices.keys().min().cloned();
+        }
+        true
+    }
+
+    /// `addr` 为 None 时返回 primary 设备
+    pub async fn get(&self, addr: Option<&str>) -> Option<Arc<MiWearDevice>> {
+        let addr = match addr {
+            Some(addr) => addr.to_string(),
+            None => self.primary.read().await.clone()?,
+        };
+        self.devices.read().await.get(&addr).cloned()
+    }
+
+    pub async fn primary_addr(&self) -> Option<String> {
+        self.primary.read().await.clone()
+    }
+
+    pub async fn set_primary(&self, addr: &str) -> bool {
+        if !self.devices.read().await.contains_key(addr) {
+            return false;
+        }
+        *self.primary.write().await = Some(addr.to_string());
+        true
+    }
+
+    pub async fn all(&self) -> Vec<Arc<MiWearDevice>> {
+        let devices = self.devices.read().await;
+        let mut addrs: Vec<&String> = devices.keys().collect();
+        addrs.sort();
+        addrs
+            .into_iter()
+            .map(|addr| devices[addr].clone())
+            .collect()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.devices.read().await.is_empty()
+    }
+}
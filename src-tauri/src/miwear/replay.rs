@@ -0,0 +1,627 @@
+//! MiWear 会话录制与回放
+//!
+//! 录制：记录 `MiWearDevice::send` 写出的与蓝牙订阅回调收到的原始字节，
+//! 以及认证时用到的非链路输入（App 随机数、是否复用了缓存的会话密钥），写成 JSON Lines 会话文件。
+//!
+//! 回放：[`SessionReplayer`] 在回环链路的设备侧按录制时的节奏（或加速）注入上行数据，
+//! 并断言 App 写出的帧与录制一致。调用方像连接真实设备一样驱动 `start_hello` /
+//! `start_auth` / 安装等操作，认证时需要传入录制时使用的 authkey。
+//!
+//! 会话文件不保存会话密钥。录制时复用了缓存密钥的会话，回放时需通过
+//! [`ReplayOptions::session_keys`] 提供同一组密钥。
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use super::{
+    capture::Direction,
+    device::{MiWearDevice, SecurityKeys},
+    packet::{Chunk, FrameSplitter, HELLO_MAGIC},
+    transport::loopback::{self, LoopbackPeer},
+};
+use crate::tools::{hex_stream_to_bytes, to_hex_string};
+
+const SESSION_FILE_VERSION: u32 = 1;
+
+/// 会话文件中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SessionEvent {
+    Header {
+        version: u32,
+        name: String,
+        addr: String,
+        max_frame_size: usize,
+    },
+    /// 设备 → App，`t_us` 为距录制开始的微秒数
+    Rx {
+        t_us: u64,
+        data: String,
+    },
+    /// App → 设备
+    Tx {
+        t_us: u64,
+        data: String,
+    },
+    Input {
+        t_us: u64,
+        input: SessionInput,
+    },
+}
+
+/// 不经过链路、但会影响 App 写出内容的输入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionInput {
+    AuthNonce {
+        data: String,
+    },
+    /// 只记录是否复用了缓存的会话密钥，密钥本身不落盘
+    CachedSession {
+        used: bool,
+    },
+}
+
+/// 已加载的会话文件
+#[derive(Debug, Clone)]
+pub struct SessionRecording {
+    pub name: String,
+    pub addr: String,
+    pub max_frame_size: usize,
+    pub events: Vec<SessionEvent>,
+}
+
+impl SessionRecording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("read session file: {}", path.display()))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty()).enumerate();
+
+        let Some((_, first)) = lines.next() else {
+            bail!("Session file is empty");
+        };
+        let SessionEvent::Header {
+            version,
+            name,
+            addr,
+            max_frame_size,
+        } = serde_json::from_str(first).context("parse session header")?
+        else {
+            bail!("Session file does not start with a header");
+        };
+        if version != SESSION_FILE_VERSION {
+            bail!("Unsupported session file version {}", version);
+        }
+
+        let events = lines
+            .map(|(idx, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("parse session event on line {}", idx + 1))
+            })
+            .collect::<Result<Vec<SessionEvent>>>()?;
+
+        Ok(Self {
+            name,
+            addr,
+            max_frame_size,
+            events,
+        })
+    }
+
+    /// 录制时是否复用了缓存的会话密钥
+    pub fn uses_cached_session(&self) -> bool {
+        self.events.iter().any(|e| {
+            matches!(
+                e,
+                SessionEvent::Input {
+                    input: SessionInput::CachedSession { used: true },
+                    ..
+                }
+            )
+        })
+    }
+
+    fn inputs(&self) -> VecDeque<SessionInput> {
+        self.events
+            .iter()
+            .filter_map(|e| match e {
+                SessionEvent::Input { input, .. } => Some(input.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 把事件整理成回放步骤：相邻的 Tx 合成一组，组内帧的顺序不作要求
+    fn steps(&self) -> Result<Vec<ReplayStep>> {
+        let mut steps = Vec::new();
+        let mut expected = Vec::new();
+        let mut splitter = FrameSplitter::default();
+        let mut last_t_us = 0;
+
+        for event in &self.events {
+            match event {
+                SessionEvent::Tx { t_us, data } => {
+                    expected.extend(split_frames(&mut splitter, &decode_hex(data)?));
+                    last_t_us = *t_us;
+                }
+                SessionEvent::Rx { t_us, data } => {
+                    if !expected.is_empty() {
+                        steps.push(ReplayStep::ExpectTx(std::mem::take(&mut expected)));
+                    }
+                    steps.push(ReplayStep::Rx {
+                        delay: Duration::from_micros(t_us.saturating_sub(last_t_us)),
+                        data: decode_hex(data)?,
+                    });
+                    last_t_us = *t_us;
+                }
+                SessionEvent::Input { t_us, .. } => last_t_us = *t_us,
+                SessionEvent::Header { .. } => bail!("Unexpected header in session body"),
+            }
+        }
+        if !expected.is_empty() {
+            steps.push(ReplayStep::ExpectTx(expected));
+        }
+        Ok(steps)
+    }
+}
+
+/* ─── 录制 ─────────────────────────────────────────────────── */
+
+struct Recorder {
+    path: PathBuf,
+    file: BufWriter<File>,
+    started: Instant,
+}
+
+impl Recorder {
+    fn write(&mut self, event: &SessionEvent) -> Result<()> {
+        serde_json::to_writer(&mut self.file, event)?;
+        self.file.write_all(b"\n")?;
+        self.file.flush()?;
+        Ok(())
+    }
+
+    fn t_us(&self) -> u64 {
+        self.started.elapsed().as_micros() as u64
+    }
+}
+
+/// address → 录制中的会话
+static RECORDERS: Lazy<Mutex<HashMap<String, Recorder>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 回放中的设备地址
+static REPLAYING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+pub fn is_recording(addr: &str) -> bool {
+    RECORDERS.lock().contains_key(addr)
+}
+
+pub fn is_replaying(addr: &str) -> bool {
+    REPLAYING.lock().contains(addr)
+}
+
+/// 开始录制，必须在建立会话之前调用，否则回放时 seq 与密钥都对不上
+pub fn start_recording(name: &str, addr: &str, max_frame_size: usize) -> Result<PathBuf> {
+    if is_replaying(addr) {
+        bail!("Device {} is being replayed", addr);
+    }
+
+    let mut recorders = RECORDERS.lock();
+    if let Some(recorder) = recorders.get(addr) {
+        return Ok(recorder.path.clone());
+    }
+
+    let dir = crate::logger::rslogs_dir();
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("create session dir: {}", dir.display()))?;
+    let path = dir.join(format!(
+        "{}-{}.session.jsonl",
+        Local::now().format("%Y-%m-%d_%H-%M-%S"),
+        addr.replace(':', "")
+    ));
+
+    let file =
+        File::create(&path).with_context(|| format!("create session file: {}", path.display()))?;
+    let mut recorder = Recorder {
+        path: path.clone(),
+        file: BufWriter::new(file),
+        started: Instant::now(),
+    };
+    recorder.write(&SessionEvent::Header {
+        version: SESSION_FILE_VERSION,
+        name: name.to_string(),
+        addr: addr.to_string(),
+        max_frame_size,
+    })?;
+
+    log::info!("[MiWearReplay] Recording {} to {}", addr, path.display());
+    recorders.insert(addr.to_string(), recorder);
+    Ok(path)
+}
+
+pub fn stop_recording(addr: &str) -> Option<PathBuf> {
+    let recorder = RECORDERS.lock().remove(addr)?;
+    log::info!(
+        "[MiWearReplay] Stopped recording {} ({})",
+        addr,
+        recorder.path.display()
+    );
+    Some(recorder.path)
+}
+
+/// 记录一段链路数据，未在录制时直接返回
+pub fn record(addr: &str, dir: Direction, data: &[u8]) {
+    with_recorder(addr, |recorder| {
+        let t_us = recorder.t_us();
+        let data = to_hex_string(data);
+        match dir {
+            Direction::Rx => SessionEvent::Rx { t_us, data },
+            Direction::Tx => SessionEvent::Tx { t_us, data },
+        }
+    });
+}
+
+fn record_input(addr: &str, input: SessionInput) {
+    with_recorder(addr, |recorder| SessionEvent::Input {
+        t_us: recorder.t_us(),
+        input,
+    });
+}
+
+fn with_recorder(addr: &str, event: impl FnOnce(&Recorder) -> SessionEvent) {
+    let mut recorders = RECORDERS.lock();
+    let Some(recorder) = recorders.get_mut(addr) else {
+        return;
+    };
+    let event = event(recorder);
+    if let Err(e) = recorder.write(&event) {
+        log::error!(
+            "[MiWearReplay] Failed to write session file for {}, stopping: {:#}",
+            addr,
+            e
+        );
+        recorders.remove(addr);
+    }
+}
+
+/// 认证时不经过链路的输入：App 随机数、是否复用了缓存的会话密钥
+///
+/// 只在录制与回放时挂到 [`MiWearDevice::session_inputs`] 上；没有挂载时认证直接
+/// 生成随机数、读取缓存，不会查询录制或回放状态
+pub trait SessionInputs: Send + Sync {
+    /// 认证用的 App 随机数
+    fn auth_nonce(&self) -> Vec<u8>;
+
+    /// 快速重连使用的缓存会话密钥，`load` 为正常读取缓存的逻辑
+    fn cached_session(&self, load: &dyn Fn() -> Option<SecurityKeys>) -> Option<SecurityKeys>;
+}
+
+/// 录制时：照常生成输入，并写进会话文件
+struct RecordedInputs {
+    addr: String,
+}
+
+impl SessionInputs for RecordedInputs {
+    fn auth_nonce(&self) -> Vec<u8> {
+        let nonce = crate::tools::generate_random_bytes(16);
+        record_input(
+            &self.addr,
+            SessionInput::AuthNonce {
+                data: to_hex_string(&nonce),
+            },
+        );
+        nonce
+    }
+
+    fn cached_session(&self, load: &dyn Fn() -> Option<SecurityKeys>) -> Option<SecurityKeys> {
+        let keys = load();
+        record_input(
+            &self.addr,
+            SessionInput::CachedSession {
+                used: keys.is_some(),
+            },
+        );
+        keys
+    }
+}
+
+/// 正在录制的设备返回需挂到设备上的输入源，否则返回 None
+pub fn recording_inputs(addr: &str) -> Option<Arc<dyn SessionInputs>> {
+    is_recording(addr).then(|| {
+        Arc::new(RecordedInputs {
+            addr: addr.to_string(),
+        }) as Arc<dyn SessionInputs>
+    })
+}
+
+/// 回放时：按顺序取出会话文件中录制的输入
+struct ReplayedInputs {
+    addr: String,
+    inputs: Mutex<VecDeque<SessionInput>>,
+    session_keys: Option<SecurityKeys>,
+}
+
+impl ReplayedInputs {
+    /// 取出队列中下一个满足条件的输入
+    fn take<T>(&self, pick: impl Fn(&SessionInput) -> Option<T>) -> Option<T> {
+        let mut inputs = self.inputs.lock();
+        let found = inputs
+            .iter()
+            .position(|i| pick(i).is_some())
+            .and_then(|idx| inputs.remove(idx))
+            .and_then(|i| pick(&i));
+        if found.is_none() {
+            log::warn!(
+                "[MiWearReplay] Session file has no more inputs for {}",
+                self.addr
+            );
+        }
+        found
+    }
+}
+
+impl SessionInputs for ReplayedInputs {
+    fn auth_nonce(&self) -> Vec<u8> {
+        self.take(|input| match input {
+            SessionInput::AuthNonce { data } => hex_stream_to_bytes(data).ok(),
+            _ => None,
+        })
+        .unwrap_or_else(|| crate::tools::generate_random_bytes(16))
+    }
+
+    /// 按录制结果取回放方提供的密钥，不读取本机缓存
+    fn cached_session(&self, _load: &dyn Fn() -> Option<SecurityKeys>) -> Option<SecurityKeys> {
+        let used = self.take(|input| match input {
+            SessionInput::CachedSession { used } => Some(*used),
+            _ => None,
+        });
+        if used != Some(true) {
+            return None;
+        }
+        self.session_keys.clone()
+    }
+}
+
+/* ─── 回放 ─────────────────────────────────────────────────── */
+
+#[derive(Debug, Clone, Copy)]
+pub enum ReplaySpeed {
+    /// 按录制时的间隔注入
+    RealTime,
+    /// 间隔除以倍数
+    Accelerated(f64),
+    /// 不等待，只保证因果顺序
+    Instant,
+}
+
+impl ReplaySpeed {
+    fn scale(self, delay: Duration) -> Duration {
+        match self {
+            Self::RealTime => delay,
+            Self::Accelerated(factor) if factor > 0.0 => delay.div_f64(factor),
+            Self::Accelerated(_) | Self::Instant => Duration::ZERO,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    pub speed: ReplaySpeed,
+    /// 等待 App 写出下一帧的最长时间
+    pub step_timeout: Duration,
+    /// 录制时复用的缓存会话密钥，会话文件不保存密钥
+    pub session_keys: Option<SecurityKeys>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            speed: ReplaySpeed::RealTime,
+            step_timeout: Duration::from_secs(10),
+            session_keys: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    pub rx_injected: usize,
+    pub tx_matched: usize,
+}
+
+enum ReplayStep {
+    Rx { delay: Duration, data: Vec<u8> },
+    ExpectTx(Vec<Vec<u8>>),
+}
+
+/// 在回环链路的设备侧回放会话文件
+pub struct SessionReplayer {
+    addr: String,
+    task: Mutex<Option<JoinHandle<Result<ReplayReport>>>>,
+}
+
+impl SessionReplayer {
+    /// 创建回环链路、启动回放并在其上建立 MiWearDevice
+    pub async fn connect(
+        recording: SessionRecording,
+        options: ReplayOptions,
+    ) -> Result<(Arc<MiWearDevice>, Arc<Self>)> {
+        let steps = recording.steps()?;
+        if recording.uses_cached_session() && options.session_keys.is_none() {
+            bail!(
+                "Session was recorded with cached session keys, pass them in ReplayOptions::session_keys"
+            );
+        }
+        {
+            let mut replaying = REPLAYING.lock();
+            if replaying.contains(&recording.addr) {
+                bail!("Device {} is already being replayed", recording.addr);
+            }
+            replaying.insert(recording.addr.clone());
+        }
+        let inputs = Arc::new(ReplayedInputs {
+            addr: recording.addr.clone(),
+            inputs: Mutex::new(recording.inputs()),
+            session_keys: options.session_keys.clone(),
+        });
+
+        let (transport, peer) = loopback::pair(
+            recording.name.clone(),
+            recording.addr.clone(),
+            recording.max_frame_size,
+        );
+
+        let addr = recording.addr.clone();
+        let task = tokio::spawn({
+            let addr = addr.clone();
+            async move {
+                let ret = run(peer, steps, options).await;
+                REPLAYING.lock().remove(&addr);
+                match &ret {
+                    Ok(report) => log::info!(
+                        "[MiWearReplay] Replay of {} finished: {} rx, {} tx",
+                        addr,
+                        report.rx_injected,
+                        report.tx_matched
+                    ),
+                    Err(e) => log::error!("[MiWearReplay] Replay of {} failed: {:#}", addr, e),
+                }
+                ret
+            }
+        });
+
+        let replayer = Arc::new(Self {
+            addr,
+            task: Mutex::new(Some(task)),
+        });
+
+        match MiWearDevice::connect_with_transport(transport, recording.name).await {
+            Ok(device) => {
+                // 回环链路上还没有开始握手，认证前挂上即可
+                let _ = device.session_inputs.set(inputs);
+                Ok((device, replayer))
+            }
+            Err(e) => {
+                replayer.abort();
+                Err(e)
+            }
+        }
+    }
+
+    /// 等待回放结束，App 写出的帧与录制不一致时返回错误
+    pub async fn finish(&self) -> Result<ReplayReport> {
+        let Some(task) = self.task.lock().take() else {
+            bail!("Replay of {} was already finished", self.addr);
+        };
+        task.await.context("replay task panicked")?
+    }
+
+    pub fn abort(&self) {
+        if let Some(task) = self.task.lock().take() {
+            task.abort();
+        }
+        REPLAYING.lock().remove(&self.addr);
+    }
+}
+
+async fn run(
+    peer: LoopbackPeer,
+    steps: Vec<ReplayStep>,
+    options: ReplayOptions,
+) -> Result<ReplayReport> {
+    let mut report = ReplayReport::default();
+    let mut actual = FrameSplitter::default();
+    let mut received: VecDeque<Vec<u8>> = VecDeque::new();
+
+    for (idx, step) in steps.into_iter().enumerate() {
+        match step {
+            ReplayStep::Rx { delay, data } => {
+                tokio::time::sleep(options.speed.scale(delay)).await;
+                peer.inject(data)
+                    .with_context(|| format!("inject rx at step {}", idx))?;
+                report.rx_injected += 1;
+            }
+            ReplayStep::ExpectTx(mut expected) => {
+                while !expected.is_empty() {
+                    let frame = match received.pop_front() {
+                        Some(frame) => frame,
+                        None => {
+                            let chunk = tokio::time::timeout(options.step_timeout, peer.recv())
+                                .await
+                                .with_context(|| {
+                                    format!(
+                                        "timed out at step {} waiting for {} frame(s), next expected {}",
+                                        idx,
+                                        expected.len(),
+                                        to_hex_string(&expected[0])
+                                    )
+                                })?
+                                .context("link closed before replay finished")?;
+                            received.extend(split_frames(&mut actual, &chunk));
+                            continue;
+                        }
+                    };
+
+                    match expected.iter().position(|e| *e == frame) {
+                        Some(pos) => {
+                            expected.remove(pos);
+                            report.tx_matched += 1;
+                        }
+                        None => bail!(
+                            "tx mismatch at step {}: got {}, expected one of [{}]",
+                            idx,
+                            to_hex_string(&frame),
+                            expected
+                                .iter()
+                                .map(|e| to_hex_string(e))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(frame) = received.front() {
+        bail!("unexpected tx after replay: {}", to_hex_string(frame));
+    }
+    Ok(report)
+}
+
+/// 把链路上的字节流拆成帧；hello 数据没有 MiWear 帧头，整段作为一帧，
+/// 解帧器会丢弃的数据不参与比对
+fn split_frames(splitter: &mut FrameSplitter, data: &[u8]) -> Vec<Vec<u8>> {
+    if data.starts_with(&HELLO_MAGIC) {
+        return vec![data.to_vec()];
+    }
+    splitter.push(data);
+    std::iter::from_fn(|| splitter.next_chunk())
+        .filter_map(|chunk| match chunk {
+            Chunk::Frame { raw, .. } => Some(raw),
+            Chunk::Corrupt { .. } => None,
+        })
+        .collect()
+}
+
+fn decode_hex(data: &str) -> Result<Vec<u8>> {
+    hex_stream_to_bytes(data).map_err(|e| anyhow::anyhow!("invalid hex in session file: {}", e))
+}
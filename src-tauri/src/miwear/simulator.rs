@@ -0,0 +1,633 @@
+use std::{
+    collections::BTreeMap,
+    io::Cursor,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::{bail, Context, Result};
+use byteorder::{LittleEndian, ReadBytesExt};
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use prost::Message;
+use sha2::Sha256;
+use tokio::task::JoinHandle;
+
+use crate::{
+    pb::{self, protocol::PrepareStatus},
+    tools::{hex_stream_to_bytes, to_hex_string},
+};
+
+use super::{
+    device::{
+        auth::{kdf_miwear, string_to_u8_16},
+        mass::packet::{parse_mass_inner, CompressMode},
+        MiWearDevice,
+    },
+    packet::{Channel, FrameReassembler, MiWearPacket, OpCode, PktType},
+    transport::loopback::{self, LoopbackPeer},
+};
+
+pub use super::device::mass::packet::ReceivedMass;
+
+/// 设备对 hello 的回应，App 侧只校验 badcfe 前缀
+const HELLO_REPLY: &str = "badcfe00c00300000100ef";
+
+/// 模拟手表的行为配置，运行中可通过 WatchSimulator::configure 修改
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    pub name: String,
+    pub addr: String,
+    pub authkey: String,
+    pub max_frame_size: usize,
+    pub model: String,
+    pub firmware_version: String,
+    pub serial_number: String,
+    pub imei: String,
+    /// Mass Prepare 回应中的 expected_slice_length
+    pub expected_slice_length: u32,
+    pub watchface_prepare_status: PrepareStatus,
+    pub app_prepare_status: PrepareStatus,
+    pub ota_prepare_status: PrepareStatus,
+    pub mass_prepare_status: PrepareStatus,
+    /// 收完 Mass 数据后是否上报 ReportInstallResult
+    pub report_install_result: bool,
+    /// 在 DeviceVerify 中返回错误的签名，用于模拟 authkey 不匹配
+    pub fail_auth: bool,
+    /// 是否接受 App 提出的 Deflate 压缩
+    pub support_compress: bool,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            name: "AstroBox Simulator".to_string(),
+            addr: "00:00:00:00:00:00".to_string(),
+            authkey: "00112233445566778899aabbccddeeff".to_string(),
+            max_frame_size: 977,
+            model: "simulator".to_string(),
+            firmware_version: "1.0.0".to_string(),
+            serial_number: "SIM0000000".to_string(),
+            imei: String::new(),
+            expected_slice_length: 1024,
+            watchface_prepare_status: PrepareStatus::Ready,
+            app_prepare_status: PrepareStatus::Ready,
+            ota_prepare_status: PrepareStatus::Ready,
+            mass_prepare_status: PrepareStatus::Ready,
+            report_install_result: true,
+            fail_auth: false,
+            support_compress: true,
+        }
+    }
+}
+
+/// 设备视角的会话密钥：enc 用于设备 → App，dec 用于 App → 设备
+#[derive(Clone)]
+struct SessionKeys {
+    enc_key: [u8; 16],
+    dec_key: [u8; 16],
+}
+
+struct MassSession {
+    data_type: u32,
+    md5: Vec<u8>,
+    parts: BTreeMap<u16, Vec<u8>>,
+}
+
+#[derive(Default)]
+struct SessionState {
+    rx_buffer: FrameReassembler,
+    app_random: Option<[u8; 16]>,
+    device_random: Option<[u8; 16]>,
+    pending_keys: Option<SessionKeys>,
+    keys: Option<SessionKeys>,
+    mass: Option<MassSession>,
+}
+
+/// 运行在回环传输层另一端的软件手表
+///
+/// 负责 hello / session config、ACK、设备侧认证，并按配置回应
+/// WatchFace / ThirdpartyApp / System / Mass 请求
+pub struct WatchSimulator {
+    peer: LoopbackPeer,
+    config: Mutex<SimulatorConfig>,
+    session: Mutex<SessionState>,
+    received: Mutex<Vec<ReceivedMass>>,
+    seq: AtomicU8,
+}
+
+impl WatchSimulator {
+    pub fn new(peer: LoopbackPeer, config: SimulatorConfig) -> Arc<Self> {
+        Arc::new(Self {
+            peer,
+            config: Mutex::new(config),
+            session: Mutex::new(SessionState::default()),
+            received: Mutex::new(Vec::new()),
+            seq: AtomicU8::new(0x80),
+        })
+    }
+
+    /// 创建回环链路、启动模拟手表并在其上建立 MiWearDevice
+    pub async fn connect(config: SimulatorConfig) -> Result<(Arc<MiWearDevice>, Arc<Self>)> {
+        let name = config.name.clone();
+        let (transport, peer) = loopback::pair(
+            config.name.clone(),
+            config.addr.clone(),
+            config.max_frame_size,
+        );
+        let sim = Self::new(peer, config);
+        sim.spawn();
+        let device = MiWearDevice::connect_with_transport(transport, name).await?;
+        Ok((device, sim))
+    }
+
+    /// 启动接收循环，链路关闭后自动退出
+    pub fn spawn(self: &Arc<Self>) -> JoinHandle<()> {
+        let sim = self.clone();
+        tokio::spawn(async move {
+            while let Some(data) = sim.peer.recv().await {
+                if let Err(e) = sim.handle_incoming(data) {
+                    log::error!("[WatchSimulator] {:#}", e);
+                }
+            }
+            log::info!("[WatchSimulator] Link closed, simulator stopped");
+        })
+    }
+
+    pub fn configure(&self, f: impl FnOnce(&mut SimulatorConfig)) {
+        f(&mut self.config.lock());
+    }
+
+    pub fn is_authenticated(&self) -> bool {
+        self.session.lock().keys.is_some()
+    }
+
+    pub fn received_files(&self) -> Vec<ReceivedMass> {
+        self.received.lock().clone()
+    }
+
+    /* ───────────── 接收 ───────────── */
+    fn handle_incoming(&self, data: Vec<u8>) -> Result<()> {
+        if data.starts_with(&[0xBA, 0xDC, 0xFE]) {
+            log::info!("[WatchSimulator] Hello received");
+            return self.peer.inject(hex_stream_to_bytes(HELLO_REPLY).unwrap());
+        }
+
+        let packets = {
+            let mut session = self.session.lock();
+            session.rx_buffer.push(&data);
+            std::iter::from_fn(|| session.rx_buffer.next_packet()).collect::<Vec<_>>()
+        };
+
+        for pkt in packets {
+            self.handle_packet(pkt)?;
+        }
+        Ok(())
+    }
+
+    fn handle_packet(&self, pkt: MiWearPacket) -> Result<()> {
+        match pkt.pkt_type {
+            PktType::Ack => {
+                log::debug!("[WatchSimulator] ACK seq={}", pkt.seq);
+                Ok(())
+            }
+            PktType::SessionConfig => {
+                log::info!("[WatchSimulator] SessionConfig received, echoing back");
+                let reply = MiWearPacket {
+                    pkt_type: PktType::SessionConfig,
+                    seq: pkt.seq,
+                    body: pkt.body.clone(),
+                };
+                self.peer.inject(reply.encode())
+            }
+            PktType::Data => {
+                self.peer.inject(
+                    MiWearPacket {
+                        pkt_type: PktType::Ack,
+                        seq: pkt.seq,
+                        body: Vec::new(),
+                    }
+                    .encode(),
+                )?;
+
+                let fields = pkt.data_fields().context("Malformed data frame")?;
+                let mut content = fields.data.to_vec();
+                if fields.opcode == OpCode::Encrypted {
+                    let keys = self
+                        .session
+                        .lock()
+                        .keys
+                        .clone()
+                        .context("Encrypted frame received before auth")?;
+                    content = crate::crypto::aesctr::aes128_ctr_crypt(
+                        &keys.dec_key,
+                        &keys.dec_key,
+                        &content,
+                    );
+                }
+
+                match fields.channel {
+                    Channel::Pb => self.handle_pb(pkt.seq, &content),
+                    Channel::Mass => self.handle_mass(&content),
+                    other => {
+                        log::info!(
+                            "[WatchSimulator] Ignored {:?} data: {}",
+                            other,
+                            to_hex_string(&content)
+                        );
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+
+    /* ───────────── Protobuf ───────────── */
+    fn handle_pb(&self, seq: u8, content: &[u8]) -> Result<()> {
+        use pb::protocol::wear_packet::Type;
+
+        let wp = pb::protocol::WearPacket::decode(Cursor::new(content))
+            .context("Failed to decode WearPacket")?;
+
+        log::info!("[WatchSimulator] Request type={} id={}", wp.r#type, wp.id);
+
+        match Type::try_from(wp.r#type) {
+            Ok(Type::Account) => self.handle_account(seq, wp),
+            Ok(Type::WatchFace) => self.handle_watchface(seq, wp),
+            Ok(Type::ThirdpartyApp) => self.handle_thirdparty_app(seq, wp),
+            Ok(Type::System) => self.handle_system(seq, wp),
+            Ok(Type::Mass) => self.handle_mass_prepare(seq, wp),
+            _ => {
+                log::warn!("[WatchSimulator] Unhandled WearPacket type {}", wp.r#type);
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_account(&self, seq: u8, wp: pb::protocol::WearPacket) -> Result<()> {
+        use pb::protocol::account::{AccountId, Payload};
+
+        let account = match wp.payload {
+            Some(pb::protocol::wear_packet::Payload::Account(account)) => account,
+            _ => bail!("Account payload missing"),
+        };
+
+        match account.payload {
+            Some(Payload::AuthAppVerify(verify)) => {
+                let config = self.config.lock().clone();
+                let app_random: [u8; 16] = verify
+                    .app_random
+                    .as_slice()
+                    .try_into()
+                    .context("App random must be 16 bytes")?;
+                let device_random: [u8; 16] =
+                    crate::tools::generate_random_bytes(16).try_into().unwrap();
+                let authkey =
+                    string_to_u8_16(&config.authkey).context("Simulator authkey is invalid")?;
+
+                let block = kdf_miwear(&authkey, &app_random, &device_random);
+                let keys = SessionKeys {
+                    enc_key: block[0..16].try_into().unwrap(),
+                    dec_key: block[16..32].try_into().unwrap(),
+                };
+
+                let mut device_sign =
+                    hmac_sha256(&keys.enc_key, &[&device_random[..], &app_random[..]]);
+                if config.fail_auth {
+                    device_sign[0] ^= 0xFF;
+                }
+
+                {
+                    let mut session = self.session.lock();
+                    session.app_random = Some(app_random);
+                    session.device_random = Some(device_random);
+                    session.pending_keys = Some(keys);
+                    session.keys = None;
+                }
+
+                let reply = account_packet(
+                    AccountId::AuthVerify,
+                    Payload::AuthDeviceVerify(pb::protocol::auth::DeviceVerify {
+                        device_random: device_random.to_vec(),
+                        device_sign,
+                        ..Default::default()
+                    }),
+                );
+                self.reply_pb(seq, OpCode::Plain, reply)
+            }
+            Some(Payload::AuthAppConfirm(confirm)) => {
+                let (app_random, device_random, keys) = {
+                    let session = self.session.lock();
+                    (
+                        session.app_random.context("AppConfirm before AppVerify")?,
+                        session
+                            .device_random
+                            .context("AppConfirm before AppVerify")?,
+                        session
+                            .pending_keys
+                            .clone()
+                            .context("AppConfirm before AppVerify")?,
+                    )
+                };
+
+                let expect = hmac_sha256(&keys.dec_key, &[&app_random[..], &device_random[..]]);
+                if confirm.app_sign != expect {
+                    bail!("AppConfirm signature mismatch");
+                }
+
+                self.session.lock().keys = Some(keys);
+                log::info!("[WatchSimulator] Auth finished");
+
+                let reply = account_packet(
+                    AccountId::AuthConfirm,
+                    Payload::AuthDeviceConfirm(Default::default()),
+                );
+                self.reply_pb(seq, OpCode::Plain, reply)
+            }
+            _ => {
+                log::warn!("[WatchSimulator] Unhandled Account request id={}", wp.id);
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_watchface(&self, seq: u8, wp: pb::protocol::WearPacket) -> Result<()> {
+        use pb::protocol::watch_face::{Payload, WatchFaceId};
+
+        let payload = match WatchFaceId::try_from(wp.id as i32) {
+            Ok(WatchFaceId::PrepareInstallWatchFace) => {
+                Payload::PrepareStatus(self.config.lock().watchface_prepare_status as i32)
+            }
+            Ok(WatchFaceId::GetInstalledList) => Payload::WatchFaceList(Default::default()),
+            _ => {
+                log::info!(
+                    "[WatchSimulator] WatchFace request id={} needs no reply",
+                    wp.id
+                );
+                return Ok(());
+            }
+        };
+
+        self.reply_pb(
+            seq,
+            OpCode::Encrypted,
+            pb::protocol::WearPacket {
+                r#type: pb::protocol::wear_packet::Type::WatchFace as i32,
+                id: wp.id,
+                payload: Some(pb::protocol::wear_packet::Payload::WatchFace(
+                    pb::protocol::WatchFace {
+                        payload: Some(payload),
+                    },
+                )),
+            },
+        )
+    }
+
+    fn handle_thirdparty_app(&self, seq: u8, wp: pb::protocol::WearPacket) -> Result<()> {
+        use pb::protocol::thirdparty_app::{Payload, ThirdpartyAppId};
+
+        let payload = match ThirdpartyAppId::try_from(wp.id as i32) {
+            Ok(ThirdpartyAppId::PrepareInstallApp) => {
+                Payload::InstallResponse(pb::protocol::app_installer::Response {
+                    prepare_status: self.config.lock().app_prepare_status as i32,
+                    ..Default::default()
+                })
+            }
+            Ok(ThirdpartyAppId::GetInstalledList) => Payload::AppItemList(Default::default()),
+            _ => {
+                log::info!(
+                    "[WatchSimulator] ThirdpartyApp request id={} needs no reply",
+                    wp.id
+                );
+                return Ok(());
+            }
+        };
+
+        self.reply_pb(
+            seq,
+            OpCode::Encrypted,
+            pb::protocol::WearPacket {
+                r#type: pb::protocol::wear_packet::Type::ThirdpartyApp as i32,
+                id: wp.id,
+                payload: Some(pb::protocol::wear_packet::Payload::ThirdpartyApp(
+                    pb::protocol::ThirdpartyApp {
+                        payload: Some(payload),
+                    },
+                )),
+            },
+        )
+    }
+
+    fn handle_system(&self, seq: u8, wp: pb::protocol::WearPacket) -> Result<()> {
+        use pb::protocol::system::{Payload, SystemId};
+
+        let config = self.config.lock().clone();
+        let payload = match SystemId::try_from(wp.id as i32) {
+            Ok(SystemId::GetDeviceInfo) => Payload::DeviceInfo(pb::protocol::DeviceInfo {
+                serial_number: config.serial_number,
+                firmware_version: config.firmware_version,
+                imei: config.imei,
+                model: config.model,
+                ..Default::default()
+            }),
+            Ok(SystemId::GetDeviceStatus) => Payload::DeviceStatus(Default::default()),
+            Ok(SystemId::PrepareOta) => {
+                Payload::PrepareOtaResponse(pb::protocol::prepare_ota::Response {
+                    prepare_status: config.ota_prepare_status as i32,
+                    ..Default::default()
+                })
+            }
+            _ => {
+                log::info!(
+                    "[WatchSimulator] System request id={} needs no reply",
+                    wp.id
+                );
+                return Ok(());
+            }
+        };
+
+        self.reply_pb(
+            seq,
+            OpCode::Encrypted,
+            pb::protocol::WearPacket {
+                r#type: pb::protocol::wear_packet::Type::System as i32,
+                id: wp.id,
+                payload: Some(pb::protocol::wear_packet::Payload::System(
+                    pb::protocol::System {
+                        payload: Some(payload),
+                    },
+                )),
+            },
+        )
+    }
+
+    /* ───────────── Mass ───────────── */
+    fn handle_mass_prepare(&self, seq: u8, wp: pb::protocol::WearPacket) -> Result<()> {
+        let request = match wp.payload {
+            Some(pb::protocol::wear_packet::Payload::Mass(pb::protocol::Mass {
+                payload: Some(pb::protocol::mass::Payload::PrepareRequest(req)),
+            })) => req,
+            _ => bail!("Mass prepare request missing"),
+        };
+
+        let config = self.config.lock().clone();
+        let select_compress_mode = if config.support_compress
+            && request.support_compress_mode() == CompressMode::Deflate as u32
+        {
+            CompressMode::Deflate as u32
+        } else {
+            CompressMode::None as u32
+        };
+
+        if config.mass_prepare_status == PrepareStatus::Ready {
+            let mut session = self.session.lock();
+            // 同一文件再次 prepare 视为断点续传，保留已收到的分片
+            let resumed = session
+                .mass
+                .as_ref()
+                .is_some_and(|mass| mass.md5 == request.data_id);
+            if !resumed {
+                session.mass = Some(MassSession {
+                    data_type: request.data_type,
+                    md5: request.data_id.clone(),
+                    parts: BTreeMap::new(),
+                });
+            }
+        }
+
+        self.reply_pb(
+            seq,
+            OpCode::Encrypted,
+            pb::protocol::WearPacket {
+                r#type: pb::protocol::wear_packet::Type::Mass as i32,
+                id: pb::protocol::mass::MassId::Prepare as u32,
+                payload: Some(pb::protocol::wear_packet::Payload::Mass(
+                    pb::protocol::Mass {
+                        payload: Some(pb::protocol::mass::Payload::PrepareResponse(
+                            pb::protocol::PrepareResponse {
+                                prepare_status: config.mass_prepare_status as i32,
+                                expected_slice_length: Some(config.expected_slice_length),
+                                select_compress_mode: Some(select_compress_mode),
+                                ..Default::default()
+                            },
+                        )),
+                    },
+                )),
+            },
+        )
+    }
+
+    fn handle_mass(&self, content: &[u8]) -> Result<()> {
+        let mut rdr = Cursor::new(content);
+        let total = rdr
+            .read_u16::<LittleEndian>()
+            .context("Mass part too short")?;
+        let current = rdr
+            .read_u16::<LittleEndian>()
+            .context("Mass part too short")?;
+        let fragment = content[4..].to_vec();
+
+        let finished = {
+            let mut session = self.session.lock();
+            let mass = session
+                .mass
+                .as_mut()
+                .context("Mass data received without prepare")?;
+            mass.parts.insert(current, fragment);
+            if mass.parts.len() as u16 == total {
+                session.mass.take()
+            } else {
+                None
+            }
+        };
+
+        let Some(mass) = finished else {
+            return Ok(());
+        };
+
+        let inner: Vec<u8> = mass.parts.into_values().flatten().collect();
+        let received = parse_mass_inner(&inner, Some(&mass.md5))?;
+        log::info!(
+            "[WatchSimulator] Mass transfer finished: type={} len={} crc_ok={} md5_ok={}",
+            received.data_type,
+            received.data.len(),
+            received.crc_ok,
+            received.md5_ok
+        );
+        self.received.lock().push(received);
+
+        if self.config.lock().report_install_result {
+            self.report_install_result(mass.data_type)?;
+        }
+        Ok(())
+    }
+
+    fn report_install_result(&self, data_type: u32) -> Result<()> {
+        use super::device::mass::packet::MassDataType;
+
+        let (packet_type, id) = match data_type {
+            t if t == MassDataType::WATCHFACE as u32 => (
+                pb::protocol::wear_packet::Type::WatchFace,
+                pb::protocol::watch_face::WatchFaceId::ReportInstallResult as u32,
+            ),
+            t if t == MassDataType::ThirdpartyApp as u32 => (
+                pb::protocol::wear_packet::Type::ThirdpartyApp,
+                pb::protocol::thirdparty_app::ThirdpartyAppId::ReportInstallResult as u32,
+            ),
+            _ => return Ok(()),
+        };
+
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        self.reply_pb(
+            seq,
+            OpCode::Encrypted,
+            pb::protocol::WearPacket {
+                r#type: packet_type as i32,
+                id,
+                payload: None,
+            },
+        )
+    }
+
+    /* ───────────── 发送 ───────────── */
+    fn reply_pb(&self, seq: u8, op: OpCode, wp: pb::protocol::WearPacket) -> Result<()> {
+        let mut payload = wp.encode_to_vec();
+        if op == OpCode::Encrypted {
+            let keys = self
+                .session
+                .lock()
+                .keys
+                .clone()
+                .context("Encrypted reply before auth")?;
+            payload =
+                crate::crypto::aesctr::aes128_ctr_crypt(&keys.enc_key, &keys.enc_key, &payload);
+        }
+        let frame = MiWearPacket::new_data(seq, Channel::Pb, op, &payload).encode();
+        self.peer.inject(frame)
+    }
+}
+
+fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn account_packet(
+    id: pb::protocol::account::AccountId,
+    payload: pb::protocol::account::Payload,
+) -> pb::protocol::WearPacket {
+    pb::protocol::WearPacket {
+        r#type: pb::protocol::wear_packet::Type::Account as i32,
+        id: id as u32,
+        payload: Some(pb::protocol::wear_packet::Payload::Account(
+            pb::protocol::Account {
+                payload: Some(payload),
+            },
+        )),
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::bt::device::{BTDevice, Connection};
+
+use super::device::MiWearBleCharaUuid;
+
+pub mod ble;
+pub mod loopback;
+pub mod spp;
+
+/// 底层链路上行数据回调：Ok 为收到的原始字节，Err 为链路错误
+pub type TransportCallback = Arc<dyn Fn(Result<Vec<u8>, String>) + Send + Sync>;
+
+/// MiWear 协议的传输层抽象
+///
+/// MiWearDevice 只通过该 trait 收发原始字节，不关心底层是 BLE、SPP 还是进程内回环
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// 链路上的设备名（可能为空）
+    fn name(&self) -> String;
+
+    /// 链路上的设备地址
+    fn address(&self) -> String;
+
+    /// 单次写入允许的最大字节数，超出部分由 MiWearDevice 负责分片
+    fn max_frame_size(&self) -> usize;
+
+    /// BLE 特征值信息，非 BLE 链路返回 nil uuid
+    fn ble_charas(&self) -> MiWearBleCharaUuid {
+        MiWearBleCharaUuid::default()
+    }
+
+    async fn send(&self, data: Vec<u8>) -> anyhow::Result<()>;
+
+    async fn subscribe(&self, cb: TransportCallback) -> anyhow::Result<()>;
+
+    async fn disconnect(&self) -> anyhow::Result<()>;
+}
+
+/// 根据 BTDevice 的连接类型构造对应的传输层
+pub fn from_bt_device(device: BTDevice) -> Arc<dyn Transport> {
+    match &device.handle {
+        Connection::BLE(_) => Arc::new(ble::BleTransport::new(device)),
+        Connection::SPP(_) => Arc::new(spp::SppTransport::new(device)),
+    }
+}
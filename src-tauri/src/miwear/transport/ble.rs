@@ -0,0 +1,93 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::{
+    bt::device::{BTDevice, Connection},
+    miwear::{
+        bleuuids::{BLE_UUID_KEYWORD_XIAOMI_RECV, BLE_UUID_KEYWORD_XIAOMI_SENT},
+        device::MiWearBleCharaUuid,
+    },
+    tools::uuid_contains,
+};
+
+use super::{Transport, TransportCallback};
+
+/// BLE 单帧最大长度
+pub const BLE_MAX_FRAME_SIZE: usize = 244;
+
+pub struct BleTransport {
+    btdevice: BTDevice,
+    charas: MiWearBleCharaUuid,
+}
+
+impl BleTransport {
+    pub fn new(btdevice: BTDevice) -> Self {
+        let charas = find_miwear_charas(&btdevice);
+        Self { btdevice, charas }
+    }
+}
+
+/// 从 BLE 服务列表中找出小米 fe95 服务下的收发特征值
+fn find_miwear_charas(device: &BTDevice) -> MiWearBleCharaUuid {
+    let mut charas = MiWearBleCharaUuid {
+        recv: Uuid::nil(),
+        sent: Uuid::nil(),
+    };
+    if let Some(services) = device.ble_services.as_ref() {
+        for service in services {
+            if !uuid_contains(&service.uuid, "fe95", true) {
+                continue;
+            }
+            for chara in &service.characteristics {
+                if uuid_contains(&chara.uuid, BLE_UUID_KEYWORD_XIAOMI_RECV, false) {
+                    charas.recv = chara.uuid;
+                }
+                if uuid_contains(&chara.uuid, BLE_UUID_KEYWORD_XIAOMI_SENT, false) {
+                    charas.sent = chara.uuid;
+                }
+            }
+        }
+    }
+    charas
+}
+
+#[async_trait]
+impl Transport for BleTransport {
+    fn name(&self) -> String {
+        match &self.btdevice.handle {
+            Connection::BLE(ble) => ble.name.clone(),
+            Connection::SPP(spp) => spp.name.clone().unwrap_or_default(),
+        }
+    }
+
+    fn address(&self) -> String {
+        match &self.btdevice.handle {
+            Connection::BLE(ble) => ble.address.clone(),
+            Connection::SPP(spp) => spp.address.clone(),
+        }
+    }
+
+    fn max_frame_size(&self) -> usize {
+        BLE_MAX_FRAME_SIZE
+    }
+
+    fn ble_charas(&self) -> MiWearBleCharaUuid {
+        self.charas.clone()
+    }
+
+    async fn send(&self, data: Vec<u8>) -> Result<()> {
+        if self.charas.sent.is_nil() {
+            bail!("MiWear sent characteristic not found");
+        }
+        self.btdevice.send(data, Some(self.charas.sent)).await
+    }
+
+    async fn subscribe(&self, cb: TransportCallback) -> Result<()> {
+        self.btdevice.subscribe(cb, Some(self.charas.recv)).await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        self.btdevice.disconnect().await
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+
+use super::{Transport, TransportCallback};
+
+/// 进程内回环传输层
+///
+/// MiWearDevice 写入的数据可以通过配对的 LoopbackPeer 读出，
+/// LoopbackPeer 注入的数据会投递给 MiWearDevice 的订阅回调，
+/// 用于在没有蓝牙硬件的环境下驱动整个协议栈
+pub struct LoopbackTransport {
+    name: String,
+    address: String,
+    max_frame_size: usize,
+    connected: Arc<AtomicBool>,
+    outgoing: mpsc::UnboundedSender<Vec<u8>>,
+    callback: Arc<Mutex<Option<TransportCallback>>>,
+}
+
+/// 回环链路的另一端（模拟设备侧）
+pub struct LoopbackPeer {
+    connected: Arc<AtomicBool>,
+    incoming: tokio::sync::Mutex<mpsc::UnboundedReceiver<Vec<u8>>>,
+    callback: Arc<Mutex<Option<TransportCallback>>>,
+}
+
+/// 创建一对互相连通的回环端点
+pub fn pair(
+    name: impl Into<String>,
+    address: impl Into<String>,
+    max_frame_size: usize,
+) -> (Arc<LoopbackTransport>, LoopbackPeer) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let connected = Arc::new(AtomicBool::new(true));
+    let callback = Arc::new(Mutex::new(None));
+
+    let transport = Arc::new(LoopbackTransport {
+        name: name.into(),
+        address: address.into(),
+        max_frame_size,
+        connected: connected.clone(),
+        outgoing: tx,
+        callback: callback.clone(),
+    });
+
+    let peer = LoopbackPeer {
+        connected,
+        incoming: tokio::sync::Mutex::new(rx),
+        callback,
+    };
+
+    (transport, peer)
+}
+
+#[async_trait]
+impl Transport for LoopbackTransport {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    async fn send(&self, data: Vec<u8>) -> Result<()> {
+        if !self.connected.load(Ordering::Acquire) {
+            bail!("Loopback transport is disconnected");
+        }
+        if data.len() > self.max_frame_size {
+            bail!(
+                "Loopback write of {} bytes exceeds max frame size {}",
+                data.len(),
+                self.max_frame_size
+            );
+        }
+        self.outgoing
+            .send(data)
+            .map_err(|_| anyhow::anyhow!("Loopback peer dropped"))
+    }
+
+    async fn subscribe(&self, cb: TransportCallback) -> Result<()> {
+        *self.callback.lock() = Some(cb);
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        self.connected.store(false, Ordering::Release);
+        self.callback.lock().take();
+        Ok(())
+    }
+}
+
+impl LoopbackPeer {
+    /// 读取 MiWearDevice 写入链路的下一段数据，链路关闭后返回 None
+    pub async fn recv(&self) -> Option<Vec<u8>> {
+        self.incoming.lock().await.recv().await
+    }
+
+    /// 非阻塞读取，没有待读数据时返回 None
+    pub async fn try_recv(&self) -> Option<Vec<u8>> {
+        self.incoming.lock().await.try_recv().ok()
+    }
+
+    /// 向 MiWearDevice 投递一段上行数据
+    pub fn inject(&self, data: Vec<u8>) -> Result<()> {
+        if !self.connected.load(Ordering::Acquire) {
+            bail!("Loopback transport is disconnected");
+        }
+        let cb = self.callback.lock().clone();
+        match cb {
+            Some(cb) => {
+                cb(Ok(data));
+                Ok(())
+            }
+            None => bail!("No subscriber on loopback transport"),
+        }
+    }
+
+    /// 模拟链路错误（例如蓝牙断开）
+    pub fn fail(&self, err: impl Into<String>) {
+        let cb = self.callback.lock().clone();
+        if let Some(cb) = cb {
+            cb(Err(err.into()));
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::bt::device::{BTDevice, Connection};
+
+use super::{Transport, TransportCallback};
+
+/// SPP 单帧最大长度：1004(max size) - 27(bluetooth packet header)
+pub const SPP_MAX_FRAME_SIZE: usize = 977;
+
+pub struct SppTransport {
+    btdevice: BTDevice,
+}
+
+impl SppTransport {
+    pub fn new(btdevice: BTDevice) -> Self {
+        Self { btdevice }
+    }
+}
+
+#[async_trait]
+impl Transport for SppTransport {
+    fn name(&self) -> String {
+        match &self.btdevice.handle {
+            Connection::BLE(ble) => ble.name.clone(),
+            Connection::SPP(spp) => spp.name.clone().unwrap_or_default(),
+        }
+    }
+
+    fn address(&self) -> String {
+        match &self.btdevice.handle {
+            Connection::BLE(ble) => ble.address.clone(),
+            Connection::SPP(spp) => spp.address.clone(),
+        }
+    }
+
+    fn max_frame_size(&self) -> usize {
+        SPP_MAX_FRAME_SIZE
+    }
+
+    async fn send(&self, data: Vec<u8>) -> Result<()> {
+        self.btdevice.send(data, None).await
+    }
+
+    async fn subscribe(&self, cb: TransportCallback) -> Result<()> {
+        self.btdevice.subscribe(cb, None).await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        self.btdevice.disconnect().await
+    }
+}
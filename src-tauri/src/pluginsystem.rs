@@ -141,3 +141,45 @@ where
 
     rx.await.map_err(|_| anyhow!("Plugin thread dropped the response"))
 }
+
+/// 把事件负载序列化为 JSON 字符串，投递给所有监听了 `event` 的插件
+///
+/// 在插件线程上异步执行，不等待监听函数返回
+pub fn notify_plugins<T: serde::Serialize>(event: &'static str, payload: &T) {
+    let payload = match serde_json::to_string(payload) {
+        Ok(payload) => payload,
+        Err(e) => {
+            log::error!(
+                "[PluginSystem] Failed to serialize {} payload: {}",
+                event,
+                e
+            );
+            return;
+        }
+    };
+
+    tauri::async_runtime::spawn(async move {
+        let ret = with_plugin_manager_async(move |pm| {
+            for (name, plug) in pm.plugins.iter_mut() {
+                let Some(listener) = plug.js_env_data.event_listeners.get(event).cloned() else {
+                    continue;
+                };
+
+                if let Err(e) = listener.call(
+                    &JsValue::undefined(),
+                    &[JsValue::String(boa_engine::JsString::from(
+                        payload.as_str(),
+                    ))],
+                    &mut plug.js_context,
+                ) {
+                    log::error!("[PluginSystem] {} listener error in {}: {}", event, name, e);
+                }
+                plug.js_context.run_jobs();
+            }
+        })
+        .await;
+        if let Err(e) = ret {
+            log::debug!("[PluginSystem] {} not delivered: {}", event, e);
+        }
+    });
+}
@@ -15,6 +15,9 @@ pub mod provider;
 pub mod debug;
 pub mod thirdpartyapp;
 pub mod filesystem;
+pub mod channel;
+pub mod download;
+pub mod health;
 
 pub fn register_apis(context: &mut Context) -> Result<(), String> {
     let mut initializer = ObjectInitializer::new(context);
@@ -33,6 +36,9 @@ pub fn register_apis(context: &mut Context) -> Result<(), String> {
     debug::register_debug(&mut initializer)?;
     thirdpartyapp::register_thirdpartyapp(&mut initializer)?;
     filesystem::register_filesystem(&mut initializer)?;
+    channel::register_channel(&mut initializer)?;
+    download::register_download(&mut initializer)?;
+    health::register_health(&mut initializer)?;
 
     let jsobj = initializer.build();
 
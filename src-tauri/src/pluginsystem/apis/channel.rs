@@ -0,0 +1,161 @@
+use base64::{engine::general_purpose, Engine};
+use boa_engine::{
+    js_error, js_string, object::ObjectInitializer, property::Attribute, Context, JsResult,
+    JsString, JsValue, NativeFunction,
+};
+use std::sync::Arc;
+
+use crate::{
+    miwear::{
+        channels::{self, ChannelPayload, PayloadKind},
+        packet::Channel,
+    },
+    pluginsystem::utils::{get_plugin_name, plugin_permission_check},
+};
+
+pub static CHANNEL_PERMISSION: &str = "channel";
+
+fn parse_args(
+    args: &[JsValue],
+    reassembled_index: usize,
+    ctx: &mut Context,
+) -> JsResult<(Channel, PayloadKind)> {
+    let name = args
+        .first()
+        .ok_or_else(|| js_error!("args[0] channel missing"))?
+        .to_string(ctx)
+        .map_err(|e| js_error!("{}", e))?
+        .to_std_string_lossy();
+    let channel =
+        Channel::parse_name(&name).ok_or_else(|| js_error!("Unknown channel: {}", name))?;
+    let kind = if args.get(reassembled_index).is_some_and(|v| v.to_boolean()) {
+        PayloadKind::Reassembled
+    } else {
+        PayloadKind::Raw
+    };
+    Ok((channel, kind))
+}
+
+/// 回调参数为 JSON 字符串：{ addr, channel, kind, data(base64) }
+fn forward_to_plugin(
+    plugin_name: String,
+    channel: Channel,
+    kind: PayloadKind,
+) -> channels::ChannelHandler {
+    Arc::new(move |payload: &ChannelPayload| {
+        let json = serde_json::json!({
+            "addr": payload.addr,
+            "channel": payload.channel,
+            "kind": payload.kind,
+            "data": general_purpose::STANDARD.encode(&payload.data),
+        })
+        .to_string();
+        let plugin_name = plugin_name.clone();
+
+        tauri::async_runtime::spawn(async move {
+            crate::pluginsystem::with_plugin_manager_async(move |pm| {
+                let Some(plug) = pm.plugins.get_mut(&plugin_name) else {
+                    return;
+                };
+                let Some((listener, _)) = plug
+                    .js_env_data
+                    .channel_listeners
+                    .get(&(channel, kind))
+                    .cloned()
+                else {
+                    return;
+                };
+
+                if let Err(e) = listener.call(
+                    &JsValue::undefined(),
+                    &[JsValue::String(JsString::from(json.as_str()))],
+                    &mut plug.js_context,
+                ) {
+                    log::error!("[PluginChannel] {:?} listener error: {}", channel, e);
+                }
+                plug.js_context.run_jobs();
+            })
+            .await
+            .ok();
+        });
+    })
+}
+
+/// subscribe(channel, callback, reassembled?)，同一通道同一种负载只保留最后一个回调
+pub fn subscribe(_this: &JsValue, args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
+    if let Some(err) = plugin_permission_check(ctx, CHANNEL_PERMISSION.to_string()) {
+        return Err(err);
+    }
+
+    let (channel, kind) = parse_args(args, 2, ctx)?;
+    let callback = args
+        .get(1)
+        .and_then(|v| v.as_function())
+        .ok_or_else(|| js_error!("subscribe args[1] expected a function"))?
+        .clone();
+
+    let plugin_name = get_plugin_name(ctx);
+    let id = channels::subscribe(
+        channel,
+        kind,
+        forward_to_plugin(plugin_name.clone(), channel, kind),
+    );
+
+    crate::pluginsystem::with_plugin_manager_sync(|pm| {
+        pm.set_plugin_data(&plugin_name, |data| {
+            if let Some((_, old)) = data
+                .channel_listeners
+                .insert((channel, kind), (callback, id))
+            {
+                channels::unsubscribe(old);
+            }
+        })
+        .unwrap_or_else(|e| {
+            channels::unsubscribe(id);
+            log::error!("{}", e)
+        });
+    })
+    .unwrap_or_else(|e| log::error!("{}", e));
+
+    Ok(JsValue::undefined())
+}
+
+/// unsubscribe(channel, reassembled?)
+pub fn unsubscribe(_this: &JsValue, args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
+    if let Some(err) = plugin_permission_check(ctx, CHANNEL_PERMISSION.to_string()) {
+        return Err(err);
+    }
+
+    let (channel, kind) = parse_args(args, 1, ctx)?;
+
+    crate::pluginsystem::with_plugin_manager_sync(|pm| {
+        pm.set_plugin_data(&get_plugin_name(ctx), |data| {
+            if let Some((_, id)) = data.channel_listeners.remove(&(channel, kind)) {
+                channels::unsubscribe(id);
+            }
+        })
+        .unwrap_or_else(|e| log::error!("{}", e));
+    })
+    .unwrap_or_else(|e| log::error!("{}", e));
+
+    Ok(JsValue::undefined())
+}
+
+pub fn register_channel(global: &mut ObjectInitializer) -> Result<(), String> {
+    let jsobj = ObjectInitializer::new(global.context())
+        .function(
+            NativeFunction::from_fn_ptr(subscribe),
+            js_string!("subscribe"),
+            3,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(unsubscribe),
+            js_string!("unsubscribe"),
+            2,
+        )
+        .build();
+
+    global.property(js_string!("channel"), jsobj, Attribute::READONLY);
+
+    Ok(())
+}
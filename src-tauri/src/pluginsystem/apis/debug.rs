@@ -3,7 +3,7 @@ use std::future::Future;
 use base64::{engine::general_purpose, Engine};
 use boa_engine::{js_error, js_string, object::ObjectInitializer, property::Attribute, Context, JsResult, JsValue, NativeFunction, JsError};
 
-use crate::pluginsystem::utils::plugin_permission_check;
+use crate::pluginsystem::utils::{optional_device_addr, plugin_permission_check};
 
 pub static DEBUG_PERMISSION: &str = "debug";
 
@@ -12,7 +12,7 @@ pub fn send_raw(
     args: &[JsValue],
     ctx: &mut Context,
 ) -> impl Future<Output = JsResult<JsValue>> {
-    let parsed: Result<Vec<u8>, JsError> = (|| {
+    let parsed: Result<(Vec<u8>, Option<String>), JsError> = (|| {
         let raw_b64 = args
             .get(0)
             .ok_or_else(|| js_error!("sendRaw args[0] is missing"))?
@@ -22,7 +22,8 @@ pub fn send_raw(
         let payload = general_purpose::STANDARD
             .decode(raw_b64)
             .map_err(|_| js_error!("sendRaw args[0] data is invalid"))?;
-        Ok(payload)
+        let addr = optional_device_addr(args, 1, ctx)?;
+        Ok((payload, addr))
     })();
 
     let permission_result = plugin_permission_check(ctx, DEBUG_PERMISSION.to_string());
@@ -33,8 +34,8 @@ pub fn send_raw(
             return Err(err);
         }
 
-        let payload = parsed?;
-        crate::miwear::with_connected_device_async(|dev| async move {
+        let (payload, addr) = parsed?;
+        crate::miwear::with_device_async(addr.as_deref(), |dev| async move {
             dev.send(payload).await?;
             anyhow::Ok(())
         })
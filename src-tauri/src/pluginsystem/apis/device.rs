@@ -1,4 +1,4 @@
-use crate::{miwear::device::MiWearState, pluginsystem::{apis::models::GetDeviceListReturn, utils::plugin_permission_check}};
+use crate::{miwear::device::MiWearState, pluginsystem::{apis::models::GetDeviceListReturn, utils::{optional_device_addr, plugin_permission_check}}};
 use boa_engine::{js_error, js_string, object::ObjectInitializer, property::Attribute, Context, JsResult, JsString, JsValue, NativeFunction};
 use std::{future::Future, str::FromStr};
 
@@ -97,11 +97,12 @@ pub fn modify_device_state(
 
 pub fn disconnect_device(
     _this: &JsValue,
-    _args: &[JsValue],
+    args: &[JsValue],
     _ctx: &mut Context,
 ) -> impl Future<Output = JsResult<JsValue>> {
 
     let permission_result = plugin_permission_check(_ctx, DEVICE_PERMISSION.to_string());
+    let addr_res = optional_device_addr(args, 0, _ctx);
     
     async move {
 
@@ -109,7 +110,8 @@ pub fn disconnect_device(
             return Err(err);
         }
 
-        crate::miwear::with_connected_device_async(|dev| async move {
+        let addr = addr_res?;
+        crate::miwear::with_device_async(addr.as_deref(), |dev| async move {
             dev.disconnect().await?;
             anyhow::Ok(())
         })
@@ -119,6 +121,56 @@ pub fn disconnect_device(
     }
 }
 
+pub fn get_connected_device_list(
+    _this: &JsValue,
+    _args: &[JsValue],
+    _ctx: &mut Context,
+) -> impl Future<Output = JsResult<JsValue>> {
+
+    let permission_result = plugin_permission_check(_ctx, DEVICE_PERMISSION.to_string());
+
+    async move {
+
+        if let Some(err) = permission_result {
+            return Err(err);
+        }
+
+        let devices = crate::miwear::connected_device_states().await;
+        let list_json = serde_json::to_string(&devices).map_err(|e| js_error!("{}", e))?;
+        Ok(JsValue::String(
+            JsString::from_str(&list_json).map_err(|e| js_error!("{}", e))?,
+        ))
+    }
+}
+
+pub fn get_connection_state(
+    _this: &JsValue,
+    args: &[JsValue],
+    _ctx: &mut Context,
+) -> impl Future<Output = JsResult<JsValue>> {
+
+    let permission_result = plugin_permission_check(_ctx, DEVICE_PERMISSION.to_string());
+    let addr_res = optional_device_addr(args, 0, _ctx);
+
+    async move {
+
+        if let Some(err) = permission_result {
+            return Err(err);
+        }
+
+        let addr = match addr_res? {
+            Some(addr) => addr,
+            None => crate::miwear::DEVICES
+                .primary_addr()
+                .await
+                .ok_or_else(|| js_error!("getConnectionState: no device is connected"))?,
+        };
+        Ok(JsValue::String(JsString::from(
+            crate::miwear::connection::current(&addr).as_str(),
+        )))
+    }
+}
+
 pub fn register_device(
     global: &mut ObjectInitializer,
 ) -> Result<(), String> {
@@ -126,7 +178,9 @@ pub fn register_device(
         .function(NativeFunction::from_fn_ptr(get_device_list), js_string!("getDeviceList"), 0)
         .function(NativeFunction::from_fn_ptr(get_device_state), js_string!("getDeviceState"), 1)
         .function(NativeFunction::from_fn_ptr(modify_device_state), js_string!("modifyDeviceState"), 2)
-        .function(NativeFunction::from_async_fn(disconnect_device), js_string!("disconnectDevice"), 0)
+        .function(NativeFunction::from_async_fn(disconnect_device), js_string!("disconnectDevice"), 1)
+        .function(NativeFunction::from_async_fn(get_connected_device_list), js_string!("getConnectedDeviceList"), 0)
+        .function(NativeFunction::from_async_fn(get_connection_state), js_string!("getConnectionState"), 1)
         .build();
 
     global.property(js_string!("device"), jsobj, Attribute::READONLY);
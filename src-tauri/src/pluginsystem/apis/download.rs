@@ -0,0 +1,65 @@
+use base64::{engine::general_purpose, Engine};
+use boa_engine::{
+    js_error, js_string, object::ObjectInitializer, property::Attribute, Context, JsResult,
+    JsString, JsValue, NativeFunction,
+};
+
+use crate::{miwear::device::download, pluginsystem::utils::plugin_permission_check};
+
+/// 读取从设备下载的文件；新文件到达时触发 `onFileDownloaded` 事件
+pub static DOWNLOAD_PERMISSION: &str = "download";
+
+fn id_arg(args: &[JsValue], ctx: &mut Context) -> JsResult<String> {
+    Ok(args
+        .first()
+        .ok_or_else(|| js_error!("args[0] id missing"))?
+        .to_string(ctx)
+        .map_err(|e| js_error!("{}", e))?
+        .to_std_string_lossy())
+}
+
+/// list() → JSON 字符串，DownloadedFile 数组，按接收时间从新到旧
+pub fn list(_this: &JsValue, _args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
+    if let Some(err) = plugin_permission_check(ctx, DOWNLOAD_PERMISSION.to_string()) {
+        return Err(err);
+    }
+
+    let json = serde_json::to_string(&download::list()).map_err(|e| js_error!("{}", e))?;
+    Ok(JsValue::String(JsString::from(json.as_str())))
+}
+
+/// read(id) → base64 编码的文件内容
+pub fn read(_this: &JsValue, args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
+    if let Some(err) = plugin_permission_check(ctx, DOWNLOAD_PERMISSION.to_string()) {
+        return Err(err);
+    }
+
+    let id = id_arg(args, ctx)?;
+    let data = download::read(&id).map_err(|e| js_error!("{:#}", e))?;
+    Ok(JsValue::String(JsString::from(
+        general_purpose::STANDARD.encode(data).as_str(),
+    )))
+}
+
+/// remove(id) → 是否存在并已删除
+pub fn remove(_this: &JsValue, args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
+    if let Some(err) = plugin_permission_check(ctx, DOWNLOAD_PERMISSION.to_string()) {
+        return Err(err);
+    }
+
+    let id = id_arg(args, ctx)?;
+    let removed = download::remove(&id).map_err(|e| js_error!("{:#}", e))?;
+    Ok(JsValue::Boolean(removed))
+}
+
+pub fn register_download(global: &mut ObjectInitializer) -> Result<(), String> {
+    let jsobj = ObjectInitializer::new(global.context())
+        .function(NativeFunction::from_fn_ptr(list), js_string!("list"), 0)
+        .function(NativeFunction::from_fn_ptr(read), js_string!("read"), 1)
+        .function(NativeFunction::from_fn_ptr(remove), js_string!("remove"), 1)
+        .build();
+
+    global.property(js_string!("download"), jsobj, Attribute::READONLY);
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use boa_engine::{
+    js_error, js_string, object::ObjectInitializer, property::Attribute, Context, JsResult,
+    JsString, JsValue, NativeFunction,
+};
+
+use crate::{
+    health::{self, HealthMetric, HealthQuery},
+    pluginsystem::utils::plugin_permission_check,
+};
+
+/// 读取本地健康数据库
+pub static HEALTH_PERMISSION: &str = "health";
+
+fn string_arg(args: &[JsValue], index: usize, name: &str, ctx: &mut Context) -> JsResult<String> {
+    Ok(args
+        .get(index)
+        .ok_or_else(|| js_error!("args[{}] {} missing", index, name))?
+        .to_string(ctx)
+        .map_err(|e| js_error!("{}", e))?
+        .to_std_string_lossy())
+}
+
+fn number_arg(args: &[JsValue], index: usize, name: &str, ctx: &mut Context) -> JsResult<i64> {
+    let value = args
+        .get(index)
+        .ok_or_else(|| js_error!("args[{}] {} missing", index, name))?
+        .to_number(ctx)
+        .map_err(|e| js_error!("{}", e))?;
+    Ok(value as i64)
+}
+
+/// 缺省、null、undefined 都视为未提供
+fn optional_arg(args: &[JsValue], index: usize) -> Option<&JsValue> {
+    args.get(index).filter(|v| !v.is_null_or_undefined())
+}
+
+fn json_result<T: serde::Serialize>(value: &T) -> JsResult<JsValue> {
+    let json = serde_json::to_string(value).map_err(|e| js_error!("{}", e))?;
+    Ok(JsValue::String(JsString::from(json.as_str())))
+}
+
+/// query(metric, from, to, addr?, limit?) → JSON 字符串，记录数组，按时间升序
+///
+/// metric 为 heart_rate / spo2 / steps / sleep / workouts，时间为 unix 秒，范围 [from, to)
+pub fn query(_this: &JsValue, args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
+    if let Some(err) = plugin_permission_check(ctx, HEALTH_PERMISSION.to_string()) {
+        return Err(err);
+    }
+
+    let name = string_arg(args, 0, "metric", ctx)?;
+    let metric =
+        HealthMetric::parse_name(&name).ok_or_else(|| js_error!("Unknown metric: {}", name))?;
+    let addr = match optional_arg(args, 3) {
+        Some(v) => Some(
+            v.to_string(ctx)
+                .map_err(|e| js_error!("{}", e))?
+                .to_std_string_lossy(),
+        ),
+        None => None,
+    };
+    let limit = match optional_arg(args, 4) {
+        Some(v) => Some(v.to_u32(ctx).map_err(|e| js_error!("{}", e))?),
+        None => None,
+    };
+
+    let q = HealthQuery {
+        metric,
+        addr,
+        from: number_arg(args, 1, "from", ctx)?,
+        to: number_arg(args, 2, "to", ctx)?,
+        limit,
+    };
+    let records = health::query(&q).map_err(|e| js_error!("{:#}", e))?;
+    json_result(&records)
+}
+
+/// getWorkout(addr, id) → JSON 字符串，含完整轨迹；不存在时为 "null"
+pub fn get_workout(_this: &JsValue, args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
+    if let Some(err) = plugin_permission_check(ctx, HEALTH_PERMISSION.to_string()) {
+        return Err(err);
+    }
+
+    let addr = string_arg(args, 0, "addr", ctx)?;
+    let id = string_arg(args, 1, "id", ctx)?;
+    let workout = health::get_workout(&addr, &id).map_err(|e| js_error!("{:#}", e))?;
+    json_result(&workout)
+}
+
+/// devices() → JSON 字符串，有数据的设备地址数组
+pub fn devices(_this: &JsValue, _args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
+    if let Some(err) = plugin_permission_check(ctx, HEALTH_PERMISSION.to_string()) {
+        return Err(err);
+    }
+
+    let devices = health::devices().map_err(|e| js_error!("{:#}", e))?;
+    json_result(&devices)
+}
+
+pub fn register_health(global: &mut ObjectInitializer) -> Result<(), String> {
+    let jsobj = ObjectInitializer::new(global.context())
+        .function(NativeFunction::from_fn_ptr(query), js_string!("query"), 5)
+        .function(
+            NativeFunction::from_fn_ptr(get_workout),
+            js_string!("getWorkout"),
+            2,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(devices),
+            js_string!("devices"),
+            0,
+        )
+        .build();
+
+    global.property(js_string!("health"), jsobj, Attribute::READONLY);
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use boa_engine::{js_error, js_string, object::ObjectInitializer, property::Attribute, Context, JsError, JsNativeError, JsResult, JsValue, NativeFunction};
 use std::future::Future;
 
-use crate::pluginsystem::utils::plugin_permission_check;
+use crate::pluginsystem::utils::{optional_device_addr, plugin_permission_check};
 
 pub static INTERCONNECT_PERMISSION: &str = "interconnect";
 
@@ -10,7 +10,7 @@ pub fn send(
     args: &[JsValue],
     ctx: &mut Context,
 ) -> impl Future<Output = JsResult<JsValue>> {
-    let parsed: Result<(String, String), JsError> = (|| {
+    let parsed: Result<(String, String, Option<String>), JsError> = (|| {
         let pkgname_arg = args
             .get(0)
             .ok_or_else(|| js_error!("sendQAICMessage args[0] pkgName missing"))?;
@@ -27,7 +27,9 @@ pub fn send(
             .map_err(|e| js_error!("{}", e))?
             .to_std_string_lossy();
 
-        Ok((pkgname, data))
+        let addr = optional_device_addr(args, 2, ctx)?;
+
+        Ok((pkgname, data, addr))
     })();
 
     let permission_result = plugin_permission_check(ctx, INTERCONNECT_PERMISSION.to_string());
@@ -38,8 +40,8 @@ pub fn send(
             return Err(err);
         }
 
-        let (pkgname, data) = parsed?;
-        let res = crate::miwear::with_connected_device_async(async |dev| {
+        let (pkgname, data, addr) = parsed?;
+        let res = crate::miwear::with_device_async(addr.as_deref(), async |dev| {
             crate::miwear::device::thirdpartyapp::send_inter_packet(dev, &pkgname, &data).await?;
             anyhow::Ok(())
         })
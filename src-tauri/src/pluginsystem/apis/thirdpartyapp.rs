@@ -2,7 +2,7 @@ use std::{future::{ready, Future}, pin::Pin, str::FromStr};
 
 use boa_engine::{js_error, js_string, object::ObjectInitializer, property::Attribute, Context, JsResult, JsString, JsValue, NativeFunction};
 
-use crate::{miwear::device::thirdpartyapp::AppInfo, pluginsystem::utils::plugin_permission_check};
+use crate::{miwear::device::thirdpartyapp::AppInfo, pluginsystem::utils::{optional_device_addr, plugin_permission_check}};
 
 pub static THIRD_PARTY_APP_PERMISSION: &str = "thirdpartyapp";
 
@@ -26,13 +26,16 @@ pub fn launch(
         None => Err(js_error!("launchQA args[1] pageName missing")),
     };
 
+    let addr_res = optional_device_addr(args, 2, ctx);
+
     let info_res: Result<AppInfo, boa_engine::JsError> = app_res
         .and_then(|app| serde_json::from_str(&app).map_err(|e| js_error!("{}", e)));
 
     Box::pin(async move {
         let appinfo = info_res?;
         let pagename = page_res?;
-        crate::miwear::with_connected_device_async(async move |dev| {
+        let addr = addr_res?;
+        crate::miwear::with_device_async(addr.as_deref(), async move |dev| {
             crate::miwear::device::thirdpartyapp::launch_app(dev, appinfo, &pagename).await?;
             anyhow::Ok(())
         })
@@ -45,11 +48,12 @@ pub fn launch(
 
 pub fn get_thirdparty_app_list(
     _this: &JsValue,
-    _args: &[JsValue],
+    args: &[JsValue],
     _ctx: &mut Context,
 ) -> impl Future<Output = JsResult<JsValue>> {
 
     let permission_result = plugin_permission_check(_ctx, THIRD_PARTY_APP_PERMISSION.to_string());
+    let addr_res = optional_device_addr(args, 0, _ctx);
 
     async move {
 
@@ -57,7 +61,8 @@ pub fn get_thirdparty_app_list(
             return Err(err);
         }
 
-        let list = crate::miwear::with_connected_device_async(|dev| async move {
+        let addr = addr_res?;
+        let list = crate::miwear::with_device_async(addr.as_deref(), |dev| async move {
             anyhow::Ok(crate::miwear::device::thirdpartyapp::get_app_list(dev).await?)
         })
         .await
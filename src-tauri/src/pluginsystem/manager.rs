@@ -94,6 +94,7 @@ impl PluginManager {
         match self.plugins.get_mut(name) {
             Some(plug) => {
                 plug.js_context = boa_engine::Context::default();
+                plug.js_env_data.release_channel_listeners();
                 plug.js_env_data = JsEnvData::default();
                 plug.state.disabled = true;
 
@@ -118,7 +119,8 @@ impl PluginManager {
             }
         };
         match self.plugins.remove(name) {
-            Some(_) => {
+            Some(mut plug) => {
+                plug.js_env_data.release_channel_listeners();
                 match fs::remove_dir_all(dir) {
                     Ok(_) => true,
                     Err(e) => {
@@ -1,4 +1,8 @@
-use crate::{community::provider::ProgressData, pluginsystem::apis::models::PluginUINode};
+use crate::{
+    community::provider::ProgressData,
+    miwear::channels::{HandlerId, PayloadKind},
+    pluginsystem::apis::models::PluginUINode,
+};
 use super::manifest::PluginManifest;
 use anyhow::{bail, Context, Result};
 use base64::{engine::general_purpose, Engine};
@@ -102,5 +106,17 @@ pub struct JsEnvData {
     pub intervals: HashMap<u32, (JsFunction, u64)>,
     pub settings_ui: Vec<PluginUINode>,
     pub registered_functions: HashMap<String, JsFunction>,
-    pub registered_progress_channels: HashMap<String, Channel<ProgressData>>
+    pub registered_progress_channels: HashMap<String, Channel<ProgressData>>,
+    /// AstroBox.channel 订阅：(通道, 负载类型) → (回调, 注册表中的 HandlerId)
+    pub channel_listeners:
+        HashMap<(crate::miwear::packet::Channel, PayloadKind), (JsFunction, HandlerId)>,
+}
+
+impl JsEnvData {
+    /// 插件被禁用或移除时注销其在通道注册表中的订阅
+    pub fn release_channel_listeners(&mut self) {
+        for (_, (_, id)) in self.channel_listeners.drain() {
+            crate::miwear::channels::unsubscribe(id);
+        }
+    }
 }
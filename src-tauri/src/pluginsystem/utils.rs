@@ -1,4 +1,4 @@
-use boa_engine::{js_error, js_string, Context, JsError};
+use boa_engine::{js_error, js_string, Context, JsError, JsResult, JsValue};
 
 use crate::pluginsystem::apis;
 
@@ -11,6 +11,18 @@ pub fn get_plugin_name(ctx: &mut Context) -> String {
         .unwrap_or_else(|| "unknown".into())
 }
 
+/// 读取可选的目标设备地址参数，未传入或为 null / undefined 时返回 None（即 primary 设备）
+pub fn optional_device_addr(args: &[JsValue], index: usize, ctx: &mut Context) -> JsResult<Option<String>> {
+    match args.get(index) {
+        Some(v) if !v.is_null_or_undefined() => Ok(Some(
+            v.to_string(ctx)
+                .map_err(|e| js_error!("{}", e))?
+                .to_std_string_lossy(),
+        )),
+        _ => Ok(None),
+    }
+}
+
 #[cfg(debug_assertions)]
 pub fn is_debug_version() -> bool {
     return true;
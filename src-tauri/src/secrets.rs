@@ -0,0 +1,380 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{collections::HashMap, path::PathBuf};
+use tauri::{AppHandle, Manager};
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// 加密存储的敏感数据（设备 authkey、会话密钥、账号 token 等）
+///
+/// 数据以 AES-256-GCM 加密后写入 secrets.json，密钥有两种来源：
+/// - keyfile：同目录下随机生成的 secrets.key。密钥与密文放在一起，
+///   只能避免明文落盘，能读取该目录的人同样能解密
+/// - passphrase：由用户口令经 PBKDF2-HMAC-SHA256 派生，启动后需要先解锁
+static SECRETS: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+static STORE: Lazy<RwLock<Option<Store>>> = Lazy::new(|| RwLock::new(None));
+
+struct Store {
+    path: PathBuf,
+    key_path: PathBuf,
+    /// None 表示口令模式下尚未解锁
+    key: Option<[u8; KEY_LEN]>,
+    kdf: Option<KdfParams>,
+    /// 未解锁时保留的密文，解锁时使用
+    sealed: Option<SealedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KdfParams {
+    algorithm: String,
+    salt: String,
+    iterations: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SealedFile {
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf: Option<KdfParams>,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretsMode {
+    Keyfile,
+    Passphrase,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretsStatus {
+    pub mode: SecretsMode,
+    pub locked: bool,
+}
+
+pub async fn init(app: &AppHandle) -> Result<()> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .context("app_config_dir unavailable; did you call after app was ready?")?;
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let key_path = dir.join("secrets.key");
+    let path = dir.join("secrets.json");
+
+    let sealed: Option<SealedFile> = if path.exists() {
+        let json = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("read secrets: {}", path.display()))?;
+        Some(serde_json::from_str(&json)?)
+    } else {
+        None
+    };
+
+    let store = match sealed {
+        Some(sealed) if sealed.kdf.is_some() => {
+            log::info!("[Secrets] Passphrase protected, waiting for unlock");
+            Store {
+                path,
+                key_path,
+                key: None,
+                kdf: sealed.kdf.clone(),
+                sealed: Some(sealed),
+            }
+        }
+        sealed => {
+            let key = load_or_create_key(&key_path)?;
+            if let Some(sealed) = &sealed {
+                *SECRETS.write() = open(&key, sealed)?;
+            }
+            Store {
+                path,
+                key_path,
+                key: Some(key),
+                kdf: None,
+                sealed: None,
+            }
+        }
+    };
+
+    *STORE.write() = Some(store);
+    Ok(())
+}
+
+/// 已初始化且已解锁
+pub fn is_ready() -> bool {
+    STORE
+        .read()
+        .as_ref()
+        .is_some_and(|store| store.key.is_some())
+}
+
+pub fn status() -> Result<SecretsStatus> {
+    let store = STORE.read();
+    let store = store.as_ref().context("secrets store not initialized")?;
+    Ok(SecretsStatus {
+        mode: match store.kdf {
+            Some(_) => SecretsMode::Passphrase,
+            None => SecretsMode::Keyfile,
+        },
+        locked: store.key.is_none(),
+    })
+}
+
+/// 用口令解锁；口令错误时返回错误且保持锁定
+pub async fn unlock(passphrase: String) -> Result<()> {
+    let (kdf, sealed) = {
+        let store = STORE.read();
+        let store = store.as_ref().context("secrets store not initialized")?;
+        if store.key.is_some() {
+            return Ok(());
+        }
+        (
+            store
+                .kdf
+                .clone()
+                .context("secrets store is not passphrase protected")?,
+            store.sealed.clone().context("secrets file missing")?,
+        )
+    };
+
+    let key = tokio::task::spawn_blocking(move || derive_key(&passphrase, &kdf)).await??;
+    let secrets = open(&key, &sealed).map_err(|_| anyhow!("Wrong passphrase"))?;
+
+    *SECRETS.write() = secrets;
+    if let Some(store) = STORE.write().as_mut() {
+        store.key = Some(key);
+        store.sealed = None;
+    }
+    log::info!("[Secrets] Unlocked");
+    Ok(())
+}
+
+/// 设置 / 更换口令；传入 None 时改回 keyfile 模式，需要先解锁
+pub async fn set_passphrase(passphrase: Option<String>) -> Result<()> {
+    if !is_ready() {
+        bail!("secrets store is locked");
+    }
+
+    let (key, kdf) = match passphrase {
+        Some(passphrase) => {
+            if passphrase.is_empty() {
+                bail!("passphrase must not be empty");
+            }
+            let kdf = KdfParams {
+                algorithm: "pbkdf2-sha256".to_string(),
+                salt: BASE64.encode(crate::tools::generate_random_bytes(SALT_LEN)),
+                iterations: PBKDF2_ITERATIONS,
+            };
+            let params = kdf.clone();
+            let key =
+                tokio::task::spawn_blocking(move || derive_key(&passphrase, &params)).await??;
+            (key, Some(kdf))
+        }
+        None => (random_key(), None),
+    };
+
+    let key_path = {
+        let mut store = STORE.write();
+        let store = store.as_mut().context("secrets store not initialized")?;
+        store.key = Some(key);
+        store.kdf = kdf.clone();
+        store.key_path.clone()
+    };
+    persist(&SECRETS.read().clone())?;
+
+    let mode = if kdf.is_some() {
+        "passphrase"
+    } else {
+        "keyfile"
+    };
+    // 先写好新的密文再处理 keyfile，避免中途失败后无法解密
+    match kdf {
+        Some(_) => {
+            if key_path.exists() {
+                std::fs::remove_file(&key_path)
+                    .with_context(|| format!("remove key: {}", key_path.display()))?;
+            }
+        }
+        None => write_key(&key_path, &key)?,
+    }
+
+    log::info!("[Secrets] Switched to {} mode", mode);
+    Ok(())
+}
+
+pub fn read<R>(f: impl FnOnce(&HashMap<String, String>) -> R) -> R {
+    let guard = SECRETS.read();
+    f(&guard)
+}
+
+pub fn write<R>(f: impl FnOnce(&mut HashMap<String, String>) -> R) -> Result<R> {
+    if !is_ready() {
+        bail!("secrets store is locked");
+    }
+
+    let mut guard = SECRETS.write();
+    let before = guard.clone();
+    let result = f(&mut guard);
+    // 内容没有变化时不重写文件
+    if *guard == before {
+        return Ok(result);
+    }
+    let snapshot = guard.clone();
+    drop(guard);
+
+    persist(&snapshot)?;
+    Ok(result)
+}
+
+pub fn get(name: &str) -> Option<String> {
+    read(|s| s.get(name).cloned())
+}
+
+pub fn set(name: &str, value: String) -> Result<()> {
+    write(|s| {
+        s.insert(name.to_string(), value);
+    })
+}
+
+pub fn remove(name: &str) -> Result<()> {
+    write(|s| {
+        s.remove(name);
+    })
+}
+
+/// 读取 JSON 序列化的条目
+pub fn get_json<T: serde::de::DeserializeOwned>(name: &str) -> Option<T> {
+    let json = get(name)?;
+    match serde_json::from_str(&json) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            log::warn!("[Secrets] Entry {} is corrupted: {}", name, e);
+            None
+        }
+    }
+}
+
+pub fn set_json<T: Serialize>(name: &str, value: &T) -> Result<()> {
+    set(name, serde_json::to_string(value)?)
+}
+
+/// 设备相关条目的键名：`device/<addr>/<item>`
+pub fn device_key(addr: &str, item: &str) -> String {
+    format!("device/{}/{}", addr, item)
+}
+
+/// 账号相关条目的键名：`account/<provider>/<id>`
+pub fn account_key(provider: &str, id: &str) -> String {
+    format!("account/{}/{}", provider, id)
+}
+
+fn persist(secrets: &HashMap<String, String>) -> Result<()> {
+    let store = STORE.read();
+    let store = store
+        .as_ref()
+        .context("secrets store not initialized; did you forget to call init()?")?;
+    let key = store.key.as_ref().context("secrets store is locked")?;
+
+    let mut sealed = seal(key, secrets)?;
+    sealed.kdf = store.kdf.clone();
+    crate::tools::write_file_atomic(&store.path, serde_json::to_string_pretty(&sealed)?)
+        .with_context(|| format!("write secrets: {}", store.path.display()))?;
+
+    Ok(())
+}
+
+fn seal(key: &[u8; KEY_LEN], secrets: &HashMap<String, String>) -> Result<SealedFile> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| anyhow!("{}", e))?;
+    let nonce = crate::tools::generate_random_bytes(NONCE_LEN);
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            serde_json::to_vec(secrets)?.as_slice(),
+        )
+        .map_err(|_| anyhow!("failed to encrypt secrets"))?;
+
+    Ok(SealedFile {
+        version: 1,
+        kdf: None,
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn open(key: &[u8; KEY_LEN], sealed: &SealedFile) -> Result<HashMap<String, String>> {
+    if sealed.version != 1 {
+        bail!("unsupported secrets file version {}", sealed.version);
+    }
+
+    let nonce = BASE64.decode(&sealed.nonce)?;
+    if nonce.len() != NONCE_LEN {
+        bail!("secrets nonce length mismatch");
+    }
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| anyhow!("{}", e))?;
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            BASE64.decode(&sealed.ciphertext)?.as_slice(),
+        )
+        .map_err(|_| anyhow!("failed to decrypt secrets, wrong key?"))?;
+
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+fn derive_key(passphrase: &str, kdf: &KdfParams) -> Result<[u8; KEY_LEN]> {
+    if kdf.algorithm != "pbkdf2-sha256" {
+        bail!("unsupported key derivation {}", kdf.algorithm);
+    }
+    let salt = BASE64.decode(&kdf.salt)?;
+    Ok(pbkdf2::pbkdf2_hmac_array::<Sha256, KEY_LEN>(
+        passphrase.as_bytes(),
+        &salt,
+        kdf.iterations,
+    ))
+}
+
+fn random_key() -> [u8; KEY_LEN] {
+    crate::tools::generate_random_bytes(KEY_LEN)
+        .try_into()
+        .unwrap()
+}
+
+fn load_or_create_key(path: &PathBuf) -> Result<[u8; KEY_LEN]> {
+    if path.exists() {
+        let data = std::fs::read(path).with_context(|| format!("read key: {}", path.display()))?;
+        return data
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("secrets key file {} is corrupted", path.display()));
+    }
+
+    let key = random_key();
+    write_key(path, &key)?;
+    Ok(key)
+}
+
+fn write_key(path: &PathBuf, key: &[u8; KEY_LEN]) -> Result<()> {
+    crate::tools::write_file_atomic(path, key)
+        .with_context(|| format!("write key: {}", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    Ok(())
+}
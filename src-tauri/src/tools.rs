@@ -75,6 +75,19 @@ pub fn calc_crc32_bytes(data: &[u8]) -> Vec<u8> {
     crc_value.to_be_bytes().to_vec()
 }
 
+/// 先写入同目录的临时文件再 rename 覆盖，写到一半崩溃时不会留下截断的文件
+pub fn write_file_atomic(path: &std::path::Path, data: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = std::path::PathBuf::from(tmp);
+    {
+        let mut file = std::fs::File::create(&tmp)?;
+        std::io::Write::write_all(&mut file, data.as_ref())?;
+        file.sync_all()?;
+    }
+    std::fs::rename(&tmp, path)
+}
+
 /// 使用系统默认浏览器打开指定 URL
 pub fn open_url_with_default_browser(url: String) -> anyhow::Result<()> {
     let handle = crate::APP_HANDLE
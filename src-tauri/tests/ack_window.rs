@@ -0,0 +1,27 @@
+//! ACK 窗口：无等待者的帧过期后迟到的 ACK 不计为协议错误
+
+use std::time::Duration;
+
+use app_lib::miwear::command_pool::ack_window::AckWindow;
+
+#[tokio::test]
+async fn late_ack_for_expired_untracked_frame_is_ignored() {
+    let window = AckWindow::new(1);
+    let slot = window.reserve(Duration::from_millis(10)).await.unwrap();
+    let _rx = window.register(1, slot);
+    window.track(2);
+
+    // 窗口已满，reserve 超时后清理过期的无等待者帧
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(window.reserve(Duration::from_millis(10)).await.is_err());
+    assert_eq!(window.stats().in_flight, vec![1]);
+
+    assert_eq!(window.ack(2).unwrap(), None);
+    assert_eq!(window.stats().protocol_errors, 0);
+
+    // 从未发出过的 seq 依旧是协议错误
+    assert!(window.ack(3).is_err());
+    assert_eq!(window.stats().protocol_errors, 1);
+
+    assert!(window.ack(1).unwrap().is_some());
+}
@@ -0,0 +1,74 @@
+//! 接收路径：畸形帧与链路故障只记日志丢弃，不会让接收任务 panic
+
+use std::sync::Arc;
+
+use app_lib::miwear::{
+    btrecv::{handle_bt_packet, handle_miwear_packet},
+    device::MiWearDevice,
+    packet::{Channel, MiWearPacket, OpCode, PktType},
+    transport::{
+        loopback::{self, LoopbackPeer, LoopbackTransport},
+        Transport,
+    },
+};
+
+async fn connect(addr: &str) -> (Arc<MiWearDevice>, Arc<LoopbackTransport>, LoopbackPeer) {
+    let (transport, peer) = loopback::pair("BTRecv", addr, 244);
+    let device = MiWearDevice::connect_with_transport(transport.clone(), "BTRecv".to_string())
+        .await
+        .unwrap();
+    (device, transport, peer)
+}
+
+fn ack(seq: u8) -> Vec<u8> {
+    MiWearPacket {
+        pkt_type: PktType::Ack,
+        seq,
+        body: vec![],
+    }
+    .encode()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn malformed_frames_are_dropped_without_ack() {
+    let (device, _transport, peer) = connect("B7:00:00:00:00:01").await;
+
+    // 未知 opcode、缺少 channel/opcode 的 Data 帧
+    for body in [vec![Channel::FileSensor as u8, 0x7F, 0x00], vec![0x01]] {
+        let pkt = MiWearPacket {
+            pkt_type: PktType::Data,
+            seq: 0x10,
+            body,
+        };
+        handle_miwear_packet(device.clone(), pkt).await;
+    }
+
+    // 认证前收到的密文帧没有会话密钥可用
+    let encrypted = MiWearPacket::new_data(0x11, Channel::Pb, OpCode::Encrypted, &[0xAA; 16]);
+    handle_miwear_packet(device.clone(), encrypted).await;
+
+    assert!(peer.try_recv().await.is_none());
+
+    // 正常的帧依旧回 ACK
+    let plain = MiWearPacket::new_data(0x12, Channel::FileSensor, OpCode::Plain, &[0x01]);
+    handle_miwear_packet(device.clone(), plain).await;
+    assert_eq!(peer.try_recv().await, Some(ack(0x12)));
+
+    device.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn link_failure_while_replying_is_logged() {
+    let (device, transport, peer) = connect("B7:00:00:00:00:02").await;
+    transport.disconnect().await.unwrap();
+
+    // ACK 与 SessionConfig 都写不出去
+    let plain = MiWearPacket::new_data(0x20, Channel::FileSensor, OpCode::Plain, &[0x01]);
+    handle_miwear_packet(device.clone(), plain).await;
+
+    handle_bt_packet(device.clone(), vec![0xBA, 0xDC, 0xFE, 0x01]).await;
+    assert!(!*device.hello_acked.borrow());
+
+    assert!(peer.try_recv().await.is_none());
+    let _ = device.disconnect().await;
+}
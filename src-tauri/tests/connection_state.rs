@@ -0,0 +1,44 @@
+//! 连接状态机：并行传输的 TransferGuard 按设备计数
+
+use app_lib::miwear::connection::{self, ConnectionState, TransferGuard};
+
+fn ready(addr: &str) {
+    for state in [
+        ConnectionState::Connecting,
+        ConnectionState::Hello,
+        ConnectionState::Authenticating,
+        ConnectionState::Ready,
+    ] {
+        connection::transition(addr, state).unwrap();
+    }
+}
+
+#[test]
+fn last_transfer_guard_restores_ready() {
+    let addr = "C0:00:00:00:00:01";
+    ready(addr);
+
+    let first = TransferGuard::begin(addr).unwrap();
+    let second = TransferGuard::begin(addr).unwrap();
+    assert_eq!(connection::current(addr), ConnectionState::Transferring);
+
+    drop(first);
+    assert_eq!(connection::current(addr), ConnectionState::Transferring);
+
+    drop(second);
+    assert_eq!(connection::current(addr), ConnectionState::Ready);
+}
+
+#[test]
+fn transfer_guard_keeps_disconnected_state() {
+    let addr = "C0:00:00:00:00:02";
+    ready(addr);
+
+    let guard = TransferGuard::begin(addr).unwrap();
+    connection::transition(addr, ConnectionState::Disconnected).unwrap();
+    drop(guard);
+    assert_eq!(connection::current(addr), ConnectionState::Disconnected);
+
+    // 未认证的设备不能开始传输
+    assert!(TransferGuard::begin(addr).is_err());
+}
@@ -0,0 +1,49 @@
+//! 回环传输层：建立 → 发送 → 接收 → 断开
+
+use std::sync::Arc;
+
+use app_lib::miwear::transport::{loopback, Transport};
+use tokio::sync::mpsc;
+
+#[tokio::test]
+async fn loopback_connect_send_receive() {
+    let (transport, peer) = loopback::pair("Loopback", "11:22:33:44:55:66", 244);
+    assert_eq!(transport.name(), "Loopback");
+    assert_eq!(transport.address(), "11:22:33:44:55:66");
+    assert_eq!(transport.max_frame_size(), 244);
+    assert!(peer.is_connected());
+
+    // 订阅之前注入的数据没有接收方
+    assert!(peer.inject(vec![0x00]).is_err());
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    transport
+        .subscribe(Arc::new(move |data| {
+            let _ = tx.send(data);
+        }))
+        .await
+        .unwrap();
+
+    // App → 设备
+    transport.send(vec![0xBA, 0xDC, 0xFE, 0x01]).await.unwrap();
+    transport.send(vec![0xA5, 0xA5]).await.unwrap();
+    assert_eq!(peer.recv().await.unwrap(), vec![0xBA, 0xDC, 0xFE, 0x01]);
+    assert_eq!(peer.recv().await.unwrap(), vec![0xA5, 0xA5]);
+    assert!(peer.try_recv().await.is_none());
+
+    // 设备 → App
+    peer.inject(vec![0x01, 0x02, 0x03]).unwrap();
+    assert_eq!(rx.recv().await.unwrap().unwrap(), vec![0x01, 0x02, 0x03]);
+
+    peer.fail("link lost");
+    assert_eq!(rx.recv().await.unwrap().unwrap_err(), "link lost");
+
+    // 超过最大帧长的写入被拒绝，且不会到达对端
+    assert!(transport.send(vec![0; 245]).await.is_err());
+    assert!(peer.try_recv().await.is_none());
+
+    transport.disconnect().await.unwrap();
+    assert!(!peer.is_connected());
+    assert!(transport.send(vec![0x00]).await.is_err());
+    assert!(peer.inject(vec![0x00]).is_err());
+}
@@ -0,0 +1,56 @@
+//! Mass 续传记录：乱序 ACK 只沿连续前缀推进续传起点
+//!
+//! 未调用 resume::init 时记录只保存在内存中
+
+use app_lib::miwear::device::mass::resume;
+
+const MASS_ID: &[u8] = &[0xAB; 16];
+
+fn entry(addr: &str) -> Option<resume::ResumeEntry> {
+    resume::entries()
+        .into_iter()
+        .find(|e| e.device_addr == addr)
+}
+
+#[test]
+fn out_of_order_acks_advance_contiguous_prefix() {
+    let addr = "7E:00:00:00:00:01";
+    assert_eq!(resume::resume_point(addr, MASS_ID, 16, 4000, 1000, 4), 1);
+
+    resume::mark_acked(addr, MASS_ID, 2);
+    resume::mark_acked(addr, MASS_ID, 3);
+    let e = entry(addr).unwrap();
+    assert_eq!(e.next_part, 1);
+    assert_eq!(e.acked.iter().copied().collect::<Vec<_>>(), vec![2, 3]);
+
+    resume::mark_acked(addr, MASS_ID, 1);
+    let e = entry(addr).unwrap();
+    assert_eq!(e.next_part, 4);
+    assert!(e.acked.is_empty());
+    assert_eq!(resume::resume_point(addr, MASS_ID, 16, 4000, 1000, 4), 4);
+
+    // 重复与越界的 ACK 不影响记录
+    resume::mark_acked(addr, MASS_ID, 2);
+    resume::mark_acked(addr, MASS_ID, 9);
+    assert_eq!(entry(addr).unwrap().next_part, 4);
+
+    // 最后一片确认后记录被删除
+    resume::mark_acked(addr, MASS_ID, 4);
+    assert!(entry(addr).is_none());
+}
+
+#[test]
+fn layout_change_restarts_and_forgets_acked_parts() {
+    let addr = "7E:00:00:00:00:02";
+    assert_eq!(resume::resume_point(addr, MASS_ID, 16, 4000, 1000, 4), 1);
+    resume::mark_acked(addr, MASS_ID, 1);
+    resume::mark_acked(addr, MASS_ID, 3);
+
+    assert_eq!(resume::resume_point(addr, MASS_ID, 16, 4000, 500, 8), 1);
+    let e = entry(addr).unwrap();
+    assert_eq!(e.next_part, 1);
+    assert!(e.acked.is_empty());
+
+    resume::finish(addr, MASS_ID);
+    assert!(entry(addr).is_none());
+}
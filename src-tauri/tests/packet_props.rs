@@ -0,0 +1,256 @@
+//! MiWearPacket / MassPacket 编解码的性质测试
+//!
+//! 覆盖 encode ⇄ parse、parse_all 的部分结果、data_fields、流式解帧器，
+//! 以及 MassPacket::encode_with_crc32 ⇄ parse_mass_inner 的往返。
+
+use app_lib::miwear::{
+    device::mass::packet::{parse_mass_inner, CompressMode, MassDataType, MassPacket},
+    packet::{
+        Channel, FrameReassembler, MiWearPacket, OpCode, PacketError, PktType, HEADER_LEN, MAGIC,
+        MAX_BODY_LEN,
+    },
+};
+use proptest::prelude::*;
+
+fn pkt_type() -> impl Strategy<Value = PktType> {
+    prop_oneof![
+        Just(PktType::Ack),
+        Just(PktType::SessionConfig),
+        Just(PktType::Data),
+    ]
+}
+
+fn channel() -> impl Strategy<Value = Channel> {
+    (1u8..=9).prop_map(Channel::from)
+}
+
+fn opcode() -> impl Strategy<Value = OpCode> {
+    prop_oneof![Just(OpCode::Plain), Just(OpCode::Encrypted)]
+}
+
+fn packet() -> impl Strategy<Value = MiWearPacket> {
+    (
+        pkt_type(),
+        any::<u8>(),
+        prop::collection::vec(any::<u8>(), 0..512),
+    )
+        .prop_map(|(pkt_type, seq, body)| MiWearPacket {
+            pkt_type,
+            seq,
+            body,
+        })
+}
+
+/// 不含 magic 首字节的垃圾数据，避免误拼出合法帧
+fn junk() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(
+        any::<u8>().prop_filter("not magic", |b| *b != MAGIC[0]),
+        0..32,
+    )
+}
+
+fn mass_data_type() -> impl Strategy<Value = MassDataType> {
+    (0usize..4).prop_map(|i| {
+        [
+            MassDataType::WATCHFACE,
+            MassDataType::FIRMWARE,
+            MassDataType::NotificationIcon,
+            MassDataType::ThirdpartyApp,
+        ][i]
+    })
+}
+
+fn assert_same(a: &MiWearPacket, b: &MiWearPacket) {
+    assert_eq!(a.pkt_type, b.pkt_type);
+    assert_eq!(a.seq, b.seq);
+    assert_eq!(a.body, b.body);
+}
+
+proptest! {
+    #[test]
+    fn encode_parse_roundtrip(pkt in packet()) {
+        let raw = pkt.try_encode().unwrap();
+        prop_assert_eq!(raw.len(), HEADER_LEN + pkt.body.len());
+        let parsed = MiWearPacket::parse(&raw).unwrap();
+        assert_same(&pkt, &parsed);
+        prop_assert_eq!(parsed.encode(), raw);
+    }
+
+    #[test]
+    fn parse_never_panics(buf in prop::collection::vec(any::<u8>(), 0..1024)) {
+        if let Ok(pkt) = MiWearPacket::parse(&buf) {
+            // 成功解析的帧一定是 buf 的前缀
+            let raw = pkt.encode();
+            prop_assert_eq!(&buf[..raw.len()], &raw[..]);
+        }
+    }
+
+    #[test]
+    fn parse_all_never_panics(buf in prop::collection::vec(any::<u8>(), 0..2048)) {
+        let parsed = MiWearPacket::parse_all(&buf);
+        let framed: usize = parsed
+            .packets
+            .iter()
+            .map(|p| HEADER_LEN + p.body.len())
+            .sum();
+        prop_assert_eq!(framed + parsed.skipped_bytes, buf.len());
+    }
+
+    #[test]
+    fn parse_all_keeps_frames_around_junk(
+        frames in prop::collection::vec((junk(), packet()), 0..8),
+        trailing in junk(),
+    ) {
+        let mut buf = Vec::new();
+        for (gap, pkt) in &frames {
+            buf.extend_from_slice(gap);
+            buf.extend_from_slice(&pkt.encode());
+        }
+        buf.extend_from_slice(&trailing);
+
+        let parsed = MiWearPacket::parse_all(&buf);
+        prop_assert!(parsed.errors.is_empty());
+        prop_assert_eq!(parsed.packets.len(), frames.len());
+        for ((_, expected), actual) in frames.iter().zip(&parsed.packets) {
+            assert_same(expected, actual);
+        }
+        let junk_len: usize = frames.iter().map(|(j, _)| j.len()).sum::<usize>() + trailing.len();
+        prop_assert_eq!(parsed.skipped_bytes, junk_len);
+    }
+
+    #[test]
+    fn parse_all_reports_truncated_tail(
+        frames in prop::collection::vec(packet(), 1..6),
+        // 至少留下 magic，否则残帧会被当作垃圾数据
+        cut in 1usize..HEADER_LEN - 1,
+    ) {
+        let mut buf: Vec<u8> = frames.iter().flat_map(|p| p.encode()).collect();
+        let last_len = HEADER_LEN + frames.last().unwrap().body.len();
+        buf.truncate(buf.len() - cut.min(last_len));
+
+        let parsed = MiWearPacket::parse_all(&buf);
+        prop_assert_eq!(parsed.packets.len(), frames.len() - 1);
+        for (expected, actual) in frames.iter().zip(&parsed.packets) {
+            assert_same(expected, actual);
+        }
+        prop_assert!(matches!(
+            parsed.errors.first().map(|e| e.error),
+            Some(PacketError::IncompleteHeader { .. } | PacketError::IncompleteFrame { .. })
+        ));
+    }
+
+    #[test]
+    fn corrupted_body_is_rejected(pkt in packet(), flip in any::<prop::sample::Index>()) {
+        prop_assume!(!pkt.body.is_empty());
+        let mut raw = pkt.encode();
+        let i = HEADER_LEN + flip.index(pkt.body.len());
+        raw[i] ^= 0x01;
+        prop_assert!(
+            matches!(MiWearPacket::parse(&raw), Err(PacketError::CrcMismatch { .. })),
+            "CRC-16 must catch a single bit flip"
+        );
+    }
+
+    #[test]
+    fn data_fields_roundtrip(
+        seq in any::<u8>(),
+        channel in channel(),
+        op in opcode(),
+        payload in prop::collection::vec(any::<u8>(), 0..512),
+    ) {
+        let pkt = MiWearPacket::new_data(seq, channel, op, &payload);
+        let parsed = MiWearPacket::parse(&pkt.encode()).unwrap();
+        let fields = parsed.data_fields().unwrap();
+        prop_assert_eq!(fields.channel, channel);
+        prop_assert_eq!(fields.opcode, op);
+        prop_assert_eq!(fields.data, &payload[..]);
+    }
+
+    #[test]
+    fn data_fields_never_panics(pkt in packet()) {
+        if let Some(fields) = pkt.data_fields() {
+            prop_assert_eq!(pkt.pkt_type, PktType::Data);
+            prop_assert_eq!(fields.data.len() + 2, pkt.body.len());
+        }
+    }
+
+    #[test]
+    fn reassembler_handles_arbitrary_splits(
+        frames in prop::collection::vec((junk(), packet()), 0..8),
+        splits in prop::collection::vec(1usize..64, 0..32),
+    ) {
+        let mut buf = Vec::new();
+        for (gap, pkt) in &frames {
+            buf.extend_from_slice(gap);
+            buf.extend_from_slice(&pkt.encode());
+        }
+
+        // 按任意位置切成若干段依次喂入，模拟蓝牙通知的分片
+        let mut reassembler = FrameReassembler::new();
+        let mut decoded = Vec::new();
+        let mut rest = &buf[..];
+        for n in splits.into_iter().chain(std::iter::once(usize::MAX)) {
+            let (chunk, tail) = rest.split_at(n.min(rest.len()));
+            reassembler.push(chunk);
+            while let Some(pkt) = reassembler.next_packet() {
+                decoded.push(pkt);
+            }
+            rest = tail;
+        }
+
+        prop_assert_eq!(decoded.len(), frames.len());
+        for ((_, expected), actual) in frames.iter().zip(&decoded) {
+            assert_same(expected, actual);
+        }
+        prop_assert_eq!(reassembler.stats().crc_errors, 0);
+    }
+
+    #[test]
+    fn mass_inner_roundtrip(
+        data in prop::collection::vec(any::<u8>(), 0..4096),
+        data_type in mass_data_type(),
+        deflate in any::<bool>(),
+    ) {
+        let mode = if deflate { CompressMode::Deflate } else { CompressMode::None };
+        let mass = MassPacket::build_compressed(data.clone(), data_type, mode).unwrap();
+        let inner = mass.encode_with_crc32();
+        prop_assert_eq!(inner.len(), 1 + 1 + 16 + 4 + mass.original_file_data.len() + 4);
+
+        let received = parse_mass_inner(&inner, Some(&mass.md5)).unwrap();
+        prop_assert!(received.crc_ok);
+        prop_assert!(received.md5_ok);
+        prop_assert_eq!(received.data_type, data_type as u8);
+        prop_assert_eq!(received.compress_mode, mass.compress_mode as u8);
+        prop_assert_eq!(received.data, data);
+    }
+
+    #[test]
+    fn mass_inner_never_panics(inner in prop::collection::vec(any::<u8>(), 0..256)) {
+        let _ = parse_mass_inner(&inner, None);
+    }
+}
+
+#[test]
+fn oversized_body_is_not_encoded() {
+    let pkt = MiWearPacket {
+        pkt_type: PktType::Data,
+        seq: 0,
+        body: vec![0; MAX_BODY_LEN + 1],
+    };
+    assert_eq!(
+        pkt.try_encode(),
+        Err(PacketError::BodyTooLong {
+            len: MAX_BODY_LEN + 1
+        })
+    );
+}
+
+#[test]
+fn unknown_type_is_rejected() {
+    let mut raw = MiWearPacket::new_data(1, Channel::Pb, OpCode::Plain, b"hi").encode();
+    raw[2] = 0x0F;
+    assert_eq!(
+        MiWearPacket::parse(&raw).unwrap_err(),
+        PacketError::UnknownType { value: 0x0F }
+    );
+}
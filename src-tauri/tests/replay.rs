@@ -0,0 +1,111 @@
+//! 会话录制 → 回放：回放时 App 写出的帧必须与录制一致
+
+use std::time::Duration;
+
+use app_lib::miwear::{
+    replay::{self, ReplayOptions, ReplaySpeed, SessionRecording, SessionReplayer},
+    simulator::{SimulatorConfig, WatchSimulator},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+fn options() -> ReplayOptions {
+    ReplayOptions {
+        speed: ReplaySpeed::Instant,
+        ..Default::default()
+    }
+}
+
+/// 对软件手表录制一次 hello + 认证
+async fn record_session(addr: &str) -> (String, SessionRecording) {
+    let config = SimulatorConfig {
+        addr: addr.to_string(),
+        ..Default::default()
+    };
+    let authkey = config.authkey.clone();
+    let path = replay::start_recording(&config.name, addr, config.max_frame_size).unwrap();
+
+    let (device, sim) = WatchSimulator::connect(config).await.unwrap();
+    device.start_hello().await.unwrap();
+    tokio::time::timeout(TIMEOUT, device.start_auth(authkey.clone()))
+        .await
+        .expect("auth timed out")
+        .unwrap();
+    assert!(sim.is_authenticated());
+
+    assert_eq!(replay::stop_recording(addr), Some(path.clone()));
+    device.disconnect().await.unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    // 会话文件只记录是否复用了缓存密钥
+    assert!(text.contains("\"type\":\"cached_session\",\"used\":false"));
+    assert!(!text.contains("enc_key"));
+    (authkey, SessionRecording::parse(&text).unwrap())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replay_matches_recorded_tx_frames() {
+    let (authkey, recording) = record_session("AE:00:00:00:00:01").await;
+
+    let (device, replayer) = SessionReplayer::connect(recording, options())
+        .await
+        .unwrap();
+    device.start_hello().await.unwrap();
+    tokio::time::timeout(TIMEOUT, device.start_auth(authkey))
+        .await
+        .expect("auth timed out")
+        .unwrap();
+
+    let report = replayer.finish().await.unwrap();
+    assert!(report.rx_injected > 0);
+    assert!(report.tx_matched > 0);
+
+    device.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replay_reports_tx_mismatch() {
+    let (authkey, mut recording) = record_session("AE:00:00:00:00:02").await;
+
+    // 把录制的 App 随机数换掉，认证请求与录制不再一致
+    for event in &mut recording.events {
+        if let replay::SessionEvent::Input {
+            input: replay::SessionInput::AuthNonce { data },
+            ..
+        } = event
+        {
+            *data = "00".repeat(16);
+        }
+    }
+
+    let (device, replayer) = SessionReplayer::connect(recording, options())
+        .await
+        .unwrap();
+    device.start_hello().await.unwrap();
+    let _ = tokio::time::timeout(TIMEOUT, device.start_auth(authkey)).await;
+
+    let err = replayer.finish().await.unwrap_err();
+    assert!(format!("{:#}", err).contains("tx mismatch"));
+
+    let _ = device.disconnect().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cached_session_keys_are_required_for_replay() {
+    let (_, mut recording) = record_session("AE:00:00:00:00:03").await;
+    for event in &mut recording.events {
+        if let replay::SessionEvent::Input {
+            input: replay::SessionInput::CachedSession { used },
+            ..
+        } = event
+        {
+            *used = true;
+        }
+    }
+
+    let err = SessionReplayer::connect(recording, options())
+        .await
+        .err()
+        .expect("replay without session keys must fail");
+    assert!(format!("{:#}", err).contains("session_keys"));
+}
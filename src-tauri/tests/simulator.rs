@@ -0,0 +1,153 @@
+//! 在回环链路上对软件手表跑完整流程：hello → 认证 → 安装
+
+use std::{sync::Arc, time::Duration};
+
+use app_lib::{
+    miwear::{
+        device::{
+            mass::packet::MassDataType, thirdpartyapp::install_app_data,
+            watchface::install_watchface_data, MiWearDevice,
+        },
+        error::MiWearError,
+        simulator::{SimulatorConfig, WatchSimulator},
+    },
+    pb::protocol::PrepareStatus,
+};
+use tokio_util::sync::CancellationToken;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 每个用例使用独立地址，避免共享的连接状态表互相干扰
+fn config(addr: &str) -> SimulatorConfig {
+    SimulatorConfig {
+        addr: addr.to_string(),
+        ..Default::default()
+    }
+}
+
+async fn connect_and_auth(config: SimulatorConfig) -> (Arc<MiWearDevice>, Arc<WatchSimulator>) {
+    let authkey = config.authkey.clone();
+    let (device, sim) = WatchSimulator::connect(config).await.unwrap();
+    device.start_hello().await.unwrap();
+    tokio::time::timeout(TIMEOUT, device.start_auth(authkey))
+        .await
+        .expect("auth timed out")
+        .unwrap();
+    assert!(sim.is_authenticated());
+    (device, sim)
+}
+
+fn sample_data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 + i / 251) as u8).collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn install_watchface_end_to_end() {
+    let (device, sim) = connect_and_auth(config("5E:00:00:00:00:01")).await;
+
+    // 跨越多个 Mass 分片
+    let data = sample_data(5000);
+    tokio::time::timeout(
+        TIMEOUT,
+        install_watchface_data(
+            device.clone(),
+            data.clone(),
+            &"1234567890".to_string(),
+            CancellationToken::new(),
+            |_| {},
+        ),
+    )
+    .await
+    .expect("install timed out")
+    .unwrap();
+
+    let received = sim.received_files();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].data_type, MassDataType::WATCHFACE as u8);
+    assert!(received[0].crc_ok);
+    assert!(received[0].md5_ok);
+    assert_eq!(received[0].data, data);
+
+    device.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn install_app_end_to_end() {
+    let (device, sim) = connect_and_auth(config("5E:00:00:00:00:02")).await;
+
+    let data = sample_data(3000);
+    tokio::time::timeout(
+        TIMEOUT,
+        install_app_data(
+            device.clone(),
+            data.clone(),
+            &"com.example.quickapp".to_string(),
+            1,
+            CancellationToken::new(),
+            |_| {},
+        ),
+    )
+    .await
+    .expect("install timed out")
+    .unwrap();
+
+    let received = sim.received_files();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].data_type, MassDataType::ThirdpartyApp as u8);
+    assert!(received[0].md5_ok);
+    assert_eq!(received[0].data, data);
+
+    device.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn install_rejected_on_low_storage() {
+    let mut config = config("5E:00:00:00:00:03");
+    config.watchface_prepare_status = PrepareStatus::LowStorage;
+    let (device, sim) = connect_and_auth(config).await;
+
+    let err = tokio::time::timeout(
+        TIMEOUT,
+        install_watchface_data(
+            device.clone(),
+            sample_data(2000),
+            &"1234567890".to_string(),
+            CancellationToken::new(),
+            |_| {},
+        ),
+    )
+    .await
+    .expect("install timed out")
+    .unwrap_err();
+
+    match err.downcast_ref::<MiWearError>() {
+        Some(MiWearError::PrepareRejected { status }) => {
+            assert_eq!(*status, PrepareStatus::LowStorage as i32)
+        }
+        other => panic!("expected PrepareRejected, got {:?}", other),
+    }
+    assert!(sim.received_files().is_empty());
+
+    device.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn auth_fails_on_wrong_device_signature() {
+    let mut config = config("5E:00:00:00:00:04");
+    config.fail_auth = true;
+    let authkey = config.authkey.clone();
+    let (device, sim) = WatchSimulator::connect(config).await.unwrap();
+    device.start_hello().await.unwrap();
+
+    let err = tokio::time::timeout(TIMEOUT, device.start_auth(authkey))
+        .await
+        .expect("auth timed out")
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<MiWearError>(),
+        Some(MiWearError::Auth { .. })
+    ));
+    assert!(!sim.is_authenticated());
+
+    device.disconnect().await.unwrap();
+}
@@ -95,7 +95,7 @@ export default function DeviceConnectCard({
                                     invoke("miwear_remove_device",{addr: device.addr}).finally(() => {onComplete?.()})
                                 }} icon={<DeleteRegular/>}>{t('device.actions.delete')}</MenuItem>
                                 <MenuItem disabled={!connected} onClick={() => {
-                                    invoke("miwear_disconnect").finally(() => {onComplete?.()})
+                                    invoke("miwear_disconnect", {addr: device.addr}).finally(() => {onComplete?.()})
                                 }} icon={<PlugDisconnectedRegular/>}>{t('device.actions.disconnect')}</MenuItem>
                                 <QrMenu device={device} />
                             </MenuList>
@@ -0,0 +1,71 @@
+import { useI18n } from "@/i18n";
+import useToast, { makeError, makeSuceess } from "@/layout/toast";
+import {
+    Button,
+    Dialog,
+    DialogActions,
+    DialogBody,
+    DialogContent,
+    DialogSurface,
+    DialogTitle,
+    Field,
+    Input
+} from "@fluentui/react-components";
+import { invoke } from "@tauri-apps/api/core";
+import { useState } from "react";
+
+interface SecretsPassphraseDialogProps {
+    open: boolean;
+    onClose: () => void;
+    // 当前是否已经设置了口令
+    protectedByPassphrase: boolean;
+    onChanged: () => void;
+}
+
+export default function SecretsPassphraseDialog({ open, onClose, protectedByPassphrase, onChanged }: SecretsPassphraseDialogProps) {
+    const { t } = useI18n();
+    const { dispatchToast } = useToast();
+    const [passphrase, setPassphrase] = useState("");
+    const [confirm, setConfirm] = useState("");
+    const [running, setRunning] = useState(false);
+
+    const apply = (value: string | null) => {
+        setRunning(true);
+        invoke("secrets_set_passphrase", { passphrase: value })
+            .then(() => {
+                makeSuceess(dispatchToast, t(value ? "secrets.passphraseSet" : "secrets.passphraseRemoved"));
+                onClose();
+                setPassphrase("");
+                setConfirm("");
+                onChanged();
+            })
+            .catch((e) => makeError(dispatchToast, String(e)))
+            .finally(() => setRunning(false));
+    };
+
+    const mismatch = confirm !== "" && confirm !== passphrase;
+
+    return <Dialog open={open} onOpenChange={(_, data) => { if (!data.open) onClose(); }}>
+        <DialogSurface>
+            <DialogBody>
+                <DialogTitle>{t("secrets.passphraseTitle")}</DialogTitle>
+                <DialogContent style={{ display: "flex", flexDirection: "column", gap: 8 }}>
+                    <Field label={t("secrets.newPassphrase")}>
+                        <Input type="password" value={passphrase} onChange={(_, data) => setPassphrase(data.value)} />
+                    </Field>
+                    <Field label={t("secrets.confirmPassphrase")}
+                        validationMessage={mismatch ? t("secrets.mismatch") : undefined}>
+                        <Input type="password" value={confirm} onChange={(_, data) => setConfirm(data.value)} />
+                    </Field>
+                </DialogContent>
+                <DialogActions>
+                    <Button appearance="secondary" onClick={onClose}>{t("secrets.cancel")}</Button>
+                    {protectedByPassphrase &&
+                        <Button appearance="secondary" disabled={running} onClick={() => apply(null)}>{t("secrets.removePassphrase")}</Button>}
+                    <Button appearance="primary" disabled={running || !passphrase || passphrase !== confirm}
+                        onClick={() => apply(passphrase)}>{t("secrets.save")}</Button>
+                </DialogActions>
+            </DialogBody>
+        </DialogSurface>
+    </Dialog>
+}
@@ -0,0 +1,62 @@
+import { useI18n } from "@/i18n";
+import {
+    Button,
+    Dialog,
+    DialogActions,
+    DialogBody,
+    DialogContent,
+    DialogSurface,
+    DialogTitle,
+    Field,
+    Input
+} from "@fluentui/react-components";
+import { invoke } from "@tauri-apps/api/core";
+import { useEffect, useState } from "react";
+
+export interface SecretsStatus {
+    mode: "keyfile" | "passphrase";
+    locked: boolean;
+}
+
+// 口令模式下启动时解锁 secrets，否则设备 authkey 与账号 token 不可用
+export default function SecretsUnlockDialog() {
+    const { t } = useI18n();
+    const [open, setOpen] = useState(false);
+    const [passphrase, setPassphrase] = useState("");
+    const [error, setError] = useState("");
+    const [running, setRunning] = useState(false);
+
+    useEffect(() => {
+        invoke<SecretsStatus>("secrets_status")
+            .then((status) => setOpen(status.locked))
+            .catch(() => { });
+    }, []);
+
+    const unlock = () => {
+        setRunning(true);
+        setError("");
+        invoke("secrets_unlock", { passphrase })
+            .then(() => setOpen(false))
+            .catch((e) => setError(String(e)))
+            .finally(() => setRunning(false));
+    };
+
+    return <Dialog open={open} onOpenChange={(_, data) => setOpen(data.open)}>
+        <DialogSurface>
+            <DialogBody>
+                <DialogTitle>{t("secrets.unlockTitle")}</DialogTitle>
+                <DialogContent>
+                    <Field label={t("secrets.passphrase")} validationMessage={error || undefined}>
+                        <Input type="password" value={passphrase}
+                            onChange={(_, data) => setPassphrase(data.value)}
+                            onKeyDown={(e) => { if (e.key === "Enter" && passphrase) unlock(); }} />
+                    </Field>
+                </DialogContent>
+                <DialogActions>
+                    <Button appearance="secondary" onClick={() => setOpen(false)}>{t("secrets.later")}</Button>
+                    <Button appearance="primary" disabled={!passphrase || running} onClick={unlock}>{t("secrets.unlock")}</Button>
+                </DialogActions>
+            </DialogBody>
+        </DialogSurface>
+    </Dialog>
+}
@@ -1,12 +1,11 @@
 import logger from "@/log/logger";
-import { sleep } from "@/tools/common";
+import { errorMessage } from "@/types/MiWearError";
 import { invoke } from "@tauri-apps/api/core";
 
 export default async function connect(address: string,name:string,authKey:string) {
     await tryTryBreak(invoke, "connect.failed")('miwear_connect', { addr: address , name })
     logger.info("链接成功")
     await tryTryBreak(invoke, "connect.handshakeFailed")('miwear_start_hello');
-    await sleep(500)
     logger.info("握手成功")
     await tryTryBreak(invoke, "connect.authFailed")('miwear_start_auth', { authKey })
     logger.info("Auth成功")
@@ -23,7 +22,7 @@ function tryTryBreak<T extends (...args: any[]) => any>(
             return await func(...args);
         } catch (error) {
             logger.error(error);
-            const detail = error instanceof Error ? error.message : String(error);
+            const detail = errorMessage(error);
             throw new Error(`${errormsg}:${detail}`);
         }
     };
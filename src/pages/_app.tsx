@@ -33,6 +33,7 @@ import { initOpenResourceListener } from "@/listeners/resources";
 import logger from "@/log/logger";
 import Head from "next/head";
 import BoardcastDialog, { BoardcastInfo } from "@/components/BoardcastDialog/BoardcastDialog";
+import SecretsUnlockDialog from "@/components/SecretsDialog/SecretsUnlockDialog";
 
 const makeFluentTheme = (mode: 'light' | 'dark') => {
   return mode === 'light' ? webLightTheme : webDarkTheme;
@@ -231,6 +232,7 @@ function App({ Component, pageProps }: AppProps) {
             <AddDeviceFromQr />
             <DragToPush />
             <DisclaimerDialog defaultOpen={disclaimerOpen} />
+            <SecretsUnlockDialog />
             <UpdateDialog
               open={showUpdateDialog}
               onClose={() => setShowUpdateDialog(false)}
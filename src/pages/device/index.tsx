@@ -10,6 +10,7 @@ import useToast, { makeError } from "@/layout/toast";
 import { formatBytes } from "@/tools/common";
 import { timeToRead } from "@/tools/time";
 import { BatteryStatus, ChargeStatus, MiWearState } from "@/types/bluetooth";
+import { CONNECTION_STATE_EVENT, ConnectionStateEvent } from "@/types/ConnectionState";
 import { Button, Caption1, Caption2, InfoLabel, Label, Spinner, Title1 } from "@fluentui/react-components";
 import {
     AppsRegular,
@@ -78,6 +79,10 @@ export default function Device() {
         const listeners = [
             listen("device-connected", updateDeviceState),
             listen("device-disconnected", updateDeviceState),
+            // 认证完成后 authkey / 加密信息才会写入设备状态
+            listen<ConnectionStateEvent>(CONNECTION_STATE_EVENT, (e) => {
+                if (e.payload.to === "ready") updateDeviceState();
+            }),
         ]
         return () => listeners.forEach(e => e.then(e => e()))
     }, [])
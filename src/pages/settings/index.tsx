@@ -5,6 +5,8 @@ import CardButton from "@/components/CardButton/CardButton";
 import { DisclaimerDialog } from "@/components/disclaimer/disclamierDialog";
 import LoginDialog from "@/components/LoginDialog/LoginDialog";
 import DropdownCard from "@/components/settings/dropdownCard";
+import SecretsPassphraseDialog from "@/components/SecretsDialog/SecretsPassphraseDialog";
+import { SecretsStatus } from "@/components/SecretsDialog/SecretsUnlockDialog";
 import SettingsCard from "@/components/settings/settingsCard";
 import SliderCard from "@/components/settings/sliderCard";
 import SwitchCard from "@/components/settings/switchCard";
@@ -73,6 +75,7 @@ export default function SettingsPage() {
     return (<BasePage title={t('settings.title')}>
         <AnimatePresence>
             <AccountSettings />
+            <SecuritySettings />
             <InternelSettings />
             {ready && <><SourceSettings />
                 <ListSettings />
@@ -242,6 +245,28 @@ function AccountSettings() {
     </SettingsGroup>
 }
 
+function SecuritySettings() {
+    const { t } = useI18n();
+    const [open, setOpen] = useState(false)
+    const [status, setStatus] = useState<SecretsStatus>()
+    const refresh = () => {
+        invoke<SecretsStatus>("secrets_status").then(setStatus)
+    }
+    useEffect(refresh, [])
+    const protectedByPassphrase = status?.mode === "passphrase"
+    return <SettingsGroup title={t('settings.security.title')}>
+        <CardButton
+            icon={LockClosedKeyRegular}
+            content={t('settings.security.passphrase')}
+            secondaryContent={protectedByPassphrase ? t('settings.security.passphraseOn') : t('settings.security.passphraseOff')}
+            onClick={() => setOpen(true)}
+            disabled={!status || status.locked}
+        />
+        <SecretsPassphraseDialog open={open} onClose={() => setOpen(false)}
+            protectedByPassphrase={protectedByPassphrase} onChanged={refresh} />
+    </SettingsGroup>
+}
+
 function InstallSettings() {
     const { t } = useI18n();
     return <SettingsGroup title={t('settings.install.title')}>
@@ -1,5 +1,6 @@
 import { ResourceType } from "@/device/install";
 import logger from "@/log/logger";
+import { errorMessage } from "@/types/MiWearError";
 import { invoke } from "@tauri-apps/api/core";
 
 export interface TaskItem {
@@ -96,7 +97,7 @@ export class TaskList {
                         status: "error",
                         progress: item.payload.progress,
                         url: item.payload.url,
-                        progressDesc: e instanceof Error ? e.message : e,
+                        progressDesc: errorMessage(e),
                     }
                 })
             }
@@ -114,6 +115,8 @@ export class TaskList {
     stop() {
         if (this.status == "running") {
             this.status = "stopping"
+            // 中止正在传输的安装，而不是等它传完
+            if (this.type === "install") invoke("miwear_cancel_operation").catch(() => {})
             this.items = [...this.items]
             this.notify()
         }
@@ -0,0 +1,20 @@
+// 与 src-tauri/src/miwear/connection.rs 中 ConnectionState 的序列化格式对应
+export type ConnectionState =
+    | "disconnected"
+    | "connecting"
+    | "hello"
+    | "authenticating"
+    | "ready"
+    | "transferring"
+    | "reconnecting"
+    | "failed";
+
+// "device-connection-state" 事件的 payload
+export interface ConnectionStateEvent {
+    addr: string;
+    from: ConnectionState;
+    to: ConnectionState;
+    reason?: string | null;
+}
+
+export const CONNECTION_STATE_EVENT = "device-connection-state";
@@ -0,0 +1,39 @@
+// 与 src-tauri/src/miwear/error.rs 中 MiWearError 的序列化格式对应
+export type MiWearErrorKind =
+    | "not_connected"
+    | "transport"
+    | "timeout"
+    | "protocol"
+    | "crc"
+    | "auth"
+    | "prepare_rejected"
+    | "cancelled"
+    | "mass_abandoned"
+    | "other";
+
+export interface MiWearError {
+    kind: MiWearErrorKind;
+    message: string;
+    // timeout
+    operation?: string;
+    // crc
+    expected?: number;
+    actual?: number;
+    // prepare_rejected
+    status?: number;
+    status_name?: string;
+    // mass_abandoned
+    sent_parts?: number;
+    total_parts?: number;
+}
+
+export function isMiWearError(e: unknown): e is MiWearError {
+    return typeof e === "object" && e !== null && "kind" in e && "message" in e;
+}
+
+// 统一取出 invoke 抛出的错误信息，兼容 Error / MiWearError / 字符串
+export function errorMessage(e: unknown): string {
+    if (e instanceof Error) return e.message;
+    if (isMiWearError(e)) return e.message;
+    return String(e);
+}